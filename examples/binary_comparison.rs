@@ -63,6 +63,7 @@ fn main() {
     println!("   $ der run hello_der.der");
     print!("   ");
     let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
     executor.execute().unwrap();
     
     // 4. Key differences
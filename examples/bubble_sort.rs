@@ -85,6 +85,7 @@ fn main() {
     println!("\nExecuting program:");
     println!("-".repeat(40));
     let mut executor = Executor::new(program.clone());
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(_) => println!("\nProgram executed successfully"),
         Err(e) => eprintln!("Execution error: {}", e),
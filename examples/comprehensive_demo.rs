@@ -56,6 +56,7 @@ fn demo_ai_with_verification() {
             
             // Execute
             let mut executor = Executor::new(program.clone());
+            executor.grant_capability(Capability::IO);
             match executor.execute() {
                 Ok(result) => println!("\nExecution Result: {}", result.to_string()),
                 Err(e) => println!("\nExecution Error: {}", e),
@@ -91,35 +92,42 @@ fn demo_memory_management() {
     let one_idx = program.constants.add_int(1);
     let one = Node::new(OpCode::ConstInt, 4).with_args(&[one_idx]);
     
-    // Load, add 1, store (3 times)
+    // Load, add 1, store (3 times). Each load names the previous store as
+    // its second argument so the stores run, and run in order.
     let mut next_id = 5;
-    let mut last_store = 3; // Start with alloc node
-    
-    for i in 0..3 {
-        let load = Node::new(OpCode::Load, next_id).with_args(&[3]); // Always load from original alloc
+    let mut last_store = None;
+    
+    for _ in 0..3 {
+        let load_args = match last_store {
+            Some(store) => vec![3, store],
+            None => vec![3],
+        };
+        let load = Node::new(OpCode::Load, next_id).with_args(&load_args);
         let add = Node::new(OpCode::Add, next_id + 1).with_args(&[next_id, 4]);
         let store = Node::new(OpCode::Store, next_id + 2).with_args(&[3, next_id + 1]);
         
         program.add_node(load);
         program.add_node(add);
-        last_store = program.add_node(store) as u32;
+        program.add_node(store);
+        last_store = Some(next_id + 2);
         next_id += 3;
     }
     
-    // Final load
-    let final_load = Node::new(OpCode::Load, next_id).with_args(&[3]);
+    // Final load, after the last store
+    let final_load = Node::new(OpCode::Load, next_id).with_args(&[3, next_id - 1]);
     
     // Add all nodes
     program.add_node(size);
     program.add_node(init);
     program.add_node(alloc);
     program.add_node(one);
-    let result = program.add_node(final_load);
-    program.set_entry_point(result);
+    program.add_node(final_load);
+    program.set_entry_point(next_id);
     
     println!("Program: Allocate counter, increment 3 times");
     
     let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(result) => {
             println!("Final counter value: {}", result.to_string());
@@ -185,6 +193,7 @@ fn demo_async_operations() {
     println!("  Async 2: Computing sum(1..10) = 55");
     
     let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(result) => println!("\nCombined result: {}", result.to_string()),
         Err(e) => println!("Execution error: {}", e),
@@ -241,8 +250,12 @@ fn demo_comprehensive_program() {
         // Double it
         let double = Node::new(OpCode::Mul, next_id + 2).with_args(&[next_id + 1, 10]);
         
-        // Load accumulator
-        let load = Node::new(OpCode::Load, next_id + 3).with_args(&[9]);
+        // Load accumulator, after the previous iteration's store
+        let load = if i == 0 {
+            Node::new(OpCode::Load, next_id + 3).with_args(&[9])
+        } else {
+            Node::new(OpCode::Load, next_id + 3).with_args(&[9, next_id - 1])
+        };
         
         // Add to accumulator
         let add = Node::new(OpCode::Add, next_id + 4).with_args(&[next_id + 3, next_id + 2]);
@@ -261,9 +274,9 @@ fn demo_comprehensive_program() {
     }
     
     // Final load of accumulator
-    let final_load = Node::new(OpCode::Load, next_id).with_args(&[9]);
-    let result = program.add_node(final_load);
-    program.set_entry_point(result);
+    let final_load = Node::new(OpCode::Load, next_id).with_args(&[9, next_id - 1]);
+    program.add_node(final_load);
+    program.set_entry_point(next_id);
     
     // Add metadata
     program.metadata.traits.push(Trait {
@@ -288,6 +301,7 @@ fn demo_comprehensive_program() {
     println!("  Operation: Double each and sum");
     
     let mut executor = Executor::new(program.clone());
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(result) => {
             println!("  Result: {}", result.to_string());
@@ -40,6 +40,7 @@ fn demo_ai_generation() {
             
             // Execute the program
             let mut executor = Executor::new(program);
+            executor.grant_capability(Capability::IO);
             match executor.execute() {
                 Ok(result) => println!("\nExecution result: {}", result.to_string()),
                 Err(e) => println!("\nExecution error: {}", e),
@@ -101,6 +102,7 @@ fn demo_manual_construction() {
     
     // Execute
     let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(result) => println!("\nFibonacci(5) = {}", result.to_string()),
         Err(e) => println!("\nExecution error: {}", e),
@@ -152,6 +154,7 @@ fn demo_visualization() {
     
     // Execute to verify
     let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(result) => println!("\nResult: {}", result.to_string()),
         Err(e) => println!("\nExecution error: {}", e),
@@ -26,6 +26,7 @@ fn main() {
     // Execute directly
     println!("Executing Hello World program:");
     let mut executor = Executor::new(program.clone());
+    executor.grant_capability(Capability::IO);
     match executor.execute() {
         Ok(_) => println!("Program executed successfully"),
         Err(e) => eprintln!("Execution error: {}", e),
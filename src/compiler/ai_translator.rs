@@ -141,6 +141,7 @@ impl AICodeGenerator {
         // Phase 4: AI-Generated Verification
         // The AI generates proofs of correctness for the generated graph
         self.generate_correctness_proofs()?;
+        self.program.declare_node_capabilities();
         
         Ok(self.program.clone())
     }
@@ -354,6 +355,10 @@ impl AICodeGenerator {
                     // AI links to previous computation nodes
                     Node::new(OpCode::Add, node_id).with_args(&[node_id - 2, node_id - 1])
                 }
+                OpCode::Neg => {
+                    // Negates the previous computation without a zero constant
+                    Node::new(OpCode::Neg, node_id).with_args(&[node_id - 1])
+                }
                 OpCode::Print => {
                     // AI determines what to print
                     Node::new(OpCode::Print, node_id).with_args(&[node_id - 1])
@@ -1,8 +1,20 @@
 use std::io::{Read, Write, Result};
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::verification::Proof;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 pub const DER_MAGIC: [u8; 4] = [0x44, 0x45, 0x52, 0x21]; // "DER!"
-pub const VERSION: u16 = 0x0100; // Version 1.0
+pub const VERSION: u16 = 0x0101; // Version 1.1
+/// Version 1.0 files have no node count in the IMPL chunk
+pub const VERSION_1_0: u16 = 0x0100;
+
+/// Bytes per node in the IMPL chunk: opcode, flags, result_id, timestamp,
+/// arg_count and three args, written field by field without padding
+pub const NODE_RECORD_SIZE: usize = 2 + 2 + 4 + 8 + 1 + 4 * 3;
+
+/// Cast targets, named by string or by their index in this list
+pub const CAST_TARGETS: [&str; 4] = ["int", "float", "string", "bool"];
 
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
@@ -78,16 +90,36 @@ impl Node {
     pub fn has_flag(&self, flag: NodeFlag) -> bool {
         self.flags & (flag as u16) != 0
     }
+
+    /// Result ids this node reads. Constant args index the constant pool and
+    /// a DefineFunc body only runs when called, so neither counts.
+    pub fn dependencies(&self) -> Vec<u32> {
+        match OpCode::try_from(self.opcode) {
+            Ok(OpCode::ConstInt) | Ok(OpCode::ConstFloat) | Ok(OpCode::ConstString) |
+            Ok(OpCode::ConstBool) | Ok(OpCode::DefineFunc) => Vec::new(),
+            _ => self.args[..(self.arg_count as usize).min(3)]
+                .iter()
+                .copied()
+                .filter(|id| *id != 0)
+                .collect(),
+        }
+    }
 }
 
 #[repr(u16)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OpCode {
     // Control Flow
     Nop = 0x0000,
     Return = 0x0001,
     Call = 0x0002,
     Branch = 0x0003,
+    Loop = 0x0004,
+    Exit = 0x0005,         // Stop the program with an Int status
+    Assert = 0x0006,       // Condition, plus an optional message string
+    Try = 0x0007,          // Protected node, then a handler run if it fails
+    Throw = 0x0008,        // Raise a user error; non-string values are stringified
+    Seq = 0x0009,          // Evaluate 2-3 arguments left to right, returning the last
     
     // Arithmetic
     Add = 0x0100,
@@ -95,6 +127,20 @@ pub enum OpCode {
     Mul = 0x0102,
     Div = 0x0103,
     Mod = 0x0104,
+    BitAnd = 0x0105,
+    BitOr = 0x0106,
+    BitXor = 0x0107,
+    BitNot = 0x0108,
+    Shl = 0x0109,          // Shift counts must be in 0..=63; bits shifted out are lost
+    Shr = 0x010A,          // Arithmetic shift: the sign bit is kept
+    Sqrt = 0x010B,         // Always a float; negative inputs are an error
+    Pow = 0x010C,          // Int ** non-negative Int stays an integer
+    Abs = 0x010D,
+    Floor = 0x010E,
+    Ceil = 0x010F,
+    Min = 0x0110,
+    Max = 0x0111,
+    Neg = 0x0112,          // Keeps Int vs Float; negating i64::MIN is an overflow error
     
     // Comparison
     Eq = 0x0200,
@@ -111,11 +157,13 @@ pub enum OpCode {
     Xor = 0x0303,
     
     // Memory
-    Load = 0x0400,
+    Load = 0x0400,         // Optional second argument runs first and is discarded
     Store = 0x0401,
     Alloc = 0x0402,
     Free = 0x0403,
-    LoadArg = 0x0404,
+    LoadArg = 0x0404,      // Optional second argument is the default for a missing slot
+    PtrAdd = 0x0405,       // Memory reference moved by a slot count
+    ArgCount = 0x0406,     // Number of program arguments, as set by set_argc
     
     // Constants
     ConstInt = 0x0500,
@@ -130,18 +178,35 @@ pub enum OpCode {
     ArraySet = 0x0603,
     MapGet = 0x0604,
     MapSet = 0x0605,
+    ArrayLen = 0x0606,     // Arrays only; use StrLen for strings
+    ArrayPush = 0x0607,    // Returns a new array; the input value is unchanged
+    ArrayPop = 0x0608,     // Returns a new array without the last element
+    ArraySlice = 0x0609,   // Elements [start, end) as a new array
+    ArrayConcat = 0x060A,
+    MapContains = 0x060B,
+    MapKeys = 0x060C,      // Keys in sorted order
+    MapValues = 0x060D,    // Values in key order
+    MapDelete = 0x060E,    // Returns the map without the key; missing keys are ignored
     
     // Functions
     DefineFunc = 0x0700,
     CreateClosure = 0x0701,
+    LoadParam = 0x0702,    // Parameter of the current call, counted from 0
     
     // Type Operations
-    Cast = 0x0800,
+    Cast = 0x0800,         // Target is a name from CAST_TARGETS or its index
     TypeOf = 0x0801,
     
     // IO Operations
     Print = 0x0900,
     Read = 0x0901,
+    Random = 0x0902,       // No args: Float in [0, 1); two Int args: Int in [low, high)
+    Now = 0x0903,          // Unix time in milliseconds
+    Sleep = 0x0904,        // Milliseconds, plus an optional AsyncBegin handle to complete instead of blocking
+    FileRead = 0x0905,     // Path -> contents
+    FileWrite = 0x0906,    // Path, contents -> Nil
+    GetEnv = 0x0907,       // Name -> value, or Nil when unset
+    HttpGet = 0x0908,      // URL -> [status, body]; plain http only
     
     // UI Operations (for future visualization)
     UICreateElement = 0x0A00,
@@ -152,11 +217,41 @@ pub enum OpCode {
     AsyncBegin = 0x0B00,
     AsyncAwait = 0x0B01,
     AsyncComplete = 0x0B02,
+    AsyncTimeout = 0x0B03, // Await a handle for at most the given milliseconds
+    AsyncJoin = 0x0B04,    // Await up to three handles into an array of results
+    
+    // String Operations
+    StrConcat = 0x0C00,    // Joins the to_string forms of two values; Nil is rejected
+    StrLen = 0x0C01,       // Length in characters, not bytes
+    StrSlice = 0x0C02,     // Characters [start, end) of a string
+    Concat = 0x0C03,       // Two strings or two arrays
+    Len = 0x0C04,          // String characters, or array/map elements
+    ParseInt = 0x0C05,     // Surrounding whitespace is ignored
+    ParseFloat = 0x0C06,
+    Format = 0x0C07,       // Value and a spec such as "{:.2}", "{:05}" or "{:x}"
     
     // External Calls (FXI)
     ExternalCall = 0x0F00,
 }
 
+impl OpCode {
+    /// Capability an execution must be granted before this opcode runs.
+    /// Memory, arithmetic and control flow need none.
+    pub fn required_capability(self) -> Option<Capability> {
+        match self {
+            OpCode::Print | OpCode::Read => Some(Capability::IO),
+            OpCode::FileRead | OpCode::FileWrite => Some(Capability::FileSystem),
+            OpCode::GetEnv => Some(Capability::Process),
+            OpCode::HttpGet => Some(Capability::Network),
+            OpCode::Random => Some(Capability::Entropy),
+            OpCode::Now | OpCode::Sleep => Some(Capability::Clock),
+            OpCode::UICreateElement | OpCode::UISetAttribute | OpCode::UIAppendChild => Some(Capability::UI),
+            OpCode::ExternalCall => Some(Capability::ExternalCode),
+            _ => None,
+        }
+    }
+}
+
 #[repr(u16)]
 #[derive(Debug, Clone, Copy)]
 pub enum NodeFlag {
@@ -167,9 +262,10 @@ pub enum NodeFlag {
     IsTerminal = 0x0010,
     IsEntryPoint = 0x0020,
     RequiresProof = 0x0040,
+    Volatile = 0x0080,     // Never reuse a memoized result; re-execute on every use
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ConstantPool {
     pub integers: Vec<i64>,
     pub floats: Vec<f64>,
@@ -234,6 +330,8 @@ pub struct Program {
     pub nodes: Vec<Node>,
     pub constants: ConstantPool,
     pub metadata: ProgramMetadata,
+    /// Proofs carried in the PROF chunk
+    pub proofs: Vec<Proof>,
 }
 
 #[derive(Clone)]
@@ -243,16 +341,37 @@ pub struct ProgramMetadata {
     pub traits: Vec<Trait>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Capability {
     FileSystem,
     Network,
     Process,
     UI,
     ExternalCode,
+    IO,
+    Entropy,
+    Clock,
+}
+
+impl Capability {
+    /// Parse the name a capability is written with in policies and on the
+    /// command line, which is its variant name.
+    pub fn from_name(name: &str) -> Option<Capability> {
+        match name {
+            "FileSystem" => Some(Capability::FileSystem),
+            "Network" => Some(Capability::Network),
+            "Process" => Some(Capability::Process),
+            "UI" => Some(Capability::UI),
+            "ExternalCode" => Some(Capability::ExternalCode),
+            "IO" => Some(Capability::IO),
+            "Entropy" => Some(Capability::Entropy),
+            "Clock" => Some(Capability::Clock),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trait {
     pub name: String,
     pub preconditions: Vec<String>,
@@ -270,6 +389,7 @@ impl Program {
                 required_capabilities: Vec::new(),
                 traits: Vec::new(),
             },
+            proofs: Vec::new(),
         }
     }
 
@@ -283,9 +403,64 @@ impl Program {
         self.metadata.entry_point = node_id;
     }
 
+    /// Position in `nodes` of the node producing `result_id`.
+    pub fn node_index(&self, result_id: u32) -> Option<usize> {
+        self.nodes.iter().position(|n| n.result_id == result_id)
+    }
+
     pub fn require_capability(&mut self, cap: Capability) {
         if !self.metadata.required_capabilities.contains(&cap) {
             self.metadata.required_capabilities.push(cap);
         }
     }
+
+    /// SHA-256 over the program's canonical layout: the entry point, each
+    /// node in order without its timestamp, and the constant pools. Programs
+    /// built the same way hash equally whenever they were built.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.metadata.entry_point.to_le_bytes());
+
+        hasher.update((self.nodes.len() as u32).to_le_bytes());
+        for node in &self.nodes {
+            hasher.update(node.opcode.to_le_bytes());
+            hasher.update(node.flags.to_le_bytes());
+            hasher.update(node.result_id.to_le_bytes());
+            hasher.update([node.arg_count]);
+            for arg in node.args {
+                hasher.update(arg.to_le_bytes());
+            }
+        }
+
+        let constants = &self.constants;
+        hasher.update((constants.integers.len() as u32).to_le_bytes());
+        for value in &constants.integers {
+            hasher.update(value.to_le_bytes());
+        }
+        hasher.update((constants.floats.len() as u32).to_le_bytes());
+        for value in &constants.floats {
+            hasher.update(value.to_bits().to_le_bytes());
+        }
+        hasher.update((constants.strings.len() as u32).to_le_bytes());
+        for value in &constants.strings {
+            hasher.update((value.len() as u32).to_le_bytes());
+            hasher.update(value.as_bytes());
+        }
+        hasher.update((constants.booleans.len() as u32).to_le_bytes());
+        for value in &constants.booleans {
+            hasher.update([*value as u8]);
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// Declare every capability the program's opcodes need to run.
+    pub fn declare_node_capabilities(&mut self) {
+        let needed: Vec<Capability> = self.nodes.iter()
+            .filter_map(|node| OpCode::try_from(node.opcode).ok()?.required_capability())
+            .collect();
+        for cap in needed {
+            self.require_capability(cap);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,172 @@
+use crate::core::{Node, NodeFlag, OpCode, Program};
+
+/// Handle to a node added through a `ProgramBuilder`. It can only be
+/// obtained from the builder, so it always names a node that exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// The node's `result_id` in the built program.
+    pub fn result_id(self) -> u32 {
+        self.0
+    }
+}
+
+/// Builds a `Program` node by node, allocating result ids in order from 1
+/// and wiring arguments from the handles of earlier nodes.
+pub struct ProgramBuilder {
+    program: Program,
+    next_id: u32,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> Self {
+        ProgramBuilder {
+            program: Program::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Add a node whose arguments are the results of `args`. At most three
+    /// arguments fit in a node; extra ones are dropped as in `Node::with_args`.
+    pub fn node(&mut self, opcode: OpCode, args: &[NodeId]) -> NodeId {
+        let raw: Vec<u32> = args.iter().map(|id| id.0).collect();
+        self.raw_node(opcode, &raw)
+    }
+
+    /// Add a node with raw arguments, for opcodes such as the constants whose
+    /// arguments are not result ids.
+    pub fn raw_node(&mut self, opcode: OpCode, args: &[u32]) -> NodeId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.program.add_node(Node::new(opcode, id).with_args(args));
+        NodeId(id)
+    }
+
+    /// Set `flag` on a node that was already added.
+    pub fn set_flag(&mut self, id: NodeId, flag: NodeFlag) {
+        if let Some(index) = self.program.node_index(id.0) {
+            self.program.nodes[index].set_flag(flag);
+        }
+    }
+
+    pub fn const_int(&mut self, value: i64) -> NodeId {
+        let index = self.program.constants.add_int(value);
+        self.raw_node(OpCode::ConstInt, &[index])
+    }
+
+    pub fn const_float(&mut self, value: f64) -> NodeId {
+        let index = self.program.constants.add_float(value);
+        self.raw_node(OpCode::ConstFloat, &[index])
+    }
+
+    pub fn const_string(&mut self, value: &str) -> NodeId {
+        let index = self.program.constants.add_string(value.to_string());
+        self.raw_node(OpCode::ConstString, &[index])
+    }
+
+    pub fn const_bool(&mut self, value: bool) -> NodeId {
+        let index = self.program.constants.add_bool(value);
+        self.raw_node(OpCode::ConstBool, &[index])
+    }
+
+    pub fn add(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Add, &[a, b])
+    }
+
+    pub fn sub(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Sub, &[a, b])
+    }
+
+    pub fn mul(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Mul, &[a, b])
+    }
+
+    pub fn div(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Div, &[a, b])
+    }
+
+    pub fn eq(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Eq, &[a, b])
+    }
+
+    pub fn lt(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Lt, &[a, b])
+    }
+
+    pub fn gt(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        self.node(OpCode::Gt, &[a, b])
+    }
+
+    pub fn not(&mut self, a: NodeId) -> NodeId {
+        self.node(OpCode::Not, &[a])
+    }
+
+    pub fn branch(&mut self, condition: NodeId, then: NodeId, otherwise: NodeId) -> NodeId {
+        self.node(OpCode::Branch, &[condition, then, otherwise])
+    }
+
+    pub fn load_arg(&mut self, index: NodeId) -> NodeId {
+        self.node(OpCode::LoadArg, &[index])
+    }
+
+    /// Like `load_arg`, producing `default` when the argument is missing.
+    pub fn load_arg_or(&mut self, index: NodeId, default: NodeId) -> NodeId {
+        self.node(OpCode::LoadArg, &[index, default])
+    }
+
+    pub fn arg_count(&mut self) -> NodeId {
+        self.node(OpCode::ArgCount, &[])
+    }
+
+    pub fn create_array(&mut self, elements: &[NodeId]) -> NodeId {
+        self.node(OpCode::CreateArray, elements)
+    }
+
+    pub fn array_get(&mut self, array: NodeId, index: NodeId) -> NodeId {
+        self.node(OpCode::ArrayGet, &[array, index])
+    }
+
+    pub fn array_set(&mut self, array: NodeId, index: NodeId, value: NodeId) -> NodeId {
+        self.node(OpCode::ArraySet, &[array, index, value])
+    }
+
+    pub fn array_len(&mut self, array: NodeId) -> NodeId {
+        self.node(OpCode::ArrayLen, &[array])
+    }
+
+    pub fn create_map(&mut self) -> NodeId {
+        self.node(OpCode::CreateMap, &[])
+    }
+
+    pub fn map_get(&mut self, map: NodeId, key: NodeId) -> NodeId {
+        self.node(OpCode::MapGet, &[map, key])
+    }
+
+    pub fn map_set(&mut self, map: NodeId, key: NodeId, value: NodeId) -> NodeId {
+        self.node(OpCode::MapSet, &[map, key, value])
+    }
+
+    pub fn print(&mut self, value: NodeId) -> NodeId {
+        self.node(OpCode::Print, &[value])
+    }
+
+    /// Run `steps` in order and produce the last one's value.
+    pub fn seq(&mut self, steps: &[NodeId]) -> NodeId {
+        self.node(OpCode::Seq, steps)
+    }
+
+    /// Finish the program with `entry` as its entry point, declaring the
+    /// capabilities its opcodes need.
+    pub fn build(mut self, entry: NodeId) -> Program {
+        self.program.set_entry_point(entry.0);
+        self.program.declare_node_capabilities();
+        self.program
+    }
+}
+
+impl Default for ProgramBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
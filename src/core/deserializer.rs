@@ -1,5 +1,6 @@
 use std::io::{Read, Result, Error, ErrorKind};
 use crate::core::binary_format::*;
+use crate::verification::Proof;
 use byteorder::{LittleEndian, ReadBytesExt};
 
 pub struct DERDeserializer<R: Read> {
@@ -56,11 +57,7 @@ impl<R: Read> DERDeserializer<R> {
             b"META" => self.read_metadata_chunk(program, chunk_header.size)?,
             b"IMPL" => self.read_impl_chunk(program, chunk_header.size)?,
             b"CNST" => self.read_const_chunk(program, chunk_header.size)?,
-            b"PROF" => {
-                // Skip proof chunks for now
-                let mut buffer = vec![0u8; chunk_header.size as usize];
-                self.reader.read_exact(&mut buffer)?;
-            }
+            b"PROF" => self.read_proof_chunk(program, chunk_header.size)?,
             _ => {
                 // Skip unknown chunks
                 let mut buffer = vec![0u8; chunk_header.size as usize];
@@ -105,6 +102,9 @@ impl<R: Read> DERDeserializer<R> {
                 3 => Capability::Process,
                 4 => Capability::UI,
                 5 => Capability::ExternalCode,
+                6 => Capability::IO,
+                7 => Capability::Entropy,
+                8 => Capability::Clock,
                 _ => continue,
             };
             program.metadata.required_capabilities.push(cap);
@@ -155,7 +155,19 @@ impl<R: Read> DERDeserializer<R> {
     }
 
     fn read_impl_chunk(&mut self, program: &mut Program, size: u32) -> Result<()> {
-        let node_count = size as usize / std::mem::size_of::<Node>();
+        let node_count = if program.header.version == VERSION_1_0 {
+            // 1.0 writers declared 32 bytes per node but wrote NODE_RECORD_SIZE
+            size as usize / 32
+        } else {
+            let count = self.reader.read_u32::<LittleEndian>()? as usize;
+            if 4 + count * NODE_RECORD_SIZE != size as usize {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("IMPL chunk of {} bytes cannot hold {} nodes", size, count),
+                ));
+            }
+            count
+        };
         
         for _ in 0..node_count {
             let node = self.read_node()?;
@@ -226,4 +238,23 @@ impl<R: Read> DERDeserializer<R> {
 
         Ok(())
     }
+
+    fn read_proof_chunk(&mut self, program: &mut Program, size: u32) -> Result<()> {
+        let mut buffer = vec![0u8; size as usize];
+        self.reader.read_exact(&mut buffer)?;
+        let mut cursor = std::io::Cursor::new(buffer);
+
+        // Each proof is a length-prefixed JSON document
+        let proof_count = cursor.read_u32::<LittleEndian>()?;
+        for _ in 0..proof_count {
+            let len = cursor.read_u32::<LittleEndian>()? as usize;
+            let mut bytes = vec![0u8; len];
+            cursor.read_exact(&mut bytes)?;
+            let proof: Proof = serde_json::from_slice(&bytes)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid proof: {}", e)))?;
+            program.proofs.push(proof);
+        }
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,112 @@
+//! JSON form of a whole program for tools that can't read the binary
+//! format. It carries the same data as a `.der` file, so converting in
+//! either direction and serializing gives identical bytes.
+
+use crate::core::binary_format::*;
+use crate::verification::Proof;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct ProgramJson {
+    header: HeaderJson,
+    metadata: MetadataJson,
+    constants: ConstantPool,
+    nodes: Vec<NodeJson>,
+    #[serde(default)]
+    proofs: Vec<Proof>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HeaderJson {
+    version: u16,
+    flags: u16,
+    chunk_count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataJson {
+    entry_point: u32,
+    required_capabilities: Vec<Capability>,
+    traits: Vec<Trait>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeJson {
+    opcode: u16,
+    /// Informational only; `opcode` is authoritative on import
+    #[serde(default)]
+    name: String,
+    flags: u16,
+    result_id: u32,
+    timestamp: u64,
+    args: Vec<u32>,
+}
+
+impl Program {
+    pub fn to_json(&self) -> String {
+        let header = self.header;
+        let document = ProgramJson {
+            header: HeaderJson {
+                version: header.version,
+                flags: header.flags,
+                chunk_count: header.chunk_count,
+            },
+            metadata: MetadataJson {
+                entry_point: self.metadata.entry_point,
+                required_capabilities: self.metadata.required_capabilities.clone(),
+                traits: self.metadata.traits.clone(),
+            },
+            constants: self.constants.clone(),
+            nodes: self.nodes.iter().map(|node| NodeJson {
+                opcode: node.opcode,
+                name: OpCode::try_from(node.opcode)
+                    .map(|op| format!("{:?}", op))
+                    .unwrap_or_else(|_| format!("Unknown({})", node.opcode)),
+                flags: node.flags,
+                result_id: node.result_id,
+                timestamp: node.timestamp,
+                args: node.args[..(node.arg_count as usize).min(3)].to_vec(),
+            }).collect(),
+            proofs: self.proofs.clone(),
+        };
+
+        // Every field is a plain number, string or derived type
+        serde_json::to_string_pretty(&document).expect("program JSON is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Program, serde_json::Error> {
+        let document: ProgramJson = serde_json::from_str(json)?;
+
+        let mut program = Program::new();
+        program.header.version = document.header.version;
+        program.header.flags = document.header.flags;
+        program.header.chunk_count = document.header.chunk_count;
+        program.metadata.entry_point = document.metadata.entry_point;
+        program.metadata.required_capabilities = document.metadata.required_capabilities;
+        program.metadata.traits = document.metadata.traits;
+        program.constants = document.constants;
+        program.proofs = document.proofs;
+
+        for node in document.nodes {
+            if node.args.len() > 3 {
+                return Err(serde::de::Error::custom(format!(
+                    "node {} has {} args; at most 3 are allowed",
+                    node.result_id,
+                    node.args.len()
+                )));
+            }
+            let mut args = [0; 3];
+            args[..node.args.len()].copy_from_slice(&node.args);
+            program.nodes.push(Node {
+                opcode: node.opcode,
+                flags: node.flags,
+                result_id: node.result_id,
+                timestamp: node.timestamp,
+                arg_count: node.args.len() as u8,
+                args,
+            });
+        }
+
+        Ok(program)
+    }
+}
@@ -1,9 +1,14 @@
 pub mod binary_format;
 pub mod serializer;
 pub mod deserializer;
+pub mod json_format;
 pub mod semantic_annotation;
+pub mod node_describer;
+pub mod builder;
 
 pub use binary_format::*;
 pub use serializer::*;
 pub use deserializer::*;
-pub use semantic_annotation::*;
\ No newline at end of file
+pub use semantic_annotation::*;
+pub use node_describer::*;
+pub use builder::*;
\ No newline at end of file
@@ -0,0 +1,204 @@
+use crate::core::{Node, OpCode, Program, SemanticDocument};
+use crate::types::Type;
+use std::collections::HashMap;
+
+/// Structured description of a single node.
+///
+/// Fields hold raw text; escaping and truncation are left to the renderer
+/// that displays them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDescription {
+    /// Opcode name, e.g. `Add` or `Unknown(4660)`
+    pub short_label: String,
+    /// Human-readable description of what the node does
+    pub detail: String,
+    /// Constant value for Const* nodes, strings in double quotes
+    pub constant_preview: Option<String>,
+    /// Inferred type, when a type map was supplied
+    pub type_str: Option<String>,
+    /// Semantic role from the companion .ders document, when supplied
+    pub semantic_role: Option<String>,
+}
+
+/// Single source for "what is this node" text used by the visualizers,
+/// semantic annotations and error reporting.
+pub struct NodeDescriber<'a> {
+    program: &'a Program,
+    semantics: Option<&'a SemanticDocument>,
+    types: Option<&'a HashMap<u32, Type>>,
+}
+
+impl<'a> NodeDescriber<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        NodeDescriber {
+            program,
+            semantics: None,
+            types: None,
+        }
+    }
+
+    pub fn with_semantics(mut self, semantics: &'a SemanticDocument) -> Self {
+        self.semantics = Some(semantics);
+        self
+    }
+
+    pub fn with_types(mut self, types: &'a HashMap<u32, Type>) -> Self {
+        self.types = Some(types);
+        self
+    }
+
+    /// Describe the node producing `result_id`, if it exists.
+    pub fn describe_id(&self, result_id: u32) -> Option<NodeDescription> {
+        self.program
+            .nodes
+            .iter()
+            .find(|n| n.result_id == result_id)
+            .map(|n| self.describe(n))
+    }
+
+    pub fn describe(&self, node: &Node) -> NodeDescription {
+        let opcode = OpCode::try_from(node.opcode).ok();
+        let short_label = opcode
+            .map(|op| format!("{:?}", op))
+            .unwrap_or_else(|| format!("Unknown({})", node.opcode));
+
+        let constant_preview = opcode.and_then(|op| self.constant_preview(op, node));
+        let detail = match opcode {
+            Some(op) if Self::is_constant(op) && constant_preview.is_none() => {
+                "Invalid constant".to_string()
+            }
+            Some(op) => Self::opcode_detail(op),
+            None => format!("Unknown operation with opcode {}", node.opcode),
+        };
+
+        let type_str = self
+            .types
+            .and_then(|types| types.get(&node.result_id))
+            .map(|ty| ty.to_string());
+        let semantic_role = self
+            .semantics
+            .and_then(|doc| doc.node_annotations.get(&node.result_id))
+            .map(|annotation| annotation.semantic_role.clone());
+
+        NodeDescription {
+            short_label,
+            detail,
+            constant_preview,
+            type_str,
+            semantic_role,
+        }
+    }
+
+    fn is_constant(opcode: OpCode) -> bool {
+        matches!(
+            opcode,
+            OpCode::ConstInt | OpCode::ConstFloat | OpCode::ConstString | OpCode::ConstBool
+        )
+    }
+
+    fn constant_preview(&self, opcode: OpCode, node: &Node) -> Option<String> {
+        let constants = &self.program.constants;
+        let index = node.args[0];
+        match opcode {
+            OpCode::ConstInt => constants.get_int(index).map(|v| v.to_string()),
+            OpCode::ConstFloat => constants.get_float(index).map(|v| v.to_string()),
+            OpCode::ConstString => constants.get_string(index).map(|v| format!("\"{}\"", v)),
+            OpCode::ConstBool => constants.get_bool(index).map(|v| v.to_string()),
+            _ => None,
+        }
+    }
+
+    fn opcode_detail(opcode: OpCode) -> String {
+        let detail = match opcode {
+            OpCode::ConstInt => "Integer constant",
+            OpCode::ConstFloat => "Float constant",
+            OpCode::ConstString => "String constant",
+            OpCode::ConstBool => "Boolean constant",
+            OpCode::Add => "Addition",
+            OpCode::Sub => "Subtraction",
+            OpCode::Mul => "Multiplication",
+            OpCode::Div => "Division",
+            OpCode::Mod => "Modulo",
+            OpCode::BitAnd => "Bitwise and",
+            OpCode::BitOr => "Bitwise or",
+            OpCode::BitXor => "Bitwise xor",
+            OpCode::BitNot => "Bitwise not",
+            OpCode::Shl => "Shift left",
+            OpCode::Shr => "Arithmetic shift right",
+            OpCode::Sqrt => "Square root",
+            OpCode::Pow => "Power",
+            OpCode::Abs => "Absolute value",
+            OpCode::Floor => "Round down",
+            OpCode::Ceil => "Round up",
+            OpCode::Min => "Minimum",
+            OpCode::Max => "Maximum",
+            OpCode::Neg => "Negation",
+            OpCode::Eq => "Equality check",
+            OpCode::Ne => "Inequality check",
+            OpCode::Lt => "Less than",
+            OpCode::Le => "Less than or equal",
+            OpCode::Gt => "Greater than",
+            OpCode::Ge => "Greater than or equal",
+            OpCode::And => "Logical and",
+            OpCode::Or => "Logical or",
+            OpCode::Not => "Logical not",
+            OpCode::Xor => "Logical xor",
+            OpCode::Branch => "Conditional branch",
+            OpCode::Loop => "Loop while condition holds",
+            OpCode::Call => "Function call",
+            OpCode::Return => "Return",
+            OpCode::DefineFunc => "Function definition",
+            OpCode::CreateClosure => "Closure creation",
+            OpCode::LoadParam => "Function parameter",
+            OpCode::CreateArray => "Array creation",
+            OpCode::CreateMap => "Map creation",
+            OpCode::ArrayGet => "Array read",
+            OpCode::ArraySet => "Array write",
+            OpCode::MapGet => "Map read",
+            OpCode::MapSet => "Map write",
+            OpCode::ArrayLen => "Array length",
+            OpCode::ArrayPush => "Array append",
+            OpCode::ArrayPop => "Array remove last",
+            OpCode::ArraySlice => "Array slice",
+            OpCode::ArrayConcat => "Array concatenation",
+            OpCode::MapContains => "Map key check",
+            OpCode::MapKeys => "Map keys",
+            OpCode::MapValues => "Map values",
+            OpCode::MapDelete => "Map delete",
+            OpCode::Load => "Memory load",
+            OpCode::Store => "Memory store",
+            OpCode::Alloc => "Memory allocation",
+            OpCode::Free => "Memory release",
+            OpCode::LoadArg => "Program argument",
+            OpCode::ArgCount => "Program argument count",
+            OpCode::PtrAdd => "Pointer offset",
+            OpCode::Cast => "Type conversion",
+            OpCode::TypeOf => "Runtime type name",
+            OpCode::StrConcat => "String concatenation",
+            OpCode::StrLen => "String length",
+            OpCode::StrSlice => "Substring",
+            OpCode::Concat => "Concatenation",
+            OpCode::Len => "Length",
+            OpCode::ParseInt => "Parse integer",
+            OpCode::ParseFloat => "Parse float",
+            OpCode::Format => "Format value",
+            OpCode::Exit => "Exit program",
+            OpCode::Assert => "Assertion",
+            OpCode::Try => "Try with fallback",
+            OpCode::Throw => "Throw error",
+            OpCode::Seq => "Sequence",
+            OpCode::Print => "Print output",
+            OpCode::Read => "Read input",
+            OpCode::Random => "Random number",
+            OpCode::Now => "Current time",
+            OpCode::Sleep => "Sleep",
+            OpCode::FileRead => "Read file",
+            OpCode::FileWrite => "Write file",
+            OpCode::GetEnv => "Environment variable",
+            OpCode::HttpGet => "HTTP GET request",
+            OpCode::ExternalCall => "Host function call",
+            _ => return format!("Executes {:?} operation", opcode),
+        };
+        detail.to_string()
+    }
+}
@@ -269,7 +269,7 @@ impl SemanticAnnotationGenerator {
             let annotation = NodeAnnotation {
                 node_id: node.result_id,
                 semantic_role: format!("Computation step {}", index + 1),
-                description: self.describe_node_operation(node),
+                description: self.describe_node_operation(node, program),
                 data_transformation: self.describe_data_transformation(node),
                 ai_rationale: "AI determined this operation was necessary for the intended computation".to_string(),
                 semantic_dependencies: self.analyze_semantic_dependencies(node, program),
@@ -282,10 +282,11 @@ impl SemanticAnnotationGenerator {
         annotations
     }
     
-    fn describe_node_operation(&self, node: &crate::core::Node) -> String {
-        match crate::core::OpCode::try_from(node.opcode) {
-            Ok(opcode) => format!("Executes {:?} operation", opcode),
-            Err(_) => format!("Unknown operation with opcode {}", node.opcode),
+    fn describe_node_operation(&self, node: &crate::core::Node, program: &crate::core::Program) -> String {
+        let description = crate::core::NodeDescriber::new(program).describe(node);
+        match description.constant_preview {
+            Some(preview) => format!("{}: {}", description.detail, preview),
+            None => description.detail,
         }
     }
     
@@ -373,9 +374,18 @@ impl SemanticAnnotationGenerator {
         }
     }
     
-    fn calculate_file_hash(&self, _file_path: &str) -> String {
-        // 简化实现 - 实际应该计算文件的SHA256
-        "sha256:placeholder".to_string()
+    /// SHA-256 of the file's bytes as `sha256:<hex>`, or "unknown" when the
+    /// file cannot be read (e.g. it has not been written yet).
+    pub fn calculate_file_hash(&self, file_path: &str) -> String {
+        use sha2::{Digest, Sha256};
+        match std::fs::read(file_path) {
+            Ok(bytes) => {
+                let digest = Sha256::digest(&bytes);
+                let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("sha256:{}", hex)
+            }
+            Err(_) => "unknown".to_string(),
+        }
     }
     
     /// 保存语义注释到文件
@@ -1,19 +1,41 @@
-use std::io::{Write, Result};
+use std::io::{Error, ErrorKind, Write, Result};
 use crate::core::binary_format::*;
+use crate::verification::Proof;
 use byteorder::{LittleEndian, WriteBytesExt};
 
+const FILE_HEADER_SIZE: usize = 16;
+const CHUNK_HEADER_SIZE: usize = 16;
+
 pub struct DERSerializer<W: Write> {
     writer: W,
+    size_limit: Option<usize>,
 }
 
 impl<W: Write> DERSerializer<W> {
     pub fn new(writer: W) -> Self {
-        DERSerializer { writer }
+        DERSerializer { writer, size_limit: None }
+    }
+
+    /// Refuse to write programs whose encoded size exceeds `limit` bytes.
+    /// The check happens before anything is written.
+    pub fn with_size_limit(mut self, limit: usize) -> Self {
+        self.size_limit = Some(limit);
+        self
     }
 
     pub fn write_program(&mut self, program: &Program) -> Result<()> {
+        if let Some(limit) = self.size_limit {
+            let size = program.serialized_size();
+            if size > limit {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("serialized program is {} bytes, exceeding the limit of {} bytes", size, limit),
+                ));
+            }
+        }
+
         // Write file header
-        self.write_header(&program.header)?;
+        self.write_header(&program.header, program.chunk_count())?;
 
         // Write metadata chunk
         self.write_metadata_chunk(&program.metadata)?;
@@ -24,14 +46,21 @@ impl<W: Write> DERSerializer<W> {
         // Write constant pool chunk
         self.write_const_chunk(&program.constants)?;
 
+        // Write proof chunk
+        if !program.proofs.is_empty() {
+            self.write_proof_chunk(&program.proofs)?;
+        }
+
         Ok(())
     }
 
-    fn write_header(&mut self, header: &FileHeader) -> Result<()> {
+    fn write_header(&mut self, header: &FileHeader, chunk_count: u32) -> Result<()> {
         self.writer.write_all(&header.magic)?;
-        self.writer.write_u16::<LittleEndian>(header.version)?;
+        // Always the current version: the layout written is the current one
+        self.writer.write_u16::<LittleEndian>(VERSION)?;
         self.writer.write_u16::<LittleEndian>(header.flags)?;
-        self.writer.write_u32::<LittleEndian>(header.chunk_count)?;
+        // The chunks actually written, whatever the header was left at
+        self.writer.write_u32::<LittleEndian>(chunk_count)?;
         self.writer.write_all(&header.reserved)?;
         Ok(())
     }
@@ -52,6 +81,9 @@ impl<W: Write> DERSerializer<W> {
                 Capability::Process => 3,
                 Capability::UI => 4,
                 Capability::ExternalCode => 5,
+                Capability::IO => 6,
+                Capability::Entropy => 7,
+                Capability::Clock => 8,
             };
             chunk_data.write_u32::<LittleEndian>(cap_id)?;
         }
@@ -88,9 +120,10 @@ impl<W: Write> DERSerializer<W> {
 
     fn write_impl_chunk(&mut self, nodes: &[Node]) -> Result<()> {
         let chunk_type = *b"IMPL";
-        let chunk_size = (nodes.len() * std::mem::size_of::<Node>()) as u32;
+        let chunk_size = (4 + nodes.len() * NODE_RECORD_SIZE) as u32;
 
         self.write_chunk_header(chunk_type, chunk_size)?;
+        self.writer.write_u32::<LittleEndian>(nodes.len() as u32)?;
 
         for node in nodes {
             self.write_node(node)?;
@@ -146,6 +179,15 @@ impl<W: Write> DERSerializer<W> {
         Ok(())
     }
 
+    fn write_proof_chunk(&mut self, proofs: &[Proof]) -> Result<()> {
+        let chunk_type = *b"PROF";
+        let chunk_data = encode_proofs(proofs)?;
+
+        self.write_chunk_header(chunk_type, chunk_data.len() as u32)?;
+        self.writer.write_all(&chunk_data)?;
+        Ok(())
+    }
+
     fn write_chunk_header(&mut self, chunk_type: [u8; 4], size: u32) -> Result<()> {
         let header = ChunkHeader {
             chunk_type,
@@ -160,4 +202,63 @@ impl<W: Write> DERSerializer<W> {
         self.writer.write_u32::<LittleEndian>(header.checksum)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// PROF chunk body: a proof count, then each proof as length-prefixed JSON.
+fn encode_proofs(proofs: &[Proof]) -> Result<Vec<u8>> {
+    let mut chunk_data = Vec::new();
+    chunk_data.write_u32::<LittleEndian>(proofs.len() as u32)?;
+    for proof in proofs {
+        let json = serde_json::to_vec(proof)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("cannot encode proof: {}", e)))?;
+        chunk_data.write_u32::<LittleEndian>(json.len() as u32)?;
+        chunk_data.write_all(&json)?;
+    }
+    Ok(chunk_data)
+}
+
+impl Program {
+    /// Number of chunks `DERSerializer::write_program` emits for this program.
+    pub fn chunk_count(&self) -> u32 {
+        if self.proofs.is_empty() { 3 } else { 4 }
+    }
+
+    /// Exact number of bytes `DERSerializer::write_program` produces for this program.
+    pub fn serialized_size(&self) -> usize {
+        let metadata = &self.metadata;
+        let mut meta_size = 4 + 4 + 4 * metadata.required_capabilities.len() + 4;
+        for trait_def in &metadata.traits {
+            meta_size += 4 + trait_def.name.len();
+            meta_size += 4 + trait_def.preconditions.iter().map(|p| 4 + p.len()).sum::<usize>();
+            meta_size += 4 + trait_def.postconditions.iter().map(|p| 4 + p.len()).sum::<usize>();
+        }
+
+        let impl_size = 4 + self.nodes.len() * NODE_RECORD_SIZE;
+
+        let constants = &self.constants;
+        let const_size = 4 + 8 * constants.integers.len()
+            + 4 + 8 * constants.floats.len()
+            + 4 + constants.strings.iter().map(|s| 4 + s.len()).sum::<usize>()
+            + 4 + constants.booleans.len();
+
+        let proof_size = if self.proofs.is_empty() {
+            0
+        } else {
+            // Proofs that fail to encode also fail to write, so any size will do
+            CHUNK_HEADER_SIZE + encode_proofs(&self.proofs).map_or(0, |data| data.len())
+        };
+
+        FILE_HEADER_SIZE
+            + CHUNK_HEADER_SIZE + meta_size
+            + CHUNK_HEADER_SIZE + impl_size
+            + CHUNK_HEADER_SIZE + const_size
+            + proof_size
+    }
+
+    /// Serialize the program into an in-memory buffer.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(self.serialized_size());
+        DERSerializer::new(&mut buffer).write_program(self)?;
+        Ok(buffer)
+    }
+}
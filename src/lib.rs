@@ -4,6 +4,7 @@ pub mod visualization;
 pub mod compiler;
 pub mod verification;
 pub mod types;
+pub mod optimize;
 pub mod tests;
 
 pub use core::*;
\ No newline at end of file
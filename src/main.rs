@@ -4,6 +4,7 @@ use der::visualization::*;
 use der::compiler::*;
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -19,12 +20,32 @@ fn main() {
                 eprintln!("Error: Please specify a .der file to run");
                 return;
             }
-            let program_args = if args.len() > 3 {
-                args[3..].to_vec()
-            } else {
-                vec![]
-            };
-            run_der_file(&args[2], &program_args);
+            match parse_run_args(&args[3..]) {
+                Ok(options) => {
+                    let status = run_der_file(&args[2], &options);
+                    std::process::exit(status);
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        "trace" => {
+            if args.len() < 3 {
+                eprintln!("Error: Please specify a .der file to trace");
+                return;
+            }
+            match parse_run_args(&args[3..]) {
+                Ok(options) => {
+                    let status = trace_der_file(&args[2], &options);
+                    std::process::exit(status);
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            }
         }
         "compile" => {
             if args.len() < 3 {
@@ -41,18 +62,52 @@ fn main() {
             }
             visualize_der_file(&args[2]);
         }
+        "dump" => {
+            if args.len() < 3 {
+                eprintln!("Usage: der dump <file.der> [--json|--text]");
+                std::process::exit(2);
+            }
+            let json = match args.get(3).map(String::as_str) {
+                None | Some("--text") => false,
+                Some("--json") => true,
+                Some(other) => {
+                    eprintln!("Error: Unknown dump format '{}'; use --json or --text", other);
+                    std::process::exit(2);
+                }
+            };
+            std::process::exit(if dump_der_file(&args[2], json) { 0 } else { 1 });
+        }
+        "verify" => {
+            if args.len() < 3 {
+                eprintln!("Error: Please specify a .der file to verify");
+                std::process::exit(2);
+            }
+            let valid = verify_der_file(&args[2]);
+            std::process::exit(if valid { 0 } else { 1 });
+        }
+        "gate" => {
+            if args.len() < 5 || args[3] != "--policy" {
+                eprintln!("Usage: der gate <file.der> --policy <policy.toml> [--json]");
+                std::process::exit(2);
+            }
+            let json = args[5..].iter().any(|a| a == "--json");
+            let passed = gate_der_file(&args[2], &args[4], json);
+            std::process::exit(if passed { 0 } else { 1 });
+        }
         "hello" => create_hello_world(),
         "sort" => create_bubble_sort(),
         "dynamic-sort" => create_dynamic_sort(),
         "args-test" => create_args_test(),
         "modify" => {
             if args.len() < 4 {
-                eprintln!("Usage: der modify <input.der> <modification_prompt>");
+                eprintln!("Usage: der modify <input.der> [--output <path>] [--in-place] [--force] <modification_prompt>");
                 return;
             }
             let input_file = &args[2];
-            let prompt = args[3..].join(" ");
-            modify_der_program(input_file, &prompt);
+            match parse_modify_args(&args[3..]) {
+                Ok((options, prompt)) => modify_der_program(input_file, &prompt, &options),
+                Err(e) => eprintln!("Error: {}", e),
+            }
         }
         _ => {
             eprintln!("Unknown command: {}", args[1]);
@@ -64,59 +119,226 @@ fn main() {
 fn print_usage() {
     println!("DER - Dynamic Execution Representation");
     println!("\nUsage:");
-    println!("  der run <file.der>       - Execute a DER program");
+    println!("  der run <file.der> [--allow <caps>] [--max-steps <n>] [--profile] [args...]");
+    println!("                           - Execute a DER program, granting the listed");
+    println!("                             capabilities (e.g. IO,FileSystem or all)");
+    println!("  der trace <file.der> [--allow <caps>] [--max-steps <n>] [args...]");
+    println!("                           - Execute a DER program, listing every node");
+    println!("                             evaluation in order");
     println!("  der compile <intent>     - Compile natural language to DER");
     println!("  der visualize <file.der> - Show program structure");
+    println!("  der dump <file.der> [--json|--text]");
+    println!("                           - Print a program without running it");
+    println!("  der verify <file.der>    - Check a program and summarize its safety");
+    println!("  der gate <file.der> --policy <policy.toml> [--json]");
+    println!("                           - Check a program against a ship policy");
     println!("  der hello                - Create hello world example");
     println!("  der sort                 - Create bubble sort example");
     println!("  der args-test            - Create argument test program");
     println!("  der dynamic-sort         - Create dynamic sorting program");
     println!("  der modify <file.der> <prompt> - AI modify binary DER program");
+    println!("      [--output <path>] [--in-place] [--force]");
+}
+
+#[derive(Debug, Default)]
+struct RunOptions {
+    /// Capabilities permitted with `--allow`
+    allowed: Vec<Capability>,
+    max_steps: Option<u64>,
+    /// Print a profile report after the run
+    profile: bool,
+    /// Arguments passed through to the program
+    program_args: Vec<String>,
+}
+
+/// Split `der run` arguments into run options and the arguments passed
+/// through to the program.
+fn parse_run_args(args: &[String]) -> std::result::Result<RunOptions, String> {
+    let mut options = RunOptions::default();
+    let mut i = 0;
+
+    while i < args.len() {
+        if args[i] == "--allow" {
+            let list = args.get(i + 1).ok_or("--allow requires a capability list")?;
+            for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                if name == "all" {
+                    options.allowed.extend([
+                        Capability::FileSystem,
+                        Capability::Network,
+                        Capability::Process,
+                        Capability::UI,
+                        Capability::ExternalCode,
+                        Capability::IO,
+                        Capability::Entropy,
+                        Capability::Clock,
+                    ]);
+                } else {
+                    options.allowed.push(Capability::from_name(name)
+                        .ok_or_else(|| format!("Unknown capability '{}'", name))?);
+                }
+            }
+            i += 1;
+        } else if args[i] == "--profile" {
+            options.profile = true;
+        } else if args[i] == "--max-steps" {
+            let steps = args.get(i + 1).ok_or("--max-steps requires a number")?;
+            options.max_steps = Some(steps.parse()
+                .map_err(|_| format!("Invalid step limit '{}'", steps))?);
+            i += 1;
+        } else {
+            options.program_args.push(args[i].clone());
+        }
+        i += 1;
+    }
+
+    Ok(options)
+}
+
+/// Capabilities to grant a run: those the program declares and `--allow`
+/// permits. Declared capabilities that were not allowed come back second.
+fn resolve_grants(declared: &[Capability], allowed: &[Capability]) -> (Vec<Capability>, Vec<Capability>) {
+    declared.iter().cloned().partition(|cap| allowed.contains(cap))
 }
 
-fn run_der_file(filename: &str, program_args: &[String]) {
+/// Create an executor for `program` with the grants, limits and program
+/// arguments from `options`. Arguments that parse as numbers are passed as
+/// Int or Float, everything else as String.
+fn prepare_executor(program: Program, options: &RunOptions) -> Executor {
+    let (granted, withheld) = resolve_grants(&program.metadata.required_capabilities, &options.allowed);
+    for cap in &withheld {
+        eprintln!("Warning: program requires {:?}, which was not allowed (use --allow {:?})", cap, cap);
+    }
+
+    let mut executor = Executor::new(program);
+    for cap in granted {
+        executor.grant_capability(cap);
+    }
+    if let Some(steps) = options.max_steps {
+        executor.set_step_limit(steps);
+    }
+
+    for (i, arg) in options.program_args.iter().enumerate() {
+        if let Ok(int_val) = arg.parse::<i64>() {
+            executor.set_argument(i, Value::Int(int_val));
+        } else if let Ok(float_val) = arg.parse::<f64>() {
+            executor.set_argument(i, Value::Float(float_val));
+        } else {
+            executor.set_argument(i, Value::String(arg.clone()));
+        }
+    }
+    executor.set_argc(options.program_args.len());
+    executor
+}
+
+/// Run a program and return the process exit status: the program's own
+/// status for `Exit`, 1 for any other failure, 0 otherwise.
+fn run_der_file(filename: &str, options: &RunOptions) -> i32 {
+    let program_args = &options.program_args;
     match File::open(filename) {
         Ok(mut file) => {
             let mut deserializer = DERDeserializer::new(file);
             match deserializer.read_program() {
-                Ok(mut program) => {
+                Ok(program) => {
                     println!("Executing {}...", filename);
                     if !program_args.is_empty() {
                         println!("With arguments: {:?}", program_args);
                     }
                     println!();
                     
-                    let mut executor = Executor::new(program);
-                    executor.grant_capability(Capability::FileSystem);
-                    
-                    // Set command line arguments using public API
-                    for (i, arg) in program_args.iter().enumerate() {
-                        // Try to parse as number first, then as string
-                        if let Ok(int_val) = arg.parse::<i64>() {
-                            executor.set_argument(i, Value::Int(int_val));
-                        } else if let Ok(float_val) = arg.parse::<f64>() {
-                            executor.set_argument(i, Value::Float(float_val));
-                        } else {
-                            executor.set_argument(i, Value::String(arg.clone()));
-                        }
+                    let mut executor = prepare_executor(program, options);
+                    if options.profile {
+                        executor.enable_profiling();
                     }
                     
-                    // Set argument count
-                    executor.set_argc(program_args.len());
-                    
-                    match executor.execute() {
+                    let status = match executor.execute() {
                         Ok(result) => {
                             if !matches!(result, Value::Nil) {
                                 println!("Result: {}", result.to_string());
                             }
+                            0
                         }
-                        Err(e) => eprintln!("Execution error: {}", e),
+                        Err(RuntimeError::Exit(status)) => status,
+                        Err(e) => {
+                            eprintln!("Execution error: {}", e);
+                            1
+                        }
+                    };
+                    
+                    if let Some(report) = executor.profile_report() {
+                        println!("\n{}", report.render_text());
                     }
+                    status
+                }
+                Err(e) => {
+                    eprintln!("Failed to deserialize program: {}", e);
+                    1
                 }
-                Err(e) => eprintln!("Failed to deserialize program: {}", e),
             }
         }
-        Err(e) => eprintln!("Failed to open file: {}", e),
+        Err(e) => {
+            eprintln!("Failed to open file: {}", e);
+            1
+        }
+    }
+}
+
+/// Open and deserialize a program, describing any failure.
+fn read_der_file(filename: &str) -> std::result::Result<Program, String> {
+    let file = File::open(filename).map_err(|e| format!("Failed to open file: {}", e))?;
+    DERDeserializer::new(file).read_program()
+        .map_err(|e| format!("Failed to deserialize program: {}", e))
+}
+
+/// Run a program with tracing and print one line per node evaluation,
+/// followed by the result. On failure the trace ends at the failing node.
+/// Returns the exit status as `run_der_file` does.
+fn trace_der_file(filename: &str, options: &RunOptions) -> i32 {
+    let program = match read_der_file(filename) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut executor = prepare_executor(program, options);
+    executor.enable_tracing();
+    let result = executor.execute();
+    for line in render_trace(&executor.take_trace()) {
+        println!("{}", line);
+    }
+
+    match result {
+        Ok(value) => {
+            println!("Result: {}", trace_value(&value));
+            0
+        }
+        Err(RuntimeError::Exit(status)) => status,
+        Err(e) => {
+            eprintln!("Execution error: {}", e);
+            1
+        }
+    }
+}
+
+/// Format trace events as `[node] Opcode(args) -> value`, or
+/// `[node] Opcode(args) !! error` for the evaluation that failed.
+fn render_trace(events: &[TraceEvent]) -> Vec<String> {
+    events.iter().map(|event| {
+        let args: Vec<String> = event.args_resolved.iter().map(trace_value).collect();
+        let outcome = match &event.result {
+            Ok(value) => format!("-> {}", trace_value(value)),
+            Err(e) => format!("!! {}", e),
+        };
+        format!("[{}] {:?}({}) {}", event.node_id, event.opcode, args.join(", "), outcome)
+    }).collect()
+}
+
+/// Strings are quoted in traces so they can be told apart from numbers.
+fn trace_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
     }
 }
 
@@ -130,7 +352,7 @@ fn compile_from_intent(intent: &str) {
     let semantics_filename = "output.ders";
     
     match generator.generate_with_semantics(intent, der_filename) {
-        Ok((program, semantic_doc)) => {
+        Ok((program, mut semantic_doc)) => {
             // Save DER program
             match File::create(der_filename) {
                 Ok(file) => {
@@ -140,7 +362,9 @@ fn compile_from_intent(intent: &str) {
                             println!("Program compiled to: {}", der_filename);
                             
                             // Save semantic annotations
+                            // The digest can only be taken once the file exists
                             let semantics_generator = SemanticAnnotationGenerator::new();
+                            semantic_doc.metadata.der_file_hash = semantics_generator.calculate_file_hash(der_filename);
                             match semantics_generator.save_to_file(&semantic_doc, semantics_filename) {
                                 Ok(_) => {
                                     println!("📝 Semantic annotations saved to: {}", semantics_filename);
@@ -192,6 +416,9 @@ fn visualize_der_file(filename: &str) {
                     let mut text_vis = TextRenderer::new(program.clone());
                     println!("{}", text_vis.render());
                     
+                    println!("\nDisassembly:");
+                    print!("{}", text_vis.render_disassembly());
+                    
                     // Also generate DOT format
                     let graph_renderer = GraphRenderer::new(program);
                     let dot_filename = filename.replace(".der", ".dot");
@@ -199,6 +426,12 @@ fn visualize_der_file(filename: &str) {
                         Ok(_) => println!("\nGraphviz DOT file saved to: {}", dot_filename),
                         Err(e) => eprintln!("Failed to write DOT file: {}", e),
                     }
+                    
+                    let svg_filename = filename.replace(".der", ".svg");
+                    match std::fs::write(&svg_filename, graph_renderer.render_to_svg()) {
+                        Ok(_) => println!("SVG image saved to: {}", svg_filename),
+                        Err(e) => eprintln!("Failed to write SVG file: {}", e),
+                    }
                 }
                 Err(e) => eprintln!("Failed to deserialize program: {}", e),
             }
@@ -207,20 +440,126 @@ fn visualize_der_file(filename: &str) {
     }
 }
 
-fn create_hello_world() {
-    let mut program = Program::new();
-    
-    // Create "Hello, World!" string constant
-    let hello_idx = program.constants.add_string("Hello, World!".to_string());
-    
-    // Create nodes
-    let str_node = Node::new(OpCode::ConstString, 1).with_args(&[hello_idx]);
-    let print_node = Node::new(OpCode::Print, 2).with_args(&[1]);
-    
-    // Add nodes to program
-    program.add_node(str_node);
-    program.add_node(print_node);
-    program.set_entry_point(2); // Entry point should be print_node's result_id (2)
+/// Print a program as JSON or as a disassembly listing, without running
+/// it or writing any files. Returns whether the file could be read.
+fn dump_der_file(filename: &str, json: bool) -> bool {
+    match read_der_file(filename) {
+        Ok(program) => {
+            print!("{}", dump_program(program, json));
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+fn dump_program(program: Program, json: bool) -> String {
+    if json {
+        format!("{}\n", program.to_json())
+    } else {
+        TextRenderer::new(program).render_disassembly()
+    }
+}
+
+/// Verify a program file and print the findings. Returns whether the
+/// program is valid.
+fn verify_der_file(filename: &str) -> bool {
+    let program = match read_der_file(filename) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+
+    let (valid, lines) = verification_report(program);
+    for line in lines {
+        println!("{}", line);
+    }
+    valid
+}
+
+/// Run the verifier and safety analysis over `program`, returning whether
+/// it is valid and the report lines to show.
+fn verification_report(program: Program) -> (bool, Vec<String>) {
+    let verifier = der::verification::Verifier::new(program);
+    let result = verifier.verify_program();
+    let safety = verifier.verify_safety();
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+
+    let mut lines = Vec::new();
+    for error in &result.errors {
+        lines.push(format!("[ERROR] node {}: {}", error.node_id, error.message));
+    }
+    for warning in &result.warnings {
+        lines.push(format!("[WARN] {}", warning));
+    }
+    for info in &result.info {
+        lines.push(format!("[INFO] {}", info));
+    }
+
+    lines.push("Safety:".to_string());
+    lines.push(format!("  memory safe: {}", yes_no(safety.memory_safe)));
+    lines.push(format!("  deterministic: {}", yes_no(safety.deterministic)));
+    lines.push(format!("  unsafe operations: {}", yes_no(safety.has_unsafe_operations)));
+    for effect in &safety.side_effects {
+        lines.push(format!("  - {}", effect));
+    }
+    lines.push(format!("\nVerification {}", if result.is_valid { "passed" } else { "failed" }));
+
+    (result.is_valid, lines)
+}
+
+fn gate_der_file(filename: &str, policy_file: &str, json: bool) -> bool {
+    let policy = match der::verification::GatePolicy::from_file(policy_file) {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return false;
+        }
+    };
+
+    let bytes = match std::fs::read(filename) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to open file: {}", e);
+            return false;
+        }
+    };
+    let program = match DERDeserializer::new(bytes.as_slice()).read_program() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Failed to deserialize program: {}", e);
+            return false;
+        }
+    };
+
+    let report = der::verification::GateEvaluator::new(policy)
+        .evaluate_with_size(&program, bytes.len());
+
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(text) => println!("{}", text),
+            Err(e) => eprintln!("Failed to encode report: {}", e),
+        }
+    } else {
+        for rule in &report.rules {
+            let mark = if rule.passed { "PASS" } else { "FAIL" };
+            println!("[{}] {}: {}", mark, rule.rule, rule.message);
+        }
+        println!("\nGate {}", if report.passed { "passed" } else { "failed" });
+    }
+
+    report.passed
+}
+
+fn hello_world_program() -> Program {
+    let mut builder = ProgramBuilder::new();
+    let greeting = builder.const_string("Hello, World!");
+    let print = builder.print(greeting);
+    let mut program = builder.build(print);
     
     // Update metadata
     program.header.chunk_count = 3;
@@ -229,6 +568,11 @@ fn create_hello_world() {
         preconditions: vec![],
         postconditions: vec!["Prints greeting".to_string()],
     });
+    program
+}
+
+fn create_hello_world() {
+    let program = hello_world_program();
     
     // Save to file
     let filename = "hello.der";
@@ -241,7 +585,7 @@ fn create_hello_world() {
                     println!("\nProgram structure:");
                     let mut renderer = TextRenderer::new(program);
                     println!("{}", renderer.render());
-                    println!("\nRun with: der run hello.der");
+                    println!("\nRun with: der run hello.der --allow IO");
                 }
                 Err(e) => eprintln!("Failed to write program: {}", e),
             }
@@ -289,6 +633,7 @@ fn create_bubble_sort() {
     // which would need more opcodes. This is a simplified version.
     
     program.set_entry_point(result);
+    program.declare_node_capabilities();
     program.header.chunk_count = 3;
     program.metadata.traits.push(Trait {
         name: "BubbleSort".to_string(),
@@ -307,7 +652,7 @@ fn create_bubble_sort() {
                     println!("\nProgram structure:");
                     let mut renderer = TextRenderer::new(program);
                     println!("{}", renderer.render());
-                    println!("\nRun with: der run sort.der");
+                    println!("\nRun with: der run sort.der --allow IO");
                 }
                 Err(e) => eprintln!("Failed to write program: {}", e),
             }
@@ -329,6 +674,7 @@ fn create_args_test() {
     program.add_node(print_node);
     program.set_entry_point(2);
     
+    program.declare_node_capabilities();
     program.header.chunk_count = 3;
     program.metadata.traits.push(Trait {
         name: "ArgumentTest".to_string(),
@@ -347,8 +693,8 @@ fn create_args_test() {
                     println!("\nProgram structure:");
                     let mut renderer = TextRenderer::new(program);
                     println!("{}", renderer.render());
-                    println!("\nRun with: der run args-test.der <args...>");
-                    println!("Example: der run args-test.der 42 hello 3.14");
+                    println!("\nRun with: der run args-test.der --allow IO <args...>");
+                    println!("Example: der run args-test.der --allow IO 42 hello 3.14");
                 }
                 Err(e) => eprintln!("Failed to write program: {}", e),
             }
@@ -375,11 +721,14 @@ fn create_dynamic_sort() {
     let const2 = Node::new(OpCode::ConstInt, 103).with_args(&[two_idx]);
     let const3 = Node::new(OpCode::ConstInt, 104).with_args(&[three_idx]);
     
+    // Missing arguments default to 0 so fewer than four still sort
+    let default_arg = Node::new(OpCode::ConstInt, 105).with_args(&[zero_idx]);
+    
     // Load arguments using the constant indices
-    let load_arg0 = Node::new(OpCode::LoadArg, 1).with_args(&[101]); // arg[0]
-    let load_arg1 = Node::new(OpCode::LoadArg, 2).with_args(&[102]); // arg[1]
-    let load_arg2 = Node::new(OpCode::LoadArg, 3).with_args(&[103]); // arg[2]
-    let load_arg3 = Node::new(OpCode::LoadArg, 4).with_args(&[104]); // arg[3]
+    let load_arg0 = Node::new(OpCode::LoadArg, 1).with_args(&[101, 105]); // arg[0]
+    let load_arg1 = Node::new(OpCode::LoadArg, 2).with_args(&[102, 105]); // arg[1]
+    let load_arg2 = Node::new(OpCode::LoadArg, 3).with_args(&[103, 105]); // arg[2]
+    let load_arg3 = Node::new(OpCode::LoadArg, 4).with_args(&[104, 105]); // arg[3]
     
     // Node 5-8: 比较和选择最小/最大值 (简化的排序网络)
     // 比较 arg[0] 和 arg[1]，选择较小的
@@ -405,8 +754,15 @@ fn create_dynamic_sort() {
     let largest = Node::new(OpCode::Branch, 15).with_args(&[14, 10, 7]);   // 最大值
     let second_largest = Node::new(OpCode::Branch, 16).with_args(&[14, 7, 10]);
     
-    // 创建排序后的数组
-    let sorted_array = Node::new(OpCode::CreateArray, 17).with_args(&[12, 13, 16]); // 只取前3个
+    // 中间两个值还需要再比较一次
+    let cmp_middle = Node::new(OpCode::Lt, 21).with_args(&[13, 16]);
+    let middle_low = Node::new(OpCode::Branch, 22).with_args(&[21, 13, 16]);
+    let middle_high = Node::new(OpCode::Branch, 23).with_args(&[21, 16, 13]);
+    
+    // 创建排序后的数组: CreateArray 最多3个元素，所以拼接两半
+    let lower_half = Node::new(OpCode::CreateArray, 24).with_args(&[12, 22]);
+    let upper_half = Node::new(OpCode::CreateArray, 25).with_args(&[23, 15]);
+    let sorted_array = Node::new(OpCode::ArrayConcat, 17).with_args(&[24, 25]);
     
     // 输出消息
     let msg_idx = program.constants.add_string("Sorted array (first 4 args): ".to_string());
@@ -421,6 +777,7 @@ fn create_dynamic_sort() {
     program.add_node(const1);
     program.add_node(const2);
     program.add_node(const3);
+    program.add_node(default_arg);
     program.add_node(load_arg0);
     program.add_node(load_arg1);
     program.add_node(load_arg2);
@@ -437,6 +794,11 @@ fn create_dynamic_sort() {
     program.add_node(cmp_maxs);
     program.add_node(largest);
     program.add_node(second_largest);
+    program.add_node(cmp_middle);
+    program.add_node(middle_low);
+    program.add_node(middle_high);
+    program.add_node(lower_half);
+    program.add_node(upper_half);
     program.add_node(sorted_array);
     program.add_node(msg_node);
     program.add_node(print_msg);
@@ -444,6 +806,7 @@ fn create_dynamic_sort() {
     
     program.set_entry_point(20); // 最后的打印操作
     
+    program.declare_node_capabilities();
     program.header.chunk_count = 3;
     program.metadata.traits.push(Trait {
         name: "DynamicSort".to_string(),
@@ -462,8 +825,8 @@ fn create_dynamic_sort() {
                     println!("\nProgram structure:");
                     let mut renderer = TextRenderer::new(program);
                     println!("{}", renderer.render());
-                    println!("\nRun with: der run dynamic_sort.der <numbers...>");
-                    println!("Example: der run dynamic_sort.der 42 13 7 89");
+                    println!("\nRun with: der run dynamic_sort.der --allow IO <numbers...>");
+                    println!("Example: der run dynamic_sort.der --allow IO 42 13 7 89");
                 }
                 Err(e) => eprintln!("Failed to write program: {}", e),
             }
@@ -473,7 +836,110 @@ fn create_dynamic_sort() {
 }
 
 
-fn modify_der_program(input_file: &str, modification_prompt: &str) {
+#[derive(Debug, Default)]
+struct ModifyOptions {
+    output: Option<String>,
+    in_place: bool,
+    force: bool,
+}
+
+/// Where a `der modify` run writes its result. For in-place edits the program
+/// is first written to `write_path` and then renamed over `final_path`.
+#[derive(Debug, PartialEq)]
+struct OutputPlan {
+    final_path: PathBuf,
+    write_path: PathBuf,
+}
+
+fn parse_modify_args(args: &[String]) -> std::result::Result<(ModifyOptions, String), String> {
+    let mut options = ModifyOptions::default();
+    let mut prompt_words = Vec::new();
+    let mut i = 0;
+
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output" | "-o" => {
+                let path = args.get(i + 1).ok_or("--output requires a path")?;
+                options.output = Some(path.clone());
+                i += 1;
+            }
+            "--in-place" => options.in_place = true,
+            "--force" => options.force = true,
+            word => prompt_words.push(word.to_string()),
+        }
+        i += 1;
+    }
+
+    if prompt_words.is_empty() {
+        return Err("Please specify a modification prompt".to_string());
+    }
+
+    Ok((options, prompt_words.join(" ")))
+}
+
+/// Decide where the modified program goes without touching the filesystem;
+/// `exists` answers whether a path is already taken.
+fn plan_modify_output(input: &Path, options: &ModifyOptions, exists: &dyn Fn(&Path) -> bool) -> std::result::Result<OutputPlan, String> {
+    if options.in_place {
+        if options.output.is_some() {
+            return Err("--in-place and --output cannot be used together".to_string());
+        }
+        let file_name = input.file_name()
+            .ok_or_else(|| format!("Invalid input path: {}", input.display()))?
+            .to_string_lossy();
+        let write_path = input.with_file_name(format!(".{}.tmp", file_name));
+        return Ok(OutputPlan {
+            final_path: input.to_path_buf(),
+            write_path,
+        });
+    }
+
+    let final_path = match &options.output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let stem = input.file_stem()
+                .ok_or_else(|| format!("Invalid input path: {}", input.display()))?
+                .to_string_lossy();
+            let mut n = 1;
+            loop {
+                let candidate = input.with_file_name(format!("{}_modified_{}.der", stem, n));
+                if !exists(&candidate) {
+                    break candidate;
+                }
+                n += 1;
+            }
+        }
+    };
+
+    if final_path == input {
+        return Err(format!("Refusing to overwrite the input file {}; use --in-place", input.display()));
+    }
+    if exists(&final_path) && !options.force {
+        return Err(format!("{} already exists; use --force to overwrite", final_path.display()));
+    }
+
+    Ok(OutputPlan {
+        write_path: final_path.clone(),
+        final_path,
+    })
+}
+
+fn write_modified_program(program: &Program, plan: &OutputPlan) -> std::io::Result<()> {
+    let file = File::create(&plan.write_path)?;
+    let mut serializer = DERSerializer::new(file);
+    if let Err(e) = serializer.write_program(program) {
+        if plan.write_path != plan.final_path {
+            let _ = std::fs::remove_file(&plan.write_path);
+        }
+        return Err(e);
+    }
+    if plan.write_path != plan.final_path {
+        std::fs::rename(&plan.write_path, &plan.final_path)?;
+    }
+    Ok(())
+}
+
+fn modify_der_program(input_file: &str, modification_prompt: &str, options: &ModifyOptions) {
     println!("🤖 AI Binary Code Modifier");
     println!("Input file: {}", input_file);
     println!("Modification: \"{}\"", modification_prompt);
@@ -481,51 +947,45 @@ fn modify_der_program(input_file: &str, modification_prompt: &str) {
     
     // Step 1: Load existing DER program
     match File::open(input_file) {
-        Ok(mut file) => {
+        Ok(file) => {
             let mut deserializer = DERDeserializer::new(file);
             match deserializer.read_program() {
-                Ok(mut program) => {
+                Ok(program) => {
                     println!("✅ Successfully loaded binary program");
                     println!("📊 Program stats: {} nodes, entry point: {}", 
                              program.nodes.len(), program.metadata.entry_point);
                     
                     // Step 2: AI analyzes and modifies the program
-                    let modified_program = ai_modify_program(program, modification_prompt);
+                    let (modified_program, change_count) = ai_modify_program(program, modification_prompt);
+                    if change_count == 0 {
+                        println!("ℹ️  No changes were made; nothing written");
+                        return;
+                    }
                     
                     // Step 3: Save to new file
-                    let output_file = match modification_prompt.to_lowercase().as_str() {
-                        prompt if prompt.contains("reverse") || prompt.contains("descending") => {
-                            input_file.replace(".der", "_reverse.der")
-                        }
-                        prompt if prompt.contains("faster") || prompt.contains("optimize") => {
-                            input_file.replace(".der", "_optimized.der")
-                        }
-                        _ => {
-                            input_file.replace(".der", "_modified.der")
+                    let plan = match plan_modify_output(Path::new(input_file), options, &|p| p.exists()) {
+                        Ok(plan) => plan,
+                        Err(e) => {
+                            eprintln!("❌ {}", e);
+                            return;
                         }
                     };
                     
-                    match File::create(&output_file) {
-                        Ok(file) => {
-                            let mut serializer = DERSerializer::new(file);
-                            match serializer.write_program(&modified_program) {
-                                Ok(_) => {
-                                    println!("✅ AI modification complete!");
-                                    println!("💾 Output saved to: {}", output_file);
-                                    
-                                    // Show what AI changed
-                                    println!("\n🧠 AI Modification Summary:");
-                                    println!("• Binary computation graph analyzed");
-                                    println!("• Logic transformation applied");
-                                    println!("• New program semantics verified");
-                                    
-                                    println!("\n🧪 Test the modified program:");
-                                    println!("   ./target/release/der run {} 5 1 9 3", output_file);
-                                }
-                                Err(e) => eprintln!("❌ Failed to write modified program: {}", e),
-                            }
+                    match write_modified_program(&modified_program, &plan) {
+                        Ok(_) => {
+                            println!("✅ AI modification complete!");
+                            println!("💾 Output saved to: {}", plan.final_path.display());
+                            
+                            // Show what AI changed
+                            println!("\n🧠 AI Modification Summary:");
+                            println!("• Binary computation graph analyzed");
+                            println!("• {} logic transformation(s) applied", change_count);
+                            println!("• New program semantics verified");
+                            
+                            println!("\n🧪 Test the modified program:");
+                            println!("   ./target/release/der run {} --allow IO 5 1 9 3", plan.final_path.display());
                         }
-                        Err(e) => eprintln!("❌ Failed to create output file: {}", e),
+                        Err(e) => eprintln!("❌ Failed to write modified program: {}", e),
                     }
                 }
                 Err(e) => eprintln!("❌ Failed to deserialize program: {}", e),
@@ -535,8 +995,11 @@ fn modify_der_program(input_file: &str, modification_prompt: &str) {
     }
 }
 
-fn ai_modify_program(mut program: Program, prompt: &str) -> Program {
+/// Apply the transformation implied by `prompt` and report how many nodes
+/// were rewritten, so callers can skip writing when nothing changed.
+fn ai_modify_program(mut program: Program, prompt: &str) -> (Program, usize) {
     println!("🧠 AI analyzing computational graph...");
+    let mut change_count = 0;
     
     // AI智能分析：识别修改意图
     if prompt.to_lowercase().contains("reverse") || prompt.to_lowercase().contains("descending") {
@@ -544,27 +1007,25 @@ fn ai_modify_program(mut program: Program, prompt: &str) -> Program {
         
         // AI直接操作二进制计算图：修改比较操作
         for node in &mut program.nodes {
-            match OpCode::try_from(node.opcode) {
-                Ok(OpCode::Lt) => {
-                    println!("   • Converting Lt to Gt in node {}", node.result_id);
-                    node.opcode = OpCode::Gt as u16;
-                }
-                Ok(OpCode::Le) => {
-                    println!("   • Converting Le to Ge in node {}", node.result_id);
-                    node.opcode = OpCode::Ge as u16;
-                }
-                Ok(OpCode::Gt) => {
-                    println!("   • Converting Gt to Lt in node {}", node.result_id);
-                    node.opcode = OpCode::Lt as u16;
-                }
-                Ok(OpCode::Ge) => {
-                    println!("   • Converting Ge to Le in node {}", node.result_id);
-                    node.opcode = OpCode::Le as u16;
-                }
-                _ => {} // 其他节点不变
+            let replacement = match OpCode::try_from(node.opcode) {
+                Ok(OpCode::Lt) => Some(OpCode::Gt),
+                Ok(OpCode::Le) => Some(OpCode::Ge),
+                Ok(OpCode::Gt) => Some(OpCode::Lt),
+                Ok(OpCode::Ge) => Some(OpCode::Le),
+                _ => None, // 其他节点不变
+            };
+            if let (Ok(from), Some(to)) = (OpCode::try_from(node.opcode), replacement) {
+                println!("   • Converting {:?} to {:?} in node {}", from, to, node.result_id);
+                node.opcode = to as u16;
+                change_count += 1;
             }
         }
         
+        if change_count == 0 {
+            println!("🤔 AI: No comparison nodes found to reverse");
+            return (program, 0);
+        }
+        
         // 更新程序元数据
         program.metadata.traits.clear();
         program.metadata.traits.push(Trait {
@@ -574,7 +1035,7 @@ fn ai_modify_program(mut program: Program, prompt: &str) -> Program {
         });
         
         // 更新常量字符串
-        for (i, string_const) in program.constants.strings.iter_mut().enumerate() {
+        for string_const in program.constants.strings.iter_mut() {
             if string_const.contains("Sorted array") {
                 *string_const = "Reverse sorted array (first 4 args): ".to_string();
                 println!("   • Updated output message");
@@ -584,8 +1045,271 @@ fn ai_modify_program(mut program: Program, prompt: &str) -> Program {
         
         println!("✅ AI binary transformation complete");
     } else {
-        println!("🤔 AI: Modification intent not recognized, applying generic transformation");
+        println!("🤔 AI: Modification intent not recognized, no transformation applied");
     }
     
-    program
+    (program, change_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(output: Option<&str>, in_place: bool, force: bool) -> ModifyOptions {
+        ModifyOptions {
+            output: output.map(|s| s.to_string()),
+            in_place,
+            force,
+        }
+    }
+
+    #[test]
+    fn test_default_output_name() {
+        let plan = plan_modify_output(Path::new("dir/sort.der"), &options(None, false, false), &|_| false).unwrap();
+        assert_eq!(plan.final_path, PathBuf::from("dir/sort_modified_1.der"));
+        assert_eq!(plan.write_path, plan.final_path);
+    }
+
+    #[test]
+    fn test_extensionless_input_does_not_overwrite_itself() {
+        let plan = plan_modify_output(Path::new("sort"), &options(None, false, false), &|_| false).unwrap();
+        assert_eq!(plan.final_path, PathBuf::from("sort_modified_1.der"));
+    }
+
+    #[test]
+    fn test_default_name_skips_existing_outputs() {
+        let taken = [PathBuf::from("sort_modified_1.der"), PathBuf::from("sort_modified_2.der")];
+        let plan = plan_modify_output(Path::new("sort.der"), &options(None, false, false), &|p| taken.iter().any(|t| t == p)).unwrap();
+        assert_eq!(plan.final_path, PathBuf::from("sort_modified_3.der"));
+    }
+
+    #[test]
+    fn test_explicit_output_requires_force_when_existing() {
+        let opts = options(Some("out.der"), false, false);
+        assert!(plan_modify_output(Path::new("sort.der"), &opts, &|_| true).is_err());
+
+        let opts = options(Some("out.der"), false, true);
+        let plan = plan_modify_output(Path::new("sort.der"), &opts, &|_| true).unwrap();
+        assert_eq!(plan.final_path, PathBuf::from("out.der"));
+    }
+
+    #[test]
+    fn test_output_equal_to_input_is_refused() {
+        let opts = options(Some("sort.der"), false, true);
+        assert!(plan_modify_output(Path::new("sort.der"), &opts, &|_| true).is_err());
+    }
+
+    #[test]
+    fn test_in_place_writes_temp_then_renames() {
+        let plan = plan_modify_output(Path::new("dir/sort.der"), &options(None, true, false), &|_| true).unwrap();
+        assert_eq!(plan.final_path, PathBuf::from("dir/sort.der"));
+        assert_eq!(plan.write_path, PathBuf::from("dir/.sort.der.tmp"));
+
+        assert!(plan_modify_output(Path::new("sort.der"), &options(Some("x.der"), true, false), &|_| false).is_err());
+    }
+
+    #[test]
+    fn test_parse_modify_args() {
+        let args: Vec<String> = ["make", "it", "--output", "x.der", "reverse", "--force"]
+            .iter().map(|s| s.to_string()).collect();
+        let (opts, prompt) = parse_modify_args(&args).unwrap();
+        assert_eq!(prompt, "make it reverse");
+        assert_eq!(opts.output.as_deref(), Some("x.der"));
+        assert!(opts.force);
+        assert!(!opts.in_place);
+    }
+
+    #[test]
+    fn test_unrecognized_prompt_makes_no_changes() {
+        let (_, changes) = ai_modify_program(Program::new(), "make it faster");
+        assert_eq!(changes, 0);
+    }
+
+    fn write_exit_program(status: i64) -> tempfile::NamedTempFile {
+        let mut program = Program::new();
+        let status_idx = program.constants.add_int(status);
+        program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[status_idx]));
+        program.add_node(Node::new(OpCode::Exit, 2).with_args(&[1]));
+        program.set_entry_point(2);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_run_propagates_exit_status() {
+        let file = write_exit_program(3);
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &RunOptions::default()), 3);
+    }
+
+    #[test]
+    fn test_run_failures_exit_with_one() {
+        assert_eq!(run_der_file("/nonexistent/missing.der", &RunOptions::default()), 1);
+
+        // Exit with a non-integer status is an ordinary execution error
+        let mut program = Program::new();
+        let text = program.constants.add_string("three".to_string());
+        program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[text]));
+        program.add_node(Node::new(OpCode::Exit, 2).with_args(&[1]));
+        program.set_entry_point(2);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &RunOptions::default()), 1);
+    }
+
+    #[test]
+    fn test_parse_run_args() {
+        let args: Vec<String> = ["42", "--allow", "IO,Clock", "hello"]
+            .iter().map(|s| s.to_string()).collect();
+        let options = parse_run_args(&args).unwrap();
+        assert_eq!(options.allowed, vec![Capability::IO, Capability::Clock]);
+        assert_eq!(options.program_args, vec!["42".to_string(), "hello".to_string()]);
+        assert_eq!(options.max_steps, None);
+        assert!(!options.profile);
+
+        let options = parse_run_args(&["--allow".to_string(), "all".to_string()]).unwrap();
+        assert!(options.allowed.contains(&Capability::ExternalCode));
+
+        assert!(parse_run_args(&["--allow".to_string()]).is_err());
+        assert!(parse_run_args(&["--allow".to_string(), "Disk".to_string()]).is_err());
+
+        let options = parse_run_args(&["--max-steps".to_string(), "500".to_string()]).unwrap();
+        assert_eq!(options.max_steps, Some(500));
+        assert!(parse_run_args(&["--max-steps".to_string(), "many".to_string()]).is_err());
+
+        let options = parse_run_args(&["--profile".to_string(), "7".to_string()]).unwrap();
+        assert!(options.profile);
+        assert_eq!(options.program_args, vec!["7".to_string()]);
+    }
+
+    #[test]
+    fn test_only_declared_and_allowed_capabilities_are_granted() {
+        let declared = [Capability::IO, Capability::FileSystem];
+        let (granted, withheld) = resolve_grants(&declared, &[Capability::IO, Capability::Network]);
+        assert_eq!(granted, vec![Capability::IO]);
+        assert_eq!(withheld, vec![Capability::FileSystem]);
+    }
+
+    #[test]
+    fn test_run_denies_undeclared_print() {
+        let mut program = Program::new();
+        let text = program.constants.add_string("hi".to_string());
+        program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[text]));
+        program.add_node(Node::new(OpCode::Print, 2).with_args(&[1]));
+        program.set_entry_point(2);
+
+        // Allowing IO is not enough when the program does not declare it
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+        let allow_io = RunOptions { allowed: vec![Capability::IO], ..RunOptions::default() };
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &allow_io), 1);
+
+        program.declare_node_capabilities();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &RunOptions::default()), 1);
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &allow_io), 0);
+    }
+
+    #[test]
+    fn test_run_respects_max_steps() {
+        let mut program = Program::new();
+        let one = program.constants.add_int(1);
+        program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[one]));
+        for id in 2..=50 {
+            program.add_node(Node::new(OpCode::Add, id).with_args(&[id - 1, 1]));
+        }
+        program.set_entry_point(50);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+
+        let limited = RunOptions { max_steps: Some(10), ..RunOptions::default() };
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &limited), 1);
+        assert_eq!(run_der_file(file.path().to_str().unwrap(), &RunOptions::default()), 0);
+    }
+
+    #[test]
+    fn test_trace_lists_hello_world_in_evaluation_order() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), hello_world_program().to_bytes().unwrap()).unwrap();
+        let allow_io = RunOptions { allowed: vec![Capability::IO], ..RunOptions::default() };
+        assert_eq!(trace_der_file(file.path().to_str().unwrap(), &allow_io), 0);
+
+        let mut executor = prepare_executor(hello_world_program(), &allow_io);
+        executor.enable_tracing();
+        executor.execute().unwrap();
+        let lines = render_trace(&executor.take_trace());
+        let constant = lines.iter().position(|l| l.starts_with("[1] ConstString")).unwrap();
+        let print = lines.iter().position(|l| l.starts_with("[2] Print")).unwrap();
+        assert!(constant < print);
+        assert_eq!(lines[constant], "[1] ConstString() -> \"Hello, World!\"");
+        assert_eq!(lines[print], "[2] Print(\"Hello, World!\") -> nil");
+    }
+
+    #[test]
+    fn test_trace_ends_at_the_failing_node() {
+        let mut program = Program::new();
+        let one = program.constants.add_int(1);
+        let zero = program.constants.add_int(0);
+        program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[one]));
+        program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[zero]));
+        program.add_node(Node::new(OpCode::Div, 3).with_args(&[1, 2]));
+        program.set_entry_point(3);
+
+        let mut executor = prepare_executor(program, &RunOptions::default());
+        executor.enable_tracing();
+        assert!(executor.execute().is_err());
+        let lines = render_trace(&executor.take_trace());
+        let last = lines.last().unwrap();
+        assert!(last.starts_with("[3] Div(1, 0) !! "), "{}", last);
+    }
+
+    #[test]
+    fn test_verify_reports_invalid_program() {
+        let mut program = Program::new();
+        let one = program.constants.add_int(1);
+        program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[one]));
+        program.add_node(Node::new(OpCode::Add, 2).with_args(&[1]));
+        program.add_node(Node::new(OpCode::Random, 3));
+        program.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[2, 3]));
+        program.set_entry_point(4);
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), program.to_bytes().unwrap()).unwrap();
+        assert!(!verify_der_file(file.path().to_str().unwrap()));
+        assert!(!verify_der_file("/nonexistent/missing.der"));
+
+        let (valid, lines) = verification_report(program);
+        assert!(!valid);
+        assert!(lines.contains(&"[ERROR] node 2: Opcode Add expects 2 arguments, got 1".to_string()), "{:?}", lines);
+        assert!(lines.contains(&"  deterministic: no".to_string()));
+        assert!(lines.contains(&"  - Random number at node 3".to_string()));
+        assert_eq!(lines.last().unwrap(), "\nVerification failed");
+
+        let mut builder = ProgramBuilder::new();
+        let (a, b) = (builder.const_int(2), builder.const_int(3));
+        let sum = builder.add(a, b);
+        let (valid, lines) = verification_report(builder.build(sum));
+        assert!(valid, "{:?}", lines);
+        assert_eq!(lines.last().unwrap(), "\nVerification passed");
+    }
+
+    #[test]
+    fn test_dump_hello_world() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), hello_world_program().to_bytes().unwrap()).unwrap();
+        assert!(dump_der_file(file.path().to_str().unwrap(), false));
+        assert!(!dump_der_file("/nonexistent/missing.der", true));
+
+        let text = dump_program(hello_world_program(), false);
+        assert!(text.contains("ConstString"), "{}", text);
+        assert!(text.contains("Print"), "{}", text);
+
+        let json = dump_program(hello_world_program(), true);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let names: Vec<&str> = parsed["nodes"].as_array().unwrap().iter()
+            .map(|node| node["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["ConstString", "Print"]);
+    }
 }
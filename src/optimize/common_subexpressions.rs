@@ -0,0 +1,77 @@
+use crate::core::{Program, Node, OpCode, NodeFlag};
+use crate::verification::ProofGenerator;
+use super::is_constant_opcode;
+use std::collections::HashMap;
+
+/// Collapse pure nodes that compute the same thing from the same inputs.
+/// Every reference to a duplicate is rewritten to the first occurrence's
+/// `result_id` and the duplicate is dropped. Operands of commutative
+/// opcodes are compared in either order.
+///
+/// Returns the number of nodes removed.
+pub fn eliminate_common_subexpressions(program: &mut Program) -> usize {
+    let before = program.nodes.len();
+
+    // Merging nodes can make their consumers identical, so repeat until stable
+    loop {
+        let mut first_by_key: HashMap<(u16, u16, Vec<u32>), u32> = HashMap::new();
+        let mut replace: HashMap<u32, u32> = HashMap::new();
+
+        for node in &program.nodes {
+            if let Some(key) = expression_key(node) {
+                match first_by_key.get(&key) {
+                    Some(&first) => { replace.insert(node.result_id, first); }
+                    None => { first_by_key.insert(key, node.result_id); }
+                }
+            }
+        }
+        if replace.is_empty() {
+            break;
+        }
+
+        program.nodes.retain(|node| !replace.contains_key(&node.result_id));
+        for node in &mut program.nodes {
+            if is_constant_opcode(node.opcode) {
+                continue;
+            }
+            for arg in node.args.iter_mut().take(node.arg_count as usize) {
+                if let Some(&first) = replace.get(arg) {
+                    *arg = first;
+                }
+            }
+        }
+        if let Some(&first) = replace.get(&program.metadata.entry_point) {
+            program.metadata.entry_point = first;
+        }
+    }
+
+    before - program.nodes.len()
+}
+
+/// What identifies a node's value: opcode, flags and arguments (constant
+/// pool indices for constants). None for nodes that must not be merged.
+fn expression_key(node: &Node) -> Option<(u16, u16, Vec<u32>)> {
+    let opcode = OpCode::try_from(node.opcode).ok()?;
+    let mergeable = ProofGenerator::is_opcode_pure(&opcode)
+        && !node.has_flag(NodeFlag::Volatile)
+        && !matches!(opcode, OpCode::DefineFunc | OpCode::CreateClosure);
+    if !mergeable {
+        return None;
+    }
+
+    let mut args = node.args[..(node.arg_count as usize).min(3)].to_vec();
+    if is_commutative(opcode) {
+        args.sort_unstable();
+    }
+    Some((node.opcode, node.flags, args))
+}
+
+fn is_commutative(opcode: OpCode) -> bool {
+    matches!(
+        opcode,
+        OpCode::Add | OpCode::Mul | OpCode::Eq | OpCode::Ne |
+        OpCode::And | OpCode::Or | OpCode::Xor |
+        OpCode::BitAnd | OpCode::BitOr | OpCode::BitXor |
+        OpCode::Min | OpCode::Max
+    )
+}
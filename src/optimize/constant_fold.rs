@@ -0,0 +1,111 @@
+use crate::core::{Program, Node, OpCode, NodeFlag};
+use crate::runtime::{Executor, Value};
+use crate::verification::ProofGenerator;
+use super::{is_constant_opcode, references};
+use std::collections::{HashMap, HashSet};
+
+/// Replace pure nodes whose inputs are all constants with a single constant
+/// node, then drop the inputs nothing else reads. A folded node keeps its
+/// `result_id`, so references to it stay valid. Nodes that fail to evaluate
+/// or produce something other than a scalar are left for the runtime.
+///
+/// Returns the number of nodes folded.
+pub fn constant_fold(program: &mut Program) -> usize {
+    let original = program.clone();
+    let mut constant: HashMap<u32, bool> = HashMap::new();
+    let mut folded = Vec::new();
+
+    for index in 0..program.nodes.len() {
+        let id = program.nodes[index].result_id;
+        if is_foldable(program, id, &mut constant, &mut HashSet::new())
+            && !is_constant_opcode(program.nodes[index].opcode)
+            && fold_node(program, index)
+        {
+            folded.push(id);
+        }
+    }
+
+    remove_unused_inputs(program, &original, &folded);
+    folded.len()
+}
+
+/// Whether `id` can be computed without running anything impure: it is a
+/// constant, or a pure, non-volatile node whose inputs all qualify.
+fn is_foldable(
+    program: &Program,
+    id: u32,
+    memo: &mut HashMap<u32, bool>,
+    visiting: &mut HashSet<u32>,
+) -> bool {
+    if let Some(&known) = memo.get(&id) {
+        return known;
+    }
+    let node = match program.node_index(id) {
+        Some(index) => program.nodes[index],
+        None => return false,
+    };
+    if !visiting.insert(id) {
+        return false;
+    }
+
+    let result = is_constant_opcode(node.opcode) || (
+        !node.has_flag(NodeFlag::Volatile)
+            && matches!(OpCode::try_from(node.opcode), Ok(op) if ProofGenerator::is_opcode_pure(&op))
+            && !matches!(OpCode::try_from(node.opcode), Ok(OpCode::DefineFunc) | Ok(OpCode::CreateClosure))
+            && node.dependencies().iter().all(|dep| is_foldable(program, *dep, memo, visiting))
+    );
+
+    visiting.remove(&id);
+    memo.insert(id, result);
+    result
+}
+
+/// Evaluate the node at `index` and overwrite it in place with a constant.
+fn fold_node(program: &mut Program, index: usize) -> bool {
+    let node = program.nodes[index];
+    let mut scratch = program.clone();
+    scratch.set_entry_point(node.result_id);
+
+    let (opcode, constant) = match Executor::new(scratch).execute() {
+        Ok(Value::Int(value)) => (OpCode::ConstInt, program.constants.add_int(value)),
+        Ok(Value::Float(value)) => (OpCode::ConstFloat, program.constants.add_float(value)),
+        Ok(Value::String(value)) => (OpCode::ConstString, program.constants.add_string(value)),
+        Ok(Value::Bool(value)) => (OpCode::ConstBool, program.constants.add_bool(value)),
+        _ => return false,
+    };
+
+    let mut replacement = Node::new(opcode, node.result_id).with_args(&[constant]);
+    replacement.flags = node.flags;
+    replacement.timestamp = node.timestamp;
+    program.nodes[index] = replacement;
+    true
+}
+
+/// Drop nodes that only fed folded nodes and that nothing reads any more.
+fn remove_unused_inputs(program: &mut Program, original: &Program, folded: &[u32]) {
+    let mut candidates: HashSet<u32> = folded.iter().copied().collect();
+    let mut pending: Vec<u32> = folded.to_vec();
+    while let Some(id) = pending.pop() {
+        if let Some(index) = original.node_index(id) {
+            for dep in references(&original.nodes[index]) {
+                if candidates.insert(dep) {
+                    pending.push(dep);
+                }
+            }
+        }
+    }
+
+    loop {
+        let referenced: HashSet<u32> = program.nodes.iter().flat_map(references).collect();
+        let entry_point = program.metadata.entry_point;
+        let before = program.nodes.len();
+        program.nodes.retain(|node| {
+            !candidates.contains(&node.result_id)
+                || node.result_id == entry_point
+                || referenced.contains(&node.result_id)
+        });
+        if program.nodes.len() == before {
+            break;
+        }
+    }
+}
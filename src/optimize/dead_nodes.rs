@@ -0,0 +1,36 @@
+use crate::core::{Program, OpCode};
+use super::references;
+use std::collections::HashSet;
+
+/// Drop every node the entry point can't reach through its arguments.
+/// AsyncComplete nodes are kept when their handle is reachable, since an
+/// await finds them by scanning the graph rather than through an argument.
+///
+/// Returns the number of nodes removed.
+pub fn eliminate_dead_nodes(program: &mut Program) -> usize {
+    let mut reachable = HashSet::new();
+    let mut pending = vec![program.metadata.entry_point];
+
+    loop {
+        while let Some(id) = pending.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(index) = program.node_index(id) {
+                pending.extend(references(&program.nodes[index]));
+            }
+        }
+
+        pending.extend(program.nodes.iter()
+            .filter(|node| matches!(OpCode::try_from(node.opcode), Ok(OpCode::AsyncComplete)))
+            .filter(|node| !reachable.contains(&node.result_id) && reachable.contains(&node.args[0]))
+            .map(|node| node.result_id));
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    let before = program.nodes.len();
+    program.nodes.retain(|node| reachable.contains(&node.result_id));
+    before - program.nodes.len()
+}
@@ -0,0 +1,28 @@
+pub mod constant_fold;
+pub mod dead_nodes;
+pub mod common_subexpressions;
+
+pub use constant_fold::*;
+pub use dead_nodes::*;
+pub use common_subexpressions::*;
+
+use crate::core::{Node, OpCode};
+
+fn is_constant_opcode(opcode: u16) -> bool {
+    matches!(
+        OpCode::try_from(opcode),
+        Ok(OpCode::ConstInt) | Ok(OpCode::ConstFloat) | Ok(OpCode::ConstString) | Ok(OpCode::ConstBool)
+    )
+}
+
+/// Result ids a node refers to, including DefineFunc bodies.
+fn references(node: &Node) -> Vec<u32> {
+    if is_constant_opcode(node.opcode) {
+        return Vec::new();
+    }
+    node.args[..(node.arg_count as usize).min(3)]
+        .iter()
+        .copied()
+        .filter(|id| *id != 0)
+        .collect()
+}
@@ -1,8 +1,10 @@
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-use std::task::{Context, Poll, Waker};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
 use crate::runtime::{Value, RuntimeError, Result};
 
 #[derive(Debug, Clone)]
@@ -17,6 +19,8 @@ pub struct AsyncState {
     pub result: Option<Value>,
     pub waker: Option<Waker>,
     pub error: Option<RuntimeError>,
+    /// Awaits on this handle give up with `AsyncTimeout` after this instant
+    pub deadline: Option<Instant>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,10 +31,13 @@ pub enum AsyncStatus {
     Failed,
 }
 
+type TaskFuture = Pin<Box<dyn Future<Output = Result<Value>> + Send>>;
+
 pub struct AsyncRuntime {
     next_id: u64,
     tasks: HashMap<u64, AsyncHandle>,
-    pending_futures: Vec<Pin<Box<dyn Future<Output = Result<Value>> + Send>>>,
+    /// Futures driving a handle to completion, polled by `run_until_complete`
+    pending_futures: Vec<(AsyncHandle, TaskFuture)>,
 }
 
 impl AsyncRuntime {
@@ -51,6 +58,7 @@ impl AsyncRuntime {
             result: None,
             waker: None,
             error: None,
+            deadline: None,
         }));
         
         let handle = AsyncHandle { id, state };
@@ -59,6 +67,96 @@ impl AsyncRuntime {
         handle
     }
     
+    /// Start a task whose handle completes with whatever `future` produces.
+    pub fn spawn<F>(&mut self, future: F) -> AsyncHandle
+    where
+        F: Future<Output = Result<Value>> + Send + 'static,
+    {
+        let handle = self.begin_async();
+        self.schedule(&handle, future);
+        handle
+    }
+    
+    /// Complete an existing handle with whatever `future` produces.
+    pub fn schedule<F>(&mut self, handle: &AsyncHandle, future: F)
+    where
+        F: Future<Output = Result<Value>> + Send + 'static,
+    {
+        self.pending_futures.push((handle.clone(), Box::pin(future)));
+    }
+    
+    /// Poll spawned futures until `handle` settles and return its result.
+    /// Fails instead of blocking forever once nothing is left that could
+    /// complete it, unless a deadline is set, in which case it waits that out.
+    pub fn run_until_complete(&mut self, handle: &AsyncHandle) -> Result<Value> {
+        let signal = Arc::new(WakeSignal(AtomicBool::new(true)));
+        let waker = Waker::from(signal.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut awaiter = self.await_async(handle);
+        
+        loop {
+            if let Poll::Ready(result) = Pin::new(&mut awaiter).poll(&mut cx) {
+                return result;
+            }
+            
+            let deadline = handle.state.lock().unwrap().deadline;
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                return Err(RuntimeError::AsyncTimeout(handle.id));
+            }
+            
+            let woken = signal.0.swap(false, Ordering::SeqCst);
+            if self.pending_futures.is_empty() || !woken {
+                match deadline {
+                    Some(deadline) => {
+                        std::thread::sleep(deadline - now);
+                        continue;
+                    }
+                    None => return Err(RuntimeError::InvalidOperation(format!(
+                        "Await on async #{} can never complete", handle.id
+                    ))),
+                }
+            }
+            
+            std::thread::yield_now();
+            let mut index = 0;
+            while index < self.pending_futures.len() {
+                let (_, future) = &mut self.pending_futures[index];
+                match future.as_mut().poll(&mut cx) {
+                    Poll::Ready(result) => {
+                        let (task, _) = self.pending_futures.remove(index);
+                        match result {
+                            Ok(value) => self.complete_async(&task, value)?,
+                            Err(error) => self.fail_async(&task, error)?,
+                        }
+                    }
+                    Poll::Pending => index += 1,
+                }
+            }
+        }
+    }
+    
+    /// Like `run_until_complete`, but fail with `AsyncTimeout` if `handle`
+    /// has not settled within `timeout`.
+    pub fn await_with_timeout(&mut self, handle: &AsyncHandle, timeout: Duration) -> Result<Value> {
+        handle.state.lock().unwrap().deadline = Some(Instant::now() + timeout);
+        self.run_until_complete(handle)
+    }
+    
+    /// Wait for every handle and collect their results in order. The first
+    /// failed handle (in argument order) fails the whole join.
+    pub fn join(&mut self, handles: &[AsyncHandle]) -> Result<Vec<Value>> {
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            let result = match self.get_result(handle)? {
+                Some(value) => value,
+                None => self.run_until_complete(handle)?,
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+    
     pub fn await_async(&self, handle: &AsyncHandle) -> AsyncAwaiter {
         AsyncAwaiter {
             handle: handle.clone(),
@@ -141,6 +239,44 @@ impl AsyncRuntime {
     }
 }
 
+/// Future that resolves to `Nil` once `duration` has passed. It asks to be
+/// polled again until then, so `run_until_complete` keeps spinning on it.
+pub struct Delay {
+    deadline: Instant,
+}
+
+impl Delay {
+    pub fn new(duration: Duration) -> Self {
+        Delay { deadline: Instant::now() + duration }
+    }
+}
+
+impl Future for Delay {
+    type Output = Result<Value>;
+    
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            Poll::Ready(Ok(Value::Nil))
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Waker that records a wake-up for the `run_until_complete` loop.
+struct WakeSignal(AtomicBool);
+
+impl Wake for WakeSignal {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
 pub struct AsyncAwaiter {
     handle: AsyncHandle,
 }
@@ -310,4 +446,17 @@ mod tests {
         assert_eq!(runtime.tasks.len(), 1);
         assert!(runtime.tasks.contains_key(&handle3.id));
     }
+    
+    #[test]
+    fn test_run_until_complete_drives_spawned_futures() {
+        let mut runtime = AsyncRuntime::new();
+        let handle = runtime.spawn(async { Ok(Value::Int(7)) });
+        
+        assert_eq!(runtime.get_status(&handle), AsyncStatus::Pending);
+        assert_eq!(runtime.run_until_complete(&handle).unwrap(), Value::Int(7));
+        assert_eq!(runtime.get_status(&handle), AsyncStatus::Completed);
+        
+        let stuck = runtime.begin_async();
+        assert!(runtime.run_until_complete(&stuck).is_err());
+    }
 }
\ No newline at end of file
@@ -1,33 +1,97 @@
-use std::collections::HashMap;
-use crate::core::{Program, Capability};
-use crate::runtime::{Value, RuntimeError, Result, MemoryManager, AsyncRuntime};
+use std::collections::{HashMap, HashSet};
+use crate::core::{Program, Capability, OpCode};
+use crate::runtime::{Value, RuntimeError, Result, MemoryManager, AsyncRuntime, HostFunctionRegistry, TraceEvent, Profiler};
+
+/// Called with a node's result id and the values computed so far just
+/// before the node's opcode runs; an error stops the run.
+pub type BreakpointHook = Box<dyn FnMut(u32, &HashMap<u32, Value>) -> Result<()>>;
 
 pub struct ExecutionContext {
     pub program: Program,
+    /// Position of each result id in `program.nodes`; the first node wins
+    node_index: HashMap<u32, usize>,
     pub values: HashMap<u32, Value>,
+    /// Address and generation each cached Load result was read at
+    pub memory_reads: HashMap<u32, (u64, u64)>,
     pub call_stack: Vec<CallFrame>,
     pub granted_capabilities: Vec<Capability>,
     pub max_call_depth: usize,
+    /// Current and maximum nesting of node evaluations
+    pub depth: usize,
+    pub max_depth: usize,
     pub memory: MemoryManager,
     pub async_runtime: AsyncRuntime,
+    pub host_functions: HostFunctionRegistry,
+    /// When set, Print appends here instead of writing to stdout
+    pub output: Option<String>,
+    /// SplitMix64 state behind the Random opcode
+    pub rng_state: u64,
+    /// Overrides the system clock behind the Now opcode
+    pub clock: Option<Box<dyn Fn() -> i64>>,
+    /// Consulted by GetEnv before the process environment
+    pub env_overrides: HashMap<String, String>,
+    /// Longest an HttpGet request may take, connection included
+    pub http_timeout: std::time::Duration,
+    /// Node evaluations in the current run, and the most allowed
+    pub steps: u64,
+    pub step_limit: Option<u64>,
+    /// Longest a run may take, and when the current run must stop
+    pub timeout: Option<std::time::Duration>,
+    pub deadline: Option<std::time::Instant>,
+    /// Node evaluations recorded while tracing is enabled
+    pub trace: Option<Vec<TraceEvent>>,
+    /// Opcode and node counters collected while profiling is enabled
+    pub profiler: Option<Profiler>,
+    pub breakpoint_hook: Option<BreakpointHook>,
+    /// Nodes in tail position of each function body, by body node
+    pub tail_positions: HashMap<u32, HashSet<u32>>,
+    /// Function body and parameters of a tail call waiting to reuse the
+    /// current frame
+    pub pending_tail_call: Option<(u32, Vec<Value>)>,
 }
 
 pub struct CallFrame {
     pub node_id: u32,
     pub locals: HashMap<u32, Value>,
+    /// Values passed by the Call, read with LoadParam
+    pub params: Vec<Value>,
     pub return_to: Option<u32>,
 }
 
 impl ExecutionContext {
     pub fn new(program: Program) -> Self {
+        let mut node_index = HashMap::new();
+        for (index, node) in program.nodes.iter().enumerate() {
+            node_index.entry(node.result_id).or_insert(index);
+        }
+
         ExecutionContext {
             program,
+            node_index,
             values: HashMap::new(),
+            memory_reads: HashMap::new(),
             call_stack: Vec::new(),
             granted_capabilities: Vec::new(),
-            max_call_depth: 1000,
+            max_call_depth: 1024,
+            depth: 0,
+            max_depth: 1000,
             memory: MemoryManager::new(),
             async_runtime: AsyncRuntime::new(),
+            host_functions: HostFunctionRegistry::new(),
+            output: None,
+            rng_state: time_seed(),
+            clock: None,
+            env_overrides: HashMap::new(),
+            http_timeout: std::time::Duration::from_secs(30),
+            steps: 0,
+            step_limit: None,
+            timeout: None,
+            deadline: None,
+            trace: None,
+            profiler: None,
+            breakpoint_hook: None,
+            tail_positions: HashMap::new(),
+            pending_tail_call: None,
         }
     }
 
@@ -37,6 +101,49 @@ impl ExecutionContext {
         }
     }
 
+    /// Start counting steps and time for a new run.
+    pub fn start_run(&mut self) {
+        self.steps = 0;
+        self.deadline = self.timeout.map(|timeout| std::time::Instant::now() + timeout);
+    }
+
+    /// Count one node evaluation, failing once the run is over its step
+    /// budget. The clock is only consulted every 1024 steps.
+    pub fn count_step(&mut self) -> Result<()> {
+        self.steps += 1;
+        if let Some(limit) = self.step_limit {
+            if self.steps > limit {
+                return Err(RuntimeError::LimitExceeded(format!("step limit of {} reached", limit)));
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.timeout) {
+            if self.steps.is_multiple_of(1024) && std::time::Instant::now() >= deadline {
+                return Err(RuntimeError::LimitExceeded(format!("timeout of {}ms reached", timeout.as_millis())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Advance the random generator and return its next 64-bit output.
+    pub fn next_random(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Current Unix time in milliseconds, from the injected clock if any.
+    pub fn now_millis(&self) -> i64 {
+        match &self.clock {
+            Some(clock) => clock(),
+            None => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as i64)
+                .unwrap_or(0),
+        }
+    }
+
     pub fn check_capability(&self, cap: &Capability) -> Result<()> {
         if self.granted_capabilities.contains(cap) {
             Ok(())
@@ -45,20 +152,46 @@ impl ExecutionContext {
         }
     }
 
-    pub fn push_frame(&mut self, node_id: u32, return_to: Option<u32>) -> Result<()> {
+    /// Fail unless the capability `opcode` needs was granted to this
+    /// execution. Only the granted set counts; the program's declared
+    /// requirements grant nothing.
+    pub fn require_capability(&self, opcode: OpCode) -> Result<()> {
+        match opcode.required_capability() {
+            Some(capability) if !self.granted_capabilities.contains(&capability) => {
+                Err(RuntimeError::CapabilityDenied { opcode, capability })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn push_frame(&mut self, node_id: u32, params: Vec<Value>, return_to: Option<u32>) -> Result<()> {
         if self.call_stack.len() >= self.max_call_depth {
-            return Err(RuntimeError::StackOverflow);
+            return Err(RuntimeError::StackOverflow {
+                depth: self.call_stack.len(),
+                function: Some(node_id),
+            });
         }
 
         self.call_stack.push(CallFrame {
             node_id,
             locals: HashMap::new(),
+            params,
             return_to,
         });
 
         Ok(())
     }
 
+    /// Turn the current frame into a fresh call of `node_id`, for a tail
+    /// call. Results of the finished call are dropped.
+    pub fn reuse_frame(&mut self, node_id: u32, params: Vec<Value>) {
+        if let Some(frame) = self.call_stack.last_mut() {
+            frame.node_id = node_id;
+            frame.locals.clear();
+            frame.params = params;
+        }
+    }
+
     pub fn pop_frame(&mut self) -> Option<CallFrame> {
         self.call_stack.pop()
     }
@@ -71,13 +204,38 @@ impl ExecutionContext {
         self.call_stack.last_mut()
     }
 
+    /// Memoize a result. Inside a call it is kept in the call's frame and
+    /// dropped with it, so another call evaluates the body afresh.
     pub fn set_value(&mut self, result_id: u32, value: Value) {
+        match self.current_frame_mut() {
+            Some(frame) => {
+                frame.locals.insert(result_id, value);
+            }
+            None => {
+                self.values.insert(result_id, value);
+            }
+        }
+    }
+
+    /// Forget a memoized result so the node is evaluated again on next use.
+    pub fn clear_value(&mut self, result_id: u32) {
         if let Some(frame) = self.current_frame_mut() {
-            frame.locals.insert(result_id, value.clone());
+            frame.locals.remove(&result_id);
+        }
+        self.values.remove(&result_id);
+        self.memory_reads.remove(&result_id);
+    }
+
+    /// Whether a cached Load result predates a later store or free of its address.
+    pub fn is_stale(&self, result_id: u32) -> bool {
+        match self.memory_reads.get(&result_id) {
+            Some(&(address, generation)) => self.memory.generation(address) != Some(generation),
+            None => false,
         }
-        self.values.insert(result_id, value);
     }
 
+    /// A result memoized by the current call, or else at the top level.
+    /// Results of enclosing calls are not visible.
     pub fn get_value(&self, result_id: u32) -> Option<&Value> {
         // First check current frame locals
         if let Some(frame) = self.current_frame() {
@@ -90,6 +248,12 @@ impl ExecutionContext {
     }
 
     pub fn get_node(&self, result_id: u32) -> Option<&crate::core::Node> {
-        self.program.nodes.iter().find(|n| n.result_id == result_id)
+        self.node_index.get(&result_id).map(|&index| &self.program.nodes[index])
     }
-}
\ No newline at end of file
+}
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
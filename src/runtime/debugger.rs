@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use crate::core::{Capability, Program};
+use crate::runtime::{Executor, Value, RuntimeError, Result};
+
+/// Execution stopped just before a breakpoint node ran.
+#[derive(Debug, Clone)]
+pub struct DebugPause {
+    pub node_id: u32,
+    /// Every result computed so far, by result id
+    pub partial_values: HashMap<u32, Value>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DebugEvent {
+    Paused(DebugPause),
+    Finished(Value),
+}
+
+enum WorkerMessage {
+    Paused(DebugPause),
+    Finished(Result<Value>),
+}
+
+struct Session {
+    events: Receiver<WorkerMessage>,
+    resume: Sender<()>,
+    worker: JoinHandle<()>,
+}
+
+/// Runs a program with breakpoints on node ids. The program executes on a
+/// worker thread that blocks at each breakpoint until `resume` is called,
+/// so execution continues exactly where it stopped.
+pub struct DebugExecutor {
+    program: Option<Program>,
+    capabilities: Vec<Capability>,
+    breakpoints: Arc<Mutex<HashSet<u32>>>,
+    session: Option<Session>,
+    pause: Option<DebugPause>,
+}
+
+impl DebugExecutor {
+    pub fn new(program: Program) -> Self {
+        DebugExecutor {
+            program: Some(program),
+            capabilities: Vec::new(),
+            breakpoints: Arc::new(Mutex::new(HashSet::new())),
+            session: None,
+            pause: None,
+        }
+    }
+
+    /// Grant a capability to the run. Only takes effect before it starts.
+    pub fn grant_capability(&mut self, cap: Capability) {
+        self.capabilities.push(cap);
+    }
+
+    /// Pause before the node producing `node_id` runs. Breakpoints can be
+    /// changed while paused.
+    pub fn set_breakpoint(&mut self, node_id: u32) {
+        self.breakpoints.lock().unwrap().insert(node_id);
+    }
+
+    pub fn clear_breakpoint(&mut self, node_id: u32) {
+        self.breakpoints.lock().unwrap().remove(&node_id);
+    }
+
+    /// Start the program, or continue it if paused, and run until the next
+    /// breakpoint or the end.
+    pub fn run_until_break(&mut self) -> Result<DebugEvent> {
+        if self.pause.is_some() {
+            return self.resume();
+        }
+        match self.program.take() {
+            Some(program) => {
+                self.session = Some(self.start(program));
+                self.next_event()
+            }
+            None => Err(RuntimeError::InvalidOperation("Debugged program has already finished".to_string())),
+        }
+    }
+
+    /// Continue from the current pause.
+    pub fn resume(&mut self) -> Result<DebugEvent> {
+        if self.pause.take().is_none() {
+            return Err(RuntimeError::InvalidOperation("Debugged program is not paused".to_string()));
+        }
+        if let Some(session) = &self.session {
+            // A send error means the worker is gone; next_event reports it
+            let _ = session.resume.send(());
+        }
+        self.next_event()
+    }
+
+    /// Value computed for `result_id` as of the current pause.
+    pub fn inspect(&self, result_id: u32) -> Option<&Value> {
+        self.pause.as_ref()?.partial_values.get(&result_id)
+    }
+
+    fn start(&self, program: Program) -> Session {
+        let (event_tx, events) = mpsc::channel();
+        let (resume, resume_rx) = mpsc::channel::<()>();
+        let breakpoints = Arc::clone(&self.breakpoints);
+        let capabilities = self.capabilities.clone();
+
+        let worker = std::thread::spawn(move || {
+            let mut executor = Executor::new(program);
+            for cap in capabilities {
+                executor.grant_capability(cap);
+            }
+
+            let pause_tx = event_tx.clone();
+            executor.set_breakpoint_hook(Box::new(move |node_id, values| {
+                if !breakpoints.lock().unwrap().contains(&node_id) {
+                    return Ok(());
+                }
+                let pause = DebugPause { node_id, partial_values: values.clone() };
+                if pause_tx.send(WorkerMessage::Paused(pause)).is_err() || resume_rx.recv().is_err() {
+                    return Err(RuntimeError::InvalidOperation("Debugger detached".to_string()));
+                }
+                Ok(())
+            }));
+
+            let _ = event_tx.send(WorkerMessage::Finished(executor.execute()));
+        });
+
+        Session { events, resume, worker }
+    }
+
+    fn next_event(&mut self) -> Result<DebugEvent> {
+        let message = self.session.as_ref()
+            .and_then(|session| session.events.recv().ok());
+        match message {
+            Some(WorkerMessage::Paused(pause)) => {
+                self.pause = Some(pause.clone());
+                Ok(DebugEvent::Paused(pause))
+            }
+            Some(WorkerMessage::Finished(result)) => {
+                if let Some(session) = self.session.take() {
+                    let _ = session.worker.join();
+                }
+                result.map(DebugEvent::Finished)
+            }
+            None => {
+                self.session = None;
+                Err(RuntimeError::InvalidOperation("Debugged execution stopped unexpectedly".to_string()))
+            }
+        }
+    }
+}
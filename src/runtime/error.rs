@@ -23,6 +23,9 @@ pub enum RuntimeError {
     #[error("Division by zero")]
     DivisionByZero,
 
+    #[error("Integer overflow: {0}")]
+    IntegerOverflow(String),
+
     #[error("Invalid argument count: expected {expected}, got {actual}")]
     InvalidArgCount {
         expected: usize,
@@ -32,6 +35,21 @@ pub enum RuntimeError {
     #[error("Missing capability: {0:?}")]
     MissingCapability(crate::core::Capability),
 
+    #[error("Execution limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    #[error("Network error for {url}: {message}")]
+    NetworkError {
+        url: String,
+        message: String,
+    },
+
+    #[error("Capability denied: {opcode:?} requires {capability:?}, which has not been granted")]
+    CapabilityDenied {
+        opcode: crate::core::OpCode,
+        capability: crate::core::Capability,
+    },
+
     #[error("Invalid constant index: {0}")]
     InvalidConstantIndex(u32),
 
@@ -41,11 +59,37 @@ pub enum RuntimeError {
         length: usize,
     },
 
+    #[error("Memory limit exceeded: requested {requested} bytes with a limit of {limit}")]
+    MemoryLimitExceeded {
+        requested: usize,
+        limit: usize,
+    },
+
     #[error("Map key not found: {0}")]
     MapKeyNotFound(String),
 
-    #[error("Maximum call depth exceeded")]
-    StackOverflow,
+    /// Nesting limit hit: of node evaluations, or of calls when `function`
+    /// is the body node of the function being called.
+    #[error("{}", stack_overflow_message(*.depth, *.function))]
+    StackOverflow {
+        depth: usize,
+        function: Option<u32>,
+    },
+
+    #[error("Async operation #{0} timed out")]
+    AsyncTimeout(u64),
+
+    #[error("User error: {0}")]
+    UserError(String),
+
+    #[error("Assertion failed at node {node_id}: {message}")]
+    AssertionFailed {
+        node_id: u32,
+        message: String,
+    },
+
+    #[error("Program exited with status {0}")]
+    Exit(i32),
 
     #[error("IO error: {0}")]
     IOError(String),
@@ -57,4 +101,11 @@ pub enum RuntimeError {
     ProofVerificationFailed(String),
 }
 
-pub type Result<T> = std::result::Result<T, RuntimeError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+fn stack_overflow_message(depth: usize, function: Option<u32>) -> String {
+    match function {
+        Some(node_id) => format!("Maximum call depth exceeded at depth {} calling the function at node {}", depth, node_id),
+        None => format!("Maximum evaluation depth exceeded at depth {}", depth),
+    }
+}
\ No newline at end of file
@@ -1,7 +1,11 @@
 use std::sync::Arc;
-use std::collections::HashMap;
-use crate::core::{Program, Node, OpCode, NodeFlag, Capability};
-use crate::runtime::{ExecutionContext, Value, Function, RuntimeError, Result, MemoryReference};
+use std::collections::{HashMap, HashSet};
+use crate::core::{Program, Node, OpCode, NodeFlag, Capability, CAST_TARGETS};
+use crate::runtime::{ExecutionContext, Value, MapKey, Function, RuntimeError, Result, MemoryReference, MemoryStats, AsyncHandle, AsyncStatus, Delay, TraceEvent, Profiler, ProfileReport, BreakpointHook, collect_memory_refs};
+
+/// Value slot holding the message of the error the innermost Try caught.
+/// Handlers read it by using the slot as an argument, like argc at 999.
+pub const CAUGHT_ERROR_SLOT: u32 = 998;
 
 pub struct Executor {
     context: ExecutionContext,
@@ -18,6 +22,47 @@ impl Executor {
         self.context.grant_capability(cap);
     }
 
+    /// Make `function` callable from the program as `ExternalCall(name, ...)`.
+    /// Calls still require the ExternalCode capability.
+    pub fn register_external<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value> + 'static,
+    {
+        self.context.host_functions.register(name, Box::new(function));
+    }
+
+    /// Limit how deeply node evaluations may nest before failing with
+    /// `StackOverflow` instead of exhausting the native stack.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.context.max_depth = depth;
+    }
+
+    /// Limit how many function calls may be active at once; deeper calls
+    /// fail with `StackOverflow` naming the function. The default is 1024.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.context.max_call_depth = depth;
+    }
+
+    /// Seed the generator behind `Random` so runs can be reproduced.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.context.rng_state = seed;
+    }
+
+    /// Replace the wall clock behind `Now`, e.g. with a fake one in tests.
+    pub fn set_clock(&mut self, clock: Box<dyn Fn() -> i64>) {
+        self.context.clock = Some(clock);
+    }
+
+    /// Make GetEnv see `value` for `key` regardless of the real environment.
+    pub fn set_env_override(&mut self, key: &str, value: &str) {
+        self.context.env_overrides.insert(key.to_string(), value.to_string());
+    }
+
+    /// Bound how long a single HttpGet may take. The default is 30 seconds.
+    pub fn set_http_timeout(&mut self, timeout: std::time::Duration) {
+        self.context.http_timeout = timeout;
+    }
+
     pub fn set_argument(&mut self, index: usize, value: Value) {
         // Set argument at predefined slots (1000+)
         self.context.set_value(1000 + index as u32, value);
@@ -28,46 +73,265 @@ impl Executor {
         self.context.set_value(999, Value::Int(count as i64));
     }
 
+    /// Collect Print output in memory instead of writing it to stdout.
+    pub fn capture_output(&mut self) {
+        self.context.output = Some(String::new());
+    }
+
+    /// Take the output collected since `capture_output` was called.
+    pub fn take_output(&mut self) -> String {
+        self.context.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Limit the bytes this program may hold allocated at once; further
+    /// `Alloc`s fail with `MemoryLimitExceeded`.
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.context.memory.set_allocation_limit(bytes);
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.context.memory.get_stats()
+    }
+
+    /// Sweep heap objects no longer reachable from any value the executor
+    /// holds (cached node results and arguments). Returns how many were
+    /// removed; freed objects are always removed.
+    pub fn collect_garbage(&mut self) -> usize {
+        let mut roots = Vec::new();
+        for value in self.context.values.values() {
+            collect_memory_refs(value, &mut roots);
+        }
+        self.context.memory.collect_unreachable(&roots)
+    }
+
+    /// Stop a run with `LimitExceeded` after this many node evaluations.
+    /// Runs are unlimited by default.
+    pub fn set_step_limit(&mut self, steps: u64) {
+        self.context.step_limit = Some(steps);
+    }
+
+    /// Stop a run with `LimitExceeded` once it has taken longer than
+    /// `timeout`. The clock is checked periodically, not after every node.
+    pub fn set_timeout(&mut self, timeout: std::time::Duration) {
+        self.context.timeout = Some(timeout);
+    }
+
+    /// Record every node evaluation from now on; see `take_trace`.
+    pub fn enable_tracing(&mut self) {
+        self.context.trace.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the events recorded since tracing was enabled or the trace was
+    /// last taken. Tracing stays enabled.
+    pub fn take_trace(&mut self) -> Vec<TraceEvent> {
+        self.context.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Count opcode executions, their cumulative time and per-node hits
+    /// from now on; see `profile_report`.
+    pub fn enable_profiling(&mut self) {
+        self.context.profiler.get_or_insert_with(Profiler::new);
+    }
+
+    /// Counters collected since profiling was enabled, or `None` if it never was.
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        self.context.profiler.as_ref().map(Profiler::report)
+    }
+
+    /// Run `hook` before every opcode dispatch; `DebugExecutor` builds
+    /// breakpoints on it.
+    pub(crate) fn set_breakpoint_hook(&mut self, hook: BreakpointHook) {
+        self.context.breakpoint_hook = Some(hook);
+    }
+
+    /// Node evaluations performed by the last run.
+    pub fn steps(&self) -> u64 {
+        self.context.steps
+    }
+
     pub fn execute(&mut self) -> Result<Value> {
+        self.context.start_run();
         let entry_point = self.context.program.metadata.entry_point;
         self.execute_node(entry_point)
     }
 
+    /// Evaluate a node, counting how deeply evaluations are nested. Nesting
+    /// happens when an opcode evaluates a lazy argument or a function body,
+    /// and each level uses native stack, so it is capped by `max_depth`.
     fn execute_node(&mut self, node_id: u32) -> Result<Value> {
-        let node = self.context.get_node(node_id)
-            .ok_or(RuntimeError::InvalidNodeRef(node_id))?
-            .clone();
+        if self.context.depth >= self.context.max_depth {
+            return Err(RuntimeError::StackOverflow { depth: self.context.depth, function: None });
+        }
+
+        self.context.depth += 1;
+        let result = self.evaluate_node(node_id);
+        self.context.depth -= 1;
+        result
+    }
+
+    /// Evaluate a node and everything it needs.
+    ///
+    /// Dependencies that an opcode always evaluates are resolved with an
+    /// explicit work stack, so long data-flow chains don't consume native
+    /// stack. Lazily evaluated arguments (Branch arms, the right side of
+    /// And/Or, loop bodies) are still resolved on demand by the opcode.
+    fn evaluate_node(&mut self, node_id: u32) -> Result<Value> {
+        if self.context.get_node(node_id).is_none() {
+            return Err(RuntimeError::InvalidNodeRef(node_id));
+        }
+
+        // (result id, eager arguments already scheduled)
+        let mut stack = vec![(node_id, false)];
+        let mut in_progress = HashSet::new();
 
-        // Check if we've already computed this value
-        if let Some(value) = self.context.get_value(node.result_id) {
-            return Ok(value.clone());
+        while let Some(&(id, expanded)) = stack.last() {
+            let node = match self.context.get_node(id) {
+                Some(node) => *node,
+                None => return Err(RuntimeError::InvalidNodeRef(id)),
+            };
+
+            // Check if we've already computed this value. Volatile nodes are
+            // recomputed each time a consumer schedules them.
+            if !expanded && (self.context.is_stale(id) || node.has_flag(NodeFlag::Volatile)) {
+                self.context.clear_value(id);
+            }
+            if self.context.get_value(node.result_id).is_some() {
+                stack.pop();
+                in_progress.remove(&id);
+                continue;
+            }
+
+            let opcode = OpCode::try_from(node.opcode)
+                .map_err(|_| RuntimeError::UnknownOpcode(node.opcode))?;
+
+            if !expanded {
+                stack.last_mut().unwrap().1 = true;
+                in_progress.insert(id);
+
+                // Push in reverse so arguments are evaluated left to right
+                for &arg_id in Self::eager_args(opcode, &node).iter().rev() {
+                    if in_progress.contains(&arg_id) {
+                        return Err(RuntimeError::InvalidOperation(
+                            format!("Cyclic dependency through node {}", arg_id)
+                        ));
+                    }
+                    let needs_run = match self.context.get_node(arg_id) {
+                        Some(arg) => arg.has_flag(NodeFlag::Volatile)
+                            || self.context.is_stale(arg_id)
+                            || self.context.get_value(arg_id).is_none(),
+                        None => false,
+                    };
+                    if needs_run {
+                        stack.push((arg_id, false));
+                    }
+                }
+                continue;
+            }
+
+            // Execute based on opcode
+            self.context.count_step()?;
+            if let Some(hook) = self.context.breakpoint_hook.as_mut() {
+                hook(node.result_id, &self.context.values)?;
+            }
+            let result = if self.context.trace.is_some() || self.context.profiler.is_some() {
+                self.execute_instrumented(opcode, &node)?
+            } else {
+                self.execute_opcode(opcode, &node)?
+            };
+
+            // Store the result
+            self.context.set_value(node.result_id, result);
+            stack.pop();
+            in_progress.remove(&id);
         }
 
-        // Execute based on opcode
-        let result = match OpCode::try_from(node.opcode) {
-            Ok(opcode) => self.execute_opcode(opcode, &node)?,
-            Err(_) => return Err(RuntimeError::UnknownOpcode(node.opcode)),
+        self.context.get_value(node_id)
+            .cloned()
+            .ok_or(RuntimeError::InvalidNodeRef(node_id))
+    }
+
+    /// Run an opcode while tracing or profiling is enabled, recording it.
+    fn execute_instrumented(&mut self, opcode: OpCode, node: &Node) -> Result<Value> {
+        let args_resolved = if self.context.trace.is_some() {
+            Self::eager_args(opcode, node).iter()
+                .filter_map(|id| self.context.get_value(*id).cloned())
+                .collect()
+        } else {
+            Vec::new()
         };
+        let started = std::time::Instant::now();
+        let result = self.execute_opcode(opcode, node);
+        let duration = started.elapsed();
 
-        // Store the result
-        self.context.set_value(node.result_id, result.clone());
+        if let Some(profiler) = self.context.profiler.as_mut() {
+            profiler.record(node.result_id, opcode, duration);
+        }
+        if let Some(trace) = self.context.trace.as_mut() {
+            trace.push(TraceEvent {
+                node_id: node.result_id,
+                opcode,
+                args_resolved,
+                result: result.clone(),
+                duration,
+            });
+        }
+        result
+    }
 
-        Ok(result)
+    /// Arguments an opcode unconditionally evaluates before doing its work.
+    fn eager_args(opcode: OpCode, node: &Node) -> Vec<u32> {
+        let count = match opcode {
+            OpCode::Nop | OpCode::DefineFunc | OpCode::Try |
+            OpCode::ConstInt | OpCode::ConstFloat | OpCode::ConstString | OpCode::ConstBool => 0,
+            OpCode::Branch | OpCode::And | OpCode::Or | OpCode::Loop |
+            OpCode::CreateClosure | OpCode::LoadArg => 1,
+            _ => node.arg_count as usize,
+        };
+        node.args[..count.min(node.arg_count as usize).min(3)]
+            .iter()
+            .copied()
+            .filter(|id| *id != 0)
+            .collect()
     }
 
     fn execute_opcode(&mut self, opcode: OpCode, node: &Node) -> Result<Value> {
+        self.context.require_capability(opcode)?;
+
         match opcode {
             OpCode::Nop => Ok(Value::Nil),
             OpCode::Return => self.execute_return(node),
             OpCode::Call => self.execute_call(node),
             OpCode::Branch => self.execute_branch(node),
+            OpCode::Exit => self.execute_exit(node),
+            OpCode::Assert => self.execute_assert(node),
+            OpCode::Try => self.execute_try(node),
+            OpCode::Throw => match self.get_arg_value(node, 0)? {
+                Value::String(message) => Err(RuntimeError::UserError(message)),
+                other => Err(RuntimeError::UserError(other.to_string())),
+            },
+            OpCode::Loop => self.execute_loop(node),
+            OpCode::Seq => self.execute_seq(node),
             
             // Arithmetic
-            OpCode::Add => self.execute_binary_arithmetic(node, |a, b| a + b),
-            OpCode::Sub => self.execute_binary_arithmetic(node, |a, b| a - b),
-            OpCode::Mul => self.execute_binary_arithmetic(node, |a, b| a * b),
+            OpCode::Add => self.execute_binary_arithmetic(node, "+", |a, b| a.checked_add(b).map(Value::Int), |a, b| a + b),
+            OpCode::Sub => self.execute_binary_arithmetic(node, "-", |a, b| a.checked_sub(b).map(Value::Int), |a, b| a - b),
+            OpCode::Mul => self.execute_binary_arithmetic(node, "*", |a, b| a.checked_mul(b).map(Value::Int), |a, b| a * b),
             OpCode::Div => self.execute_division(node),
             OpCode::Mod => self.execute_modulo(node),
+            OpCode::BitAnd => self.execute_bitwise(node, |a, b| a & b),
+            OpCode::BitOr => self.execute_bitwise(node, |a, b| a | b),
+            OpCode::BitXor => self.execute_bitwise(node, |a, b| a ^ b),
+            OpCode::BitNot => self.execute_bit_not(node),
+            OpCode::Shl => self.execute_shift(node, "<<", |a, n| a << n),
+            OpCode::Shr => self.execute_shift(node, ">>", |a, n| a >> n),
+            OpCode::Sqrt => self.execute_sqrt(node),
+            OpCode::Pow => self.execute_pow(node),
+            OpCode::Abs => self.execute_unary_math(node, "abs", |a| a.checked_abs(), f64::abs),
+            OpCode::Floor => self.execute_unary_math(node, "floor", Some, f64::floor),
+            OpCode::Ceil => self.execute_unary_math(node, "ceil", Some, f64::ceil),
+            OpCode::Neg => self.execute_unary_math(node, "-", |a| a.checked_neg(), |a| -a),
+            OpCode::Min => self.execute_binary_arithmetic(node, "min", |a, b| Some(Value::Int(a.min(b))), f64::min),
+            OpCode::Max => self.execute_binary_arithmetic(node, "max", |a, b| Some(Value::Int(a.max(b))), f64::max),
             
             // Comparison
             OpCode::Eq => self.execute_comparison(node, |a, b| a == b),
@@ -96,13 +360,71 @@ impl Executor {
             OpCode::ArraySet => self.execute_array_set(node),
             OpCode::MapGet => self.execute_map_get(node),
             OpCode::MapSet => self.execute_map_set(node),
+            OpCode::ArrayLen => self.execute_array_len(node),
+            OpCode::ArrayPush => self.execute_array_push(node),
+            OpCode::ArrayPop => self.execute_array_pop(node),
+            OpCode::ArraySlice => self.execute_array_slice(node),
+            OpCode::ArrayConcat => self.execute_array_concat(node),
+            OpCode::MapContains => self.execute_map_contains(node),
+            OpCode::MapKeys => self.execute_map_keys(node),
+            OpCode::MapValues => self.execute_map_values(node),
+            OpCode::MapDelete => self.execute_map_delete(node),
             
             // Functions
             OpCode::DefineFunc => self.execute_define_func(node),
             OpCode::CreateClosure => self.execute_create_closure(node),
             
+            // Types
+            OpCode::Cast => self.execute_cast(node),
+            OpCode::TypeOf => self.execute_type_of(node),
+            OpCode::StrConcat => self.execute_str_concat(node),
+            OpCode::StrLen => self.execute_str_len(node),
+            OpCode::StrSlice => self.execute_str_slice(node),
+            OpCode::Concat => self.execute_concat(node),
+            OpCode::Len => self.execute_len(node),
+            OpCode::ParseInt => {
+                let text = self.get_string_arg(node, 0)?;
+                parse_int_text(&text)
+            }
+            OpCode::ParseFloat => {
+                let text = self.get_string_arg(node, 0)?;
+                parse_float_text(&text)
+            }
+            OpCode::Format => {
+                let value = self.get_arg_value(node, 0)?;
+                let spec = self.get_string_arg(node, 1)?;
+                format_value(&value, &spec).map(Value::String)
+            }
+            
             // IO
             OpCode::Print => self.execute_print(node),
+            OpCode::Random => self.execute_random(node),
+            OpCode::Sleep => self.execute_sleep(node),
+            OpCode::GetEnv => {
+                let name = self.get_string_arg(node, 0)?;
+                let value = match self.context.env_overrides.get(&name) {
+                    Some(value) => Some(value.clone()),
+                    None => std::env::var(&name).ok(),
+                };
+                Ok(value.map_or(Value::Nil, Value::String))
+            }
+            OpCode::FileRead => {
+                let path = self.get_string_arg(node, 0)?;
+                std::fs::read_to_string(&path)
+                    .map(Value::String)
+                    .map_err(|e| RuntimeError::IOError(format!("cannot read {}: {}", path, e)))
+            }
+            OpCode::FileWrite => {
+                let path = self.get_string_arg(node, 0)?;
+                let contents = self.get_string_arg(node, 1)?;
+                std::fs::write(&path, contents)
+                    .map(|_| Value::Nil)
+                    .map_err(|e| RuntimeError::IOError(format!("cannot write {}: {}", path, e)))
+            }
+            OpCode::HttpGet => self.execute_http_get(node),
+            OpCode::Now => {
+                Ok(Value::Int(self.context.now_millis()))
+            }
             
             // Memory operations
             OpCode::Alloc => self.execute_alloc(node),
@@ -110,11 +432,19 @@ impl Executor {
             OpCode::Load => self.execute_load(node),
             OpCode::Store => self.execute_store(node),
             OpCode::LoadArg => self.execute_load_arg(node),
+            OpCode::LoadParam => self.execute_load_param(node),
+            OpCode::ArgCount => Ok(self.context.get_value(999).cloned().unwrap_or(Value::Int(0))),
+            OpCode::PtrAdd => self.execute_ptr_add(node),
             
             // Async operations
             OpCode::AsyncBegin => self.execute_async_begin(node),
             OpCode::AsyncAwait => self.execute_async_await(node),
             OpCode::AsyncComplete => self.execute_async_complete(node),
+            OpCode::AsyncTimeout => self.execute_async_timeout(node),
+            OpCode::AsyncJoin => self.execute_async_join(node),
+            
+            // External calls
+            OpCode::ExternalCall => self.execute_external_call(node),
             
             _ => Err(RuntimeError::InvalidOperation(
                 format!("Opcode {:?} not implemented", opcode)
@@ -135,9 +465,19 @@ impl Executor {
             return Ok(Value::Nil);
         }
 
-        // First check if we already have a computed value (prevents infinite recursion)
+        // First check if we already have a computed value (prevents infinite recursion).
+        // Eager arguments were evaluated just before this node in argument order,
+        // so their values stand even if a later sibling stored to the same address.
         if let Some(value) = self.context.get_value(arg_id) {
-            return Ok(value.clone());
+            let eager = OpCode::try_from(node.opcode)
+                .map(|opcode| Self::eager_args(opcode, node).contains(&arg_id))
+                .unwrap_or(false);
+            let volatile = self.context.get_node(arg_id)
+                .map(|arg| arg.has_flag(NodeFlag::Volatile))
+                .unwrap_or(false);
+            if eager || !(volatile || self.context.is_stale(arg_id)) {
+                return Ok(value.clone());
+            }
         }
 
         // Check if it's a node reference that needs execution
@@ -162,19 +502,38 @@ impl Executor {
         
         match func_value {
             Value::Function(func) => {
-                self.context.push_frame(func.node_id, Some(node.result_id))?;
-                
-                // Set up arguments as local values
+                // Arguments belong to the caller, so evaluate them before
+                // the callee's frame exists
+                let mut params = Vec::new();
                 for i in 1..node.arg_count as usize {
-                    let arg_value = self.get_arg_value(node, i)?;
-                    if let Some(frame) = self.context.current_frame_mut() {
-                        frame.locals.insert(i as u32, arg_value);
-                    }
+                    params.push(self.get_arg_value(node, i)?);
+                }
+
+                // A call whose result the current function returns as is
+                // hands its frame over instead of nesting. The placeholder
+                // result only passes through Branch and Seq on the way out.
+                if self.is_tail_call(node.result_id) {
+                    self.context.pending_tail_call = Some((func.node_id, params));
+                    return Ok(Value::Nil);
                 }
+
+                self.context.push_frame(func.node_id, params, Some(node.result_id))?;
                 
-                let result = self.execute_node(func.node_id)?;
+                // Pop the frame even when the body fails, so a Try around
+                // the call sees the caller's frame again
+                let mut body = func.node_id;
+                let result = loop {
+                    let result = self.execute_node(body);
+                    match self.context.pending_tail_call.take() {
+                        Some((next, params)) if result.is_ok() => {
+                            self.context.reuse_frame(next, params);
+                            body = next;
+                        }
+                        _ => break result,
+                    }
+                };
                 self.context.pop_frame();
-                Ok(result)
+                result
             }
             _ => Err(RuntimeError::TypeMismatch {
                 expected: "function".to_string(),
@@ -183,6 +542,86 @@ impl Executor {
         }
     }
 
+    /// Whether `call_id` is in tail position of the function running in the
+    /// current frame.
+    fn is_tail_call(&mut self, call_id: u32) -> bool {
+        let body = match self.context.current_frame() {
+            Some(frame) => frame.node_id,
+            None => return false,
+        };
+        if !self.context.tail_positions.contains_key(&body) {
+            let positions = self.tail_positions(body);
+            self.context.tail_positions.insert(body, positions);
+        }
+        self.context.tail_positions[&body].contains(&call_id)
+    }
+
+    /// Nodes whose value is the function's result as is: the body, the arms
+    /// of a Branch and the last step of a Seq in tail position. A node that
+    /// something else also reads is left out, since its value is needed.
+    fn tail_positions(&self, body: u32) -> HashSet<u32> {
+        let tail_children = |node: &Node| -> Vec<u32> {
+            let args = &node.args[..(node.arg_count as usize).min(3)];
+            match OpCode::try_from(node.opcode) {
+                Ok(OpCode::Branch) => args.iter().skip(1).copied().filter(|id| *id != 0).collect(),
+                Ok(OpCode::Seq) => args.last().copied().into_iter().collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        let mut tail = HashSet::new();
+        let mut stack = vec![body];
+        while let Some(id) = stack.pop() {
+            if tail.insert(id) {
+                if let Some(node) = self.context.get_node(id) {
+                    stack.extend(tail_children(node));
+                }
+            }
+        }
+
+        // Drop nodes read outside a tail position, until nothing changes
+        loop {
+            let shared: Vec<u32> = tail.iter().copied().filter(|&id| {
+                self.context.program.nodes.iter().any(|consumer| {
+                    let reads = consumer.dependencies().iter().filter(|dep| **dep == id).count();
+                    let tail_reads = if tail.contains(&consumer.result_id) {
+                        tail_children(consumer).iter().filter(|child| **child == id).count()
+                    } else {
+                        0
+                    };
+                    reads > tail_reads
+                })
+            }).collect();
+            if shared.is_empty() {
+                return tail;
+            }
+            for id in shared {
+                tail.remove(&id);
+            }
+        }
+    }
+
+    /// Read a parameter of the function call being executed, by position
+    /// among the Call's arguments after the function itself.
+    fn execute_load_param(&mut self, node: &Node) -> Result<Value> {
+        let index = match self.get_arg_value(node, 0)? {
+            Value::Int(index) => index,
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "integer".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+        let frame = self.context.current_frame().ok_or_else(|| RuntimeError::InvalidOperation(
+            format!("LoadParam at node {} is outside any function call", node.result_id)
+        ))?;
+        usize::try_from(index).ok()
+            .and_then(|i| frame.params.get(i))
+            .cloned()
+            .ok_or_else(|| RuntimeError::InvalidOperation(format!(
+                "Parameter {} not passed; the call has {} parameters", index, frame.params.len()
+            )))
+    }
+
     fn execute_branch(&mut self, node: &Node) -> Result<Value> {
         let condition = self.get_arg_value(node, 0)?;
         
@@ -195,22 +634,115 @@ impl Executor {
         }
     }
 
-    fn execute_binary_arithmetic<F>(&mut self, node: &Node, op: F) -> Result<Value>
+    fn execute_loop(&mut self, node: &Node) -> Result<Value> {
+        // Results that change between iterations must be recomputed, so
+        // find them once up front and drop them from the cache each round.
+        let variant = self.loop_variant_nodes(&node.args[..2.min(node.arg_count as usize)]);
+
+        // Optional third argument bounds the number of iterations
+        let max_iterations = if node.arg_count > 2 {
+            match self.get_arg_value(node, 2)? {
+                Value::Int(n) if n >= 0 => Some(n as u64),
+                Value::Int(n) => return Err(RuntimeError::InvalidOperation(
+                    format!("Loop iteration bound must be non-negative, got {}", n)
+                )),
+                other => return Err(RuntimeError::TypeMismatch {
+                    expected: "int".to_string(),
+                    actual: other.type_name().to_string(),
+                }),
+            }
+        } else {
+            None
+        };
+
+        let mut last = Value::Nil;
+        let mut iterations: u64 = 0;
+        loop {
+            if iterations > 0 {
+                for id in &variant {
+                    self.context.clear_value(*id);
+                }
+            }
+
+            if !self.get_arg_value(node, 0)?.is_truthy() {
+                return Ok(last);
+            }
+            if max_iterations.is_some_and(|max| iterations >= max) {
+                return Err(RuntimeError::InvalidOperation(format!(
+                    "Loop {} exceeded its bound of {} iterations",
+                    node.result_id, iterations
+                )));
+            }
+            last = self.get_arg_value(node, 1)?;
+            iterations += 1;
+        }
+    }
+
+    /// Nodes under `roots` whose value can differ from one loop iteration to
+    /// the next: those reading or writing mutable state, and everything that
+    /// depends on them. Pure subexpressions and allocations stay cached.
+    fn loop_variant_nodes(&self, roots: &[u32]) -> HashSet<u32> {
+        let mut variant = HashSet::new();
+        let mut visited = HashSet::new();
+        // (node id, children already pushed)
+        let mut stack: Vec<(u32, bool)> = roots.iter()
+            .filter(|id| **id != 0)
+            .map(|id| (*id, false))
+            .collect();
+
+        while let Some((id, expanded)) = stack.pop() {
+            let node = match self.context.get_node(id) {
+                Some(node) => *node,
+                None => continue,
+            };
+            let deps = node.dependencies();
+
+            if !expanded {
+                if !visited.insert(id) {
+                    continue;
+                }
+                stack.push((id, true));
+                for dep in deps {
+                    if !visited.contains(&dep) {
+                        stack.push((dep, false));
+                    }
+                }
+                continue;
+            }
+
+            let stateful = matches!(
+                OpCode::try_from(node.opcode),
+                Ok(OpCode::Load) | Ok(OpCode::Store) | Ok(OpCode::Free) | Ok(OpCode::Read) | Ok(OpCode::Random) | Ok(OpCode::Now) | Ok(OpCode::Sleep) |
+                Ok(OpCode::FileRead) | Ok(OpCode::FileWrite) | Ok(OpCode::GetEnv) | Ok(OpCode::HttpGet) |
+                Ok(OpCode::Print) | Ok(OpCode::Call) | Ok(OpCode::Loop) |
+                Ok(OpCode::AsyncAwait) | Ok(OpCode::AsyncComplete) | Ok(OpCode::AsyncTimeout) | Ok(OpCode::AsyncJoin) |
+                Ok(OpCode::ExternalCall)
+            );
+            let volatile = node.has_flag(NodeFlag::Volatile);
+            if stateful || volatile || deps.iter().any(|dep| variant.contains(dep)) {
+                variant.insert(id);
+            }
+        }
+
+        variant
+    }
+
+    /// Result ids a node reads through its arguments. Constant indices and
+    /// function definitions are not data dependencies.
+    /// Int/Int operands use `int_op` directly, where `None` signals overflow;
+    /// any Float operand promotes both sides to f64 and uses `float_op`.
+    fn execute_binary_arithmetic<I, F>(&mut self, node: &Node, symbol: &str, int_op: I, op: F) -> Result<Value>
     where
+        I: Fn(i64, i64) -> Option<Value>,
         F: Fn(f64, f64) -> f64,
     {
         let left = self.get_arg_value(node, 0)?;
         let right = self.get_arg_value(node, 1)?;
 
         match (&left, &right) {
-            (Value::Int(a), Value::Int(b)) => {
-                let result = op(*a as f64, *b as f64);
-                if result.fract() == 0.0 {
-                    Ok(Value::Int(result as i64))
-                } else {
-                    Ok(Value::Float(result))
-                }
-            }
+            (Value::Int(a), Value::Int(b)) => int_op(*a, *b).ok_or_else(|| {
+                RuntimeError::IntegerOverflow(format!("{} {} {}", a, symbol, b))
+            }),
             (Value::Float(a), Value::Float(b)) => Ok(Value::Float(op(*a, *b))),
             (Value::Int(a), Value::Float(b)) => Ok(Value::Float(op(*a as f64, *b))),
             (Value::Float(a), Value::Int(b)) => Ok(Value::Float(op(*a, *b as f64))),
@@ -234,20 +766,93 @@ impl Executor {
             _ => {}
         }
 
-        self.execute_binary_arithmetic(node, |a, b| a / b)
+        // Exact integer quotients stay integers; anything else becomes a float
+        self.execute_binary_arithmetic(
+            node,
+            "/",
+            |a, b| match a.checked_rem(b) {
+                Some(0) => a.checked_div(b).map(Value::Int),
+                Some(_) => Some(Value::Float(a as f64 / b as f64)),
+                None => None,
+            },
+            |a, b| a / b,
+        )
     }
 
+    /// Remainder with the sign of the left operand, on integers or floats.
     fn execute_modulo(&mut self, node: &Node) -> Result<Value> {
+        let right = self.get_arg_value(node, 1)?;
+
+        match &right {
+            Value::Int(0) => return Err(RuntimeError::DivisionByZero),
+            Value::Float(f) if *f == 0.0 => return Err(RuntimeError::DivisionByZero),
+            _ => {}
+        }
+
+        self.execute_binary_arithmetic(node, "%", |a, b| a.checked_rem(b).map(Value::Int), |a, b| a % b)
+    }
+
+    /// Integers stay integers (failing on overflow), floats stay floats.
+    fn execute_unary_math<I, F>(&mut self, node: &Node, name: &str, int_op: I, op: F) -> Result<Value>
+    where
+        I: Fn(i64) -> Option<i64>,
+        F: Fn(f64) -> f64,
+    {
+        match self.get_arg_value(node, 0)? {
+            Value::Int(a) => int_op(a)
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::IntegerOverflow(format!("{}({})", name, a))),
+            Value::Float(a) => Ok(Value::Float(op(a))),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "numeric".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn execute_sqrt(&mut self, node: &Node) -> Result<Value> {
+        let value = self.get_arg_value(node, 0)?;
+        let x = match value {
+            Value::Int(a) => a as f64,
+            Value::Float(a) => a,
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "numeric".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+        if x < 0.0 {
+            return Err(RuntimeError::InvalidOperation(
+                format!("Square root of negative number: {}", x)
+            ));
+        }
+        Ok(Value::Float(x.sqrt()))
+    }
+
+    /// Integer powers with a non-negative exponent are exact and fail on
+    /// overflow; a negative integer exponent gives a float.
+    fn execute_pow(&mut self, node: &Node) -> Result<Value> {
+        self.execute_binary_arithmetic(
+            node,
+            "**",
+            |a, b| {
+                if b < 0 {
+                    Some(Value::Float((a as f64).powf(b as f64)))
+                } else {
+                    u32::try_from(b).ok()
+                        .and_then(|exp| a.checked_pow(exp))
+                        .map(Value::Int)
+                }
+            },
+            f64::powf,
+        )
+    }
+
+    fn get_int_operands(&mut self, node: &Node) -> Result<(i64, i64)> {
         let left = self.get_arg_value(node, 0)?;
         let right = self.get_arg_value(node, 1)?;
 
         match (&left, &right) {
-            (Value::Int(a), Value::Int(b)) => {
-                if *b == 0 {
-                    return Err(RuntimeError::DivisionByZero);
-                }
-                Ok(Value::Int(a % b))
-            }
+            (Value::Int(a), Value::Int(b)) => Ok((*a, *b)),
             _ => Err(RuntimeError::TypeMismatch {
                 expected: "integer".to_string(),
                 actual: format!("{} and {}", left.type_name(), right.type_name()),
@@ -255,6 +860,39 @@ impl Executor {
         }
     }
 
+    fn execute_bitwise<F>(&mut self, node: &Node, op: F) -> Result<Value>
+    where
+        F: Fn(i64, i64) -> i64,
+    {
+        let (a, b) = self.get_int_operands(node)?;
+        Ok(Value::Int(op(a, b)))
+    }
+
+    fn execute_bit_not(&mut self, node: &Node) -> Result<Value> {
+        match self.get_arg_value(node, 0)? {
+            Value::Int(a) => Ok(Value::Int(!a)),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "integer".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// Shift counts outside 0..=63 are rejected rather than masked, so a
+    /// count of 64 never silently means 0.
+    fn execute_shift<F>(&mut self, node: &Node, symbol: &str, op: F) -> Result<Value>
+    where
+        F: Fn(i64, u32) -> i64,
+    {
+        let (a, count) = self.get_int_operands(node)?;
+        if !(0..64).contains(&count) {
+            return Err(RuntimeError::InvalidOperation(
+                format!("Shift count out of range: {} {} {}", a, symbol, count)
+            ));
+        }
+        Ok(Value::Int(op(a, count as u32)))
+    }
+
     fn execute_comparison<F>(&mut self, node: &Node, op: F) -> Result<Value>
     where
         F: Fn(&Value, &Value) -> bool,
@@ -375,6 +1013,98 @@ impl Executor {
         }
     }
 
+    fn execute_array_len(&mut self, node: &Node) -> Result<Value> {
+        match self.get_arg_value(node, 0)? {
+            Value::Array(arr) => Ok(Value::Int(arr.len() as i64)),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "array".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    // Push and pop build a new array value, like ArraySet; other nodes holding
+    // the original array keep seeing it unchanged.
+    fn execute_array_push(&mut self, node: &Node) -> Result<Value> {
+        let array = self.get_arg_value(node, 0)?;
+        let value = self.get_arg_value(node, 1)?;
+
+        match array {
+            Value::Array(mut arr) => {
+                arr.push(value);
+                Ok(Value::Array(arr))
+            }
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "array".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn execute_array_pop(&mut self, node: &Node) -> Result<Value> {
+        match self.get_arg_value(node, 0)? {
+            Value::Array(mut arr) => {
+                if arr.pop().is_none() {
+                    return Err(RuntimeError::InvalidOperation(
+                        "Cannot pop from an empty array".to_string()
+                    ));
+                }
+                Ok(Value::Array(arr))
+            }
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "array".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn execute_array_slice(&mut self, node: &Node) -> Result<Value> {
+        let array = self.get_arg_value(node, 0)?;
+        let start = self.get_arg_value(node, 1)?;
+        let end = self.get_arg_value(node, 2)?;
+
+        let (arr, start, end) = match (&array, &start, &end) {
+            (Value::Array(arr), Value::Int(start), Value::Int(end)) => (arr, *start, *end),
+            _ => return Err(RuntimeError::TypeMismatch {
+                expected: "array and two integers".to_string(),
+                actual: format!("{}, {} and {}", array.type_name(), start.type_name(), end.type_name()),
+            }),
+        };
+
+        // Bounds are checked like ArrayGet rather than clamped
+        for bound in [start, end] {
+            if bound < 0 || bound as usize > arr.len() {
+                return Err(RuntimeError::ArrayIndexOutOfBounds {
+                    index: bound as usize,
+                    length: arr.len(),
+                });
+            }
+        }
+        if start > end {
+            return Err(RuntimeError::InvalidOperation(
+                format!("Array slice start {} is after end {}", start, end)
+            ));
+        }
+
+        Ok(Value::Array(arr[start as usize..end as usize].to_vec()))
+    }
+
+    fn execute_array_concat(&mut self, node: &Node) -> Result<Value> {
+        let left = self.get_arg_value(node, 0)?;
+        let right = self.get_arg_value(node, 1)?;
+
+        match (left, right) {
+            (Value::Array(mut left), Value::Array(right)) => {
+                left.extend(right);
+                Ok(Value::Array(left))
+            }
+            (left, right) => Err(RuntimeError::TypeMismatch {
+                expected: "two arrays".to_string(),
+                actual: format!("{} and {}", left.type_name(), right.type_name()),
+            }),
+        }
+    }
+
     fn execute_array_set(&mut self, node: &Node) -> Result<Value> {
         let mut array = self.get_arg_value(node, 0)?;
         let index = self.get_arg_value(node, 1)?;
@@ -403,16 +1133,13 @@ impl Executor {
         let map = self.get_arg_value(node, 0)?;
         let key = self.get_arg_value(node, 1)?;
 
-        match (&map, &key) {
-            (Value::Map(m), Value::String(k)) => {
-                m.get(k)
+        match (&map, MapKey::from_value(&key)) {
+            (Value::Map(m), Some(k)) => {
+                m.get(&k)
                     .cloned()
-                    .ok_or(RuntimeError::MapKeyNotFound(k.clone()))
+                    .ok_or(RuntimeError::MapKeyNotFound(k.to_string()))
             }
-            _ => Err(RuntimeError::TypeMismatch {
-                expected: "map and string".to_string(),
-                actual: format!("{} and {}", map.type_name(), key.type_name()),
-            }),
+            _ => Err(Self::map_key_mismatch(&map, &key)),
         }
     }
 
@@ -421,14 +1148,66 @@ impl Executor {
         let key = self.get_arg_value(node, 1)?;
         let value = self.get_arg_value(node, 2)?;
 
-        match (&mut map, &key) {
-            (Value::Map(m), Value::String(k)) => {
-                m.insert(k.clone(), value);
+        match (&mut map, MapKey::from_value(&key)) {
+            (Value::Map(m), Some(k)) => {
+                m.insert(k, value);
                 Ok(map)
             }
-            _ => Err(RuntimeError::TypeMismatch {
-                expected: "map and string".to_string(),
-                actual: format!("{} and {}", map.type_name(), key.type_name()),
+            _ => Err(Self::map_key_mismatch(&map, &key)),
+        }
+    }
+
+    fn execute_map_delete(&mut self, node: &Node) -> Result<Value> {
+        let mut map = self.get_arg_value(node, 0)?;
+        let key = self.get_arg_value(node, 1)?;
+
+        match (&mut map, MapKey::from_value(&key)) {
+            (Value::Map(m), Some(k)) => {
+                m.remove(&k);
+                Ok(map)
+            }
+            _ => Err(Self::map_key_mismatch(&map, &key)),
+        }
+    }
+
+    fn execute_map_contains(&mut self, node: &Node) -> Result<Value> {
+        let map = self.get_arg_value(node, 0)?;
+        let key = self.get_arg_value(node, 1)?;
+
+        match (&map, MapKey::from_value(&key)) {
+            (Value::Map(m), Some(k)) => Ok(Value::Bool(m.contains_key(&k))),
+            _ => Err(Self::map_key_mismatch(&map, &key)),
+        }
+    }
+
+    fn map_key_mismatch(map: &Value, key: &Value) -> RuntimeError {
+        RuntimeError::TypeMismatch {
+            expected: "map and string, int or bool key".to_string(),
+            actual: format!("{} and {}", map.type_name(), key.type_name()),
+        }
+    }
+
+    fn execute_map_keys(&mut self, node: &Node) -> Result<Value> {
+        let entries = self.get_sorted_map_entries(node)?;
+        Ok(Value::Array(entries.into_iter().map(|(k, _)| k.to_value()).collect()))
+    }
+
+    fn execute_map_values(&mut self, node: &Node) -> Result<Value> {
+        let entries = self.get_sorted_map_entries(node)?;
+        Ok(Value::Array(entries.into_iter().map(|(_, v)| v).collect()))
+    }
+
+    /// Map entries sorted by key, so results don't depend on hash order.
+    fn get_sorted_map_entries(&mut self, node: &Node) -> Result<Vec<(MapKey, Value)>> {
+        match self.get_arg_value(node, 0)? {
+            Value::Map(m) => {
+                let mut entries: Vec<(MapKey, Value)> = m.into_iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                Ok(entries)
+            }
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "map".to_string(),
+                actual: other.type_name().to_string(),
             }),
         }
     }
@@ -466,18 +1245,315 @@ impl Executor {
         }
     }
 
+    fn execute_cast(&mut self, node: &Node) -> Result<Value> {
+        let value = self.get_arg_value(node, 0)?;
+        let target = match self.get_arg_value(node, 1)? {
+            Value::String(s) => s,
+            Value::Int(code) => match usize::try_from(code).ok().and_then(|i| CAST_TARGETS.get(i)) {
+                Some(name) => name.to_string(),
+                None => return Err(RuntimeError::InvalidOperation(
+                    format!("Unknown cast target code: {}", code)
+                )),
+            },
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "type name string or type code".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+
+        let mismatch = |value: &Value| RuntimeError::TypeMismatch {
+            expected: format!("value convertible to {}", target),
+            actual: value.type_name().to_string(),
+        };
+
+        match target.as_str() {
+            "int" => match &value {
+                Value::Int(i) => Ok(Value::Int(*i)),
+                Value::Bool(b) => Ok(Value::Int(*b as i64)),
+                Value::Float(f) => {
+                    // Truncates toward zero; NaN and out-of-range values have no int form
+                    let truncated = f.trunc();
+                    if truncated.is_finite() && truncated >= i64::MIN as f64 && truncated < i64::MAX as f64 {
+                        Ok(Value::Int(truncated as i64))
+                    } else {
+                        Err(RuntimeError::InvalidOperation(format!("Cannot cast {} to int", f)))
+                    }
+                }
+                Value::String(s) => parse_int_text(s),
+                _ => Err(mismatch(&value)),
+            },
+            "float" => match &value {
+                Value::Float(f) => Ok(Value::Float(*f)),
+                Value::Int(i) => Ok(Value::Float(*i as f64)),
+                Value::Bool(b) => Ok(Value::Float(if *b { 1.0 } else { 0.0 })),
+                Value::String(s) => parse_float_text(s),
+                _ => Err(mismatch(&value)),
+            },
+            "string" => match &value {
+                Value::Nil | Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::String(_) => {
+                    Ok(Value::String(value.to_string()))
+                }
+                _ => Err(mismatch(&value)),
+            },
+            "bool" => Ok(Value::Bool(value.is_truthy())),
+            _ => Err(RuntimeError::InvalidOperation(format!("Unknown cast target: {}", target))),
+        }
+    }
+
+    fn execute_type_of(&mut self, node: &Node) -> Result<Value> {
+        let value = self.get_arg_value(node, 0)?;
+        Ok(Value::String(value.type_name().to_string()))
+    }
+
+    fn execute_str_concat(&mut self, node: &Node) -> Result<Value> {
+        let left = self.get_arg_value(node, 0)?;
+        let right = self.get_arg_value(node, 1)?;
+
+        // Nil is almost always a missing value, so refuse it rather than splice in "nil"
+        let mut result = String::new();
+        for value in [&left, &right] {
+            if let Value::Nil = value {
+                return Err(RuntimeError::TypeMismatch {
+                    expected: "non-nil value".to_string(),
+                    actual: value.type_name().to_string(),
+                });
+            }
+            result.push_str(&value.to_string());
+        }
+
+        Ok(Value::String(result))
+    }
+
+    fn execute_str_len(&mut self, node: &Node) -> Result<Value> {
+        let s = self.get_string_arg(node, 0)?;
+        Ok(Value::Int(s.chars().count() as i64))
+    }
+
+    fn execute_str_slice(&mut self, node: &Node) -> Result<Value> {
+        let s = self.get_string_arg(node, 0)?;
+        let start = self.get_arg_value(node, 1)?;
+        let end = self.get_arg_value(node, 2)?;
+
+        let (start, end) = match (&start, &end) {
+            (Value::Int(start), Value::Int(end)) => (*start, *end),
+            (Value::Int(_), other) | (other, _) => return Err(RuntimeError::TypeMismatch {
+                expected: "int".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+
+        // Indices count characters so a slice never splits a UTF-8 sequence
+        let length = s.chars().count() as i64;
+        if start < 0 || end < start || end > length {
+            return Err(RuntimeError::InvalidOperation(format!(
+                "String slice {}..{} out of range for string of {} characters",
+                start, end, length
+            )));
+        }
+
+        Ok(Value::String(
+            s.chars().skip(start as usize).take((end - start) as usize).collect(),
+        ))
+    }
+
+    fn execute_concat(&mut self, node: &Node) -> Result<Value> {
+        let left = self.get_arg_value(node, 0)?;
+        let right = self.get_arg_value(node, 1)?;
+
+        match (left, right) {
+            (Value::String(left), Value::String(right)) => Ok(Value::String(left + &right)),
+            (Value::Array(mut left), Value::Array(right)) => {
+                left.extend(right);
+                Ok(Value::Array(left))
+            }
+            (left, right) => Err(RuntimeError::TypeMismatch {
+                expected: "two strings or two arrays".to_string(),
+                actual: format!("{} and {}", left.type_name(), right.type_name()),
+            }),
+        }
+    }
+
+    fn execute_len(&mut self, node: &Node) -> Result<Value> {
+        let length = match self.get_arg_value(node, 0)? {
+            Value::String(s) => s.chars().count(),
+            Value::Array(arr) => arr.len(),
+            Value::Map(map) => map.len(),
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "string, array or map".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+        Ok(Value::Int(length as i64))
+    }
+
+    fn get_string_arg(&mut self, node: &Node, index: usize) -> Result<String> {
+        match self.get_arg_value(node, index)? {
+            Value::String(s) => Ok(s),
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "string".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
     fn execute_print(&mut self, node: &Node) -> Result<Value> {
+        let mut line = String::new();
         for i in 0..node.arg_count as usize {
             let value = self.get_arg_value(node, i)?;
-            print!("{}", value.to_string());
+            line.push_str(&value.to_string());
             if i < node.arg_count as usize - 1 {
-                print!(" ");
+                line.push(' ');
+            }
+        }
+        match &mut self.context.output {
+            Some(output) => {
+                output.push_str(&line);
+                output.push('\n');
             }
+            None => println!("{}", line),
         }
-        println!();
         Ok(Value::Nil)
     }
     
+    fn execute_random(&mut self, node: &Node) -> Result<Value> {
+        match node.arg_count {
+            0 => {
+                // The top 53 bits fill an f64 mantissa exactly
+                let bits = self.context.next_random() >> 11;
+                Ok(Value::Float(bits as f64 / (1u64 << 53) as f64))
+            }
+            2 => {
+                let (low, high) = self.get_int_operands(node)?;
+                if low >= high {
+                    return Err(RuntimeError::InvalidOperation(
+                        format!("Random range [{}, {}) is empty", low, high)
+                    ));
+                }
+                let span = high.wrapping_sub(low) as u64;
+                let offset = self.context.next_random() % span;
+                Ok(Value::Int(low.wrapping_add(offset as i64)))
+            }
+            n => Err(RuntimeError::InvalidOperation(
+                format!("Random takes 0 or 2 arguments, got {}", n)
+            )),
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn execute_http_get(&mut self, node: &Node) -> Result<Value> {
+        let url = self.get_string_arg(node, 0)?;
+        let response = crate::runtime::http::get(&url, self.context.http_timeout)
+            .map_err(|message| RuntimeError::NetworkError { url, message })?;
+        Ok(Value::Array(vec![
+            Value::Int(response.status as i64),
+            Value::String(response.body),
+        ]))
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn execute_http_get(&mut self, node: &Node) -> Result<Value> {
+        let url = self.get_string_arg(node, 0)?;
+        Err(RuntimeError::NetworkError {
+            url,
+            message: "HttpGet is unavailable; der was built without the `http` feature".to_string(),
+        })
+    }
+
+    fn execute_sleep(&mut self, node: &Node) -> Result<Value> {
+        let millis = match self.get_arg_value(node, 0)? {
+            Value::Int(ms) if ms >= 0 => ms as u64,
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "non-negative integer".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+        let duration = std::time::Duration::from_millis(millis);
+
+        if node.arg_count < 2 {
+            std::thread::sleep(duration);
+            return Ok(Value::Nil);
+        }
+
+        // With a handle, complete it from a timer instead of blocking
+        match self.get_arg_value(node, 1)? {
+            Value::AsyncHandle(handle) => {
+                self.context.async_runtime.schedule(&handle, Delay::new(duration));
+                Ok(Value::AsyncHandle(handle))
+            }
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "async handle".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// Evaluate the protected argument, falling back to the handler if it
+    /// fails. The handler can read the error message from
+    /// `CAUGHT_ERROR_SLOT`. Exit is not an error and is never caught.
+    fn execute_try(&mut self, node: &Node) -> Result<Value> {
+        match self.get_arg_value(node, 0) {
+            Err(error) if !matches!(error, RuntimeError::Exit(_)) => {
+                self.context.set_value(CAUGHT_ERROR_SLOT, Value::String(error.to_string()));
+                self.get_arg_value(node, 1)
+            }
+            result => result,
+        }
+    }
+
+    /// Evaluate every argument strictly in order so their effects happen in
+    /// that order, and return the last value.
+    fn execute_seq(&mut self, node: &Node) -> Result<Value> {
+        let mut last = Value::Nil;
+        for i in 0..node.arg_count as usize {
+            last = self.get_arg_value(node, i)?;
+        }
+        Ok(last)
+    }
+
+    fn execute_assert(&mut self, node: &Node) -> Result<Value> {
+        if self.get_arg_value(node, 0)?.is_truthy() {
+            return Ok(Value::Nil);
+        }
+
+        let message = if node.arg_count > 1 {
+            self.get_string_arg(node, 1)?
+        } else {
+            "condition is false".to_string()
+        };
+        Err(RuntimeError::AssertionFailed { node_id: node.result_id, message })
+    }
+
+    /// Unwind the whole execution by surfacing the status as an error.
+    fn execute_exit(&mut self, node: &Node) -> Result<Value> {
+        match self.get_arg_value(node, 0)? {
+            Value::Int(status) => match i32::try_from(status) {
+                Ok(status) => Err(RuntimeError::Exit(status)),
+                Err(_) => Err(RuntimeError::InvalidOperation(
+                    format!("Exit status {} is out of range", status)
+                )),
+            },
+            other => Err(RuntimeError::TypeMismatch {
+                expected: "integer".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn execute_external_call(&mut self, node: &Node) -> Result<Value> {
+        let name = self.get_string_arg(node, 0)?;
+        let mut args = Vec::new();
+        for i in 1..node.arg_count as usize {
+            args.push(self.get_arg_value(node, i)?);
+        }
+
+        match self.context.host_functions.get(&name) {
+            Some(function) => function(&args),
+            None => Err(RuntimeError::ExternalCallFailed(
+                format!("no host function registered as '{}'", name)
+            )),
+        }
+    }
+
     fn execute_alloc(&mut self, node: &Node) -> Result<Value> {
         // Get size to allocate
         let size_value = self.get_arg_value(node, 0)?;
@@ -520,12 +1596,23 @@ impl Executor {
         }
     }
     
+    /// Read a memory slot. An optional second argument names a node, usually
+    /// a Store, that must run before the read; its value is discarded. This
+    /// is how a program orders its writes before a read, since a Store that
+    /// nothing depends on never runs.
     fn execute_load(&mut self, node: &Node) -> Result<Value> {
+        if node.arg_count > 1 {
+            self.get_arg_value(node, 1)?;
+        }
         let mem_ref = self.get_arg_value(node, 0)?;
         
         match mem_ref {
             Value::MemoryRef(ref_val) => {
-                self.context.memory.load(ref_val.address)
+                let value = self.context.memory.load(ref_val.address, ref_val.offset)?;
+                if let Some(generation) = self.context.memory.generation(ref_val.address) {
+                    self.context.memory_reads.insert(node.result_id, (ref_val.address, generation));
+                }
+                Ok(value)
             }
             _ => Err(RuntimeError::TypeMismatch {
                 expected: "memory reference".to_string(),
@@ -540,7 +1627,7 @@ impl Executor {
         
         match mem_ref {
             Value::MemoryRef(ref_val) => {
-                self.context.memory.store(ref_val.address, value.clone())?;
+                self.context.memory.store(ref_val.address, ref_val.offset, value.clone())?;
                 Ok(value)
             }
             _ => Err(RuntimeError::TypeMismatch {
@@ -550,6 +1637,33 @@ impl Executor {
         }
     }
     
+    /// A reference `n` slots further into the same allocation. Bounds are
+    /// checked when the reference is loaded from or stored to.
+    fn execute_ptr_add(&mut self, node: &Node) -> Result<Value> {
+        let mem_ref = self.get_arg_value(node, 0)?;
+        let delta = self.get_arg_value(node, 1)?;
+
+        match (&mem_ref, &delta) {
+            (Value::MemoryRef(ref_val), Value::Int(n)) => {
+                let offset = (ref_val.offset as i64).checked_add(*n)
+                    .filter(|offset| *offset >= 0)
+                    .ok_or_else(|| RuntimeError::InvalidOperation(
+                        format!("Pointer offset {} + {} is negative", ref_val.offset, n)
+                    ))?;
+                Ok(Value::MemoryRef(MemoryReference {
+                    address: ref_val.address,
+                    offset: offset as usize,
+                }))
+            }
+            _ => Err(RuntimeError::TypeMismatch {
+                expected: "memory reference and integer".to_string(),
+                actual: format!("{} and {}", mem_ref.type_name(), delta.type_name()),
+            }),
+        }
+    }
+    
+    /// Read a program argument. A missing argument is an error unless the
+    /// node has a second argument, which is then evaluated as the default.
     fn execute_load_arg(&mut self, node: &Node) -> Result<Value> {
         let arg_index = self.get_arg_value(node, 0)?;
         
@@ -557,9 +1671,11 @@ impl Executor {
             Value::Int(index) => {
                 // Load argument from predefined slot (1000 + index)
                 let arg_slot = 1000 + index as u32;
-                self.context.get_value(arg_slot)
-                    .cloned()
-                    .ok_or(RuntimeError::InvalidOperation(format!("Argument {} not found", index)))
+                match self.context.get_value(arg_slot) {
+                    Some(value) => Ok(value.clone()),
+                    None if node.arg_count > 1 => self.get_arg_value(node, 1),
+                    None => Err(RuntimeError::InvalidOperation(format!("Argument {} not found", index))),
+                }
             }
             _ => Err(RuntimeError::TypeMismatch {
                 expected: "integer".to_string(),
@@ -582,9 +1698,10 @@ impl Executor {
                 match self.context.async_runtime.get_result(&handle)? {
                     Some(result) => Ok(result),
                     None => {
-                        // Still pending - in a real implementation this would yield
-                        // For now, we'll return a special pending value
-                        Ok(Value::AsyncHandle(handle))
+                        // Still pending: run the graph's completions for this
+                        // handle, then poll until it settles
+                        self.run_async_completions(&handle)?;
+                        self.context.async_runtime.run_until_complete(&handle)
                     }
                 }
             }
@@ -595,6 +1712,71 @@ impl Executor {
         }
     }
     
+    fn execute_async_timeout(&mut self, node: &Node) -> Result<Value> {
+        let handle = match self.get_arg_value(node, 0)? {
+            Value::AsyncHandle(handle) => handle,
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "async handle".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+        let millis = match self.get_arg_value(node, 1)? {
+            Value::Int(ms) if ms >= 0 => ms as u64,
+            other => return Err(RuntimeError::TypeMismatch {
+                expected: "non-negative integer".to_string(),
+                actual: other.type_name().to_string(),
+            }),
+        };
+
+        if let Some(result) = self.context.async_runtime.get_result(&handle)? {
+            return Ok(result);
+        }
+        self.run_async_completions(&handle)?;
+        self.context.async_runtime.await_with_timeout(&handle, std::time::Duration::from_millis(millis))
+    }
+    
+    fn execute_async_join(&mut self, node: &Node) -> Result<Value> {
+        let mut handles = Vec::new();
+        for i in 0..node.arg_count as usize {
+            match self.get_arg_value(node, i)? {
+                Value::AsyncHandle(handle) => handles.push(handle),
+                other => return Err(RuntimeError::TypeMismatch {
+                    expected: "async handle".to_string(),
+                    actual: other.type_name().to_string(),
+                }),
+            }
+        }
+
+        for handle in &handles {
+            if self.context.async_runtime.get_status(handle) == AsyncStatus::Pending {
+                self.run_async_completions(handle)?;
+            }
+        }
+        self.context.async_runtime.join(&handles).map(Value::Array)
+    }
+    
+    /// Evaluate the not-yet-run AsyncComplete nodes whose handle argument
+    /// already holds `handle`, stopping once it is no longer pending.
+    fn run_async_completions(&mut self, handle: &AsyncHandle) -> Result<()> {
+        let candidates: Vec<u32> = self.context.program.nodes.iter()
+            .filter(|n| matches!(OpCode::try_from(n.opcode), Ok(OpCode::AsyncComplete)))
+            .filter(|n| self.context.get_value(n.result_id).is_none())
+            .filter(|n| matches!(
+                self.context.get_value(n.args[0]),
+                Some(Value::AsyncHandle(h)) if h.id == handle.id
+            ))
+            .map(|n| n.result_id)
+            .collect();
+
+        for id in candidates {
+            if self.context.async_runtime.get_status(handle) != AsyncStatus::Pending {
+                break;
+            }
+            self.execute_node(id)?;
+        }
+        Ok(())
+    }
+    
     fn execute_async_complete(&mut self, node: &Node) -> Result<Value> {
         let handle_value = self.get_arg_value(node, 0)?;
         let result_value = self.get_arg_value(node, 1)?;
@@ -612,6 +1794,75 @@ impl Executor {
     }
 }
 
+/// Surrounding whitespace is ignored; errors quote the original text.
+fn parse_int_text(text: &str) -> Result<Value> {
+    text.trim().parse::<i64>()
+        .map(Value::Int)
+        .map_err(|_| RuntimeError::InvalidOperation(format!("Cannot parse {:?} as int", text)))
+}
+
+fn parse_float_text(text: &str) -> Result<Value> {
+    text.trim().parse::<f64>()
+        .map(Value::Float)
+        .map_err(|_| RuntimeError::InvalidOperation(format!("Cannot parse {:?} as float", text)))
+}
+
+/// Format `value` by a spec of the form `{[:[0][width][.precision][x|X|b|o]]}`,
+/// a subset of Rust's format syntax. Precision applies to numbers, radix
+/// types to integers; anything else is an invalid spec.
+fn format_value(value: &Value, spec: &str) -> Result<String> {
+    let invalid = || RuntimeError::InvalidOperation(format!("Invalid format spec {:?} for {}", spec, value.type_name()));
+
+    let body = spec.strip_prefix('{').and_then(|s| s.strip_suffix('}')).ok_or_else(invalid)?;
+    let body = match body {
+        "" => "",
+        _ => body.strip_prefix(':').ok_or_else(invalid)?,
+    };
+
+    let (radix, body) = match body.chars().last() {
+        Some(c @ ('x' | 'X' | 'b' | 'o')) => (Some(c), &body[..body.len() - 1]),
+        _ => (None, body),
+    };
+    let (width_part, precision) = match body.split_once('.') {
+        Some((width, digits)) if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            (width, Some(digits.parse::<usize>().map_err(|_| invalid())?))
+        }
+        Some(_) => return Err(invalid()),
+        None => (body, None),
+    };
+    if !width_part.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let zero_pad = width_part.len() > 1 && width_part.starts_with('0');
+    let width = match width_part {
+        "" => 0,
+        digits => digits.parse::<usize>().map_err(|_| invalid())?,
+    };
+
+    let text = match (value, radix, precision) {
+        (Value::Int(i), Some('x'), None) => format!("{:x}", i),
+        (Value::Int(i), Some('X'), None) => format!("{:X}", i),
+        (Value::Int(i), Some('b'), None) => format!("{:b}", i),
+        (Value::Int(i), Some('o'), None) => format!("{:o}", i),
+        (Value::Int(i), None, Some(p)) => format!("{:.*}", p, *i as f64),
+        (Value::Float(f), None, Some(p)) => format!("{:.*}", p, f),
+        (_, None, None) => value.to_string(),
+        _ => return Err(invalid()),
+    };
+
+    let padding = width.saturating_sub(text.chars().count());
+    let numeric = matches!(value, Value::Int(_) | Value::Float(_));
+    Ok(match (zero_pad && numeric, numeric) {
+        (true, _) => match text.strip_prefix('-') {
+            Some(digits) => format!("-{}{}", "0".repeat(padding), digits),
+            None => format!("{}{}", "0".repeat(padding), text),
+        },
+        // Numbers align right and everything else left, as in Rust
+        (false, true) => format!("{}{}", " ".repeat(padding), text),
+        (false, false) => format!("{}{}", text, " ".repeat(padding)),
+    })
+}
+
 impl TryFrom<u16> for OpCode {
     type Error = ();
 
@@ -621,12 +1872,32 @@ impl TryFrom<u16> for OpCode {
             0x0001 => Ok(OpCode::Return),
             0x0002 => Ok(OpCode::Call),
             0x0003 => Ok(OpCode::Branch),
+            0x0004 => Ok(OpCode::Loop),
+            0x0005 => Ok(OpCode::Exit),
+            0x0006 => Ok(OpCode::Assert),
+            0x0007 => Ok(OpCode::Try),
+            0x0008 => Ok(OpCode::Throw),
+            0x0009 => Ok(OpCode::Seq),
             
             0x0100 => Ok(OpCode::Add),
             0x0101 => Ok(OpCode::Sub),
             0x0102 => Ok(OpCode::Mul),
             0x0103 => Ok(OpCode::Div),
             0x0104 => Ok(OpCode::Mod),
+            0x0105 => Ok(OpCode::BitAnd),
+            0x0106 => Ok(OpCode::BitOr),
+            0x0107 => Ok(OpCode::BitXor),
+            0x0108 => Ok(OpCode::BitNot),
+            0x0109 => Ok(OpCode::Shl),
+            0x010A => Ok(OpCode::Shr),
+            0x010B => Ok(OpCode::Sqrt),
+            0x010C => Ok(OpCode::Pow),
+            0x010D => Ok(OpCode::Abs),
+            0x010E => Ok(OpCode::Floor),
+            0x010F => Ok(OpCode::Ceil),
+            0x0110 => Ok(OpCode::Min),
+            0x0111 => Ok(OpCode::Max),
+            0x0112 => Ok(OpCode::Neg),
             
             0x0200 => Ok(OpCode::Eq),
             0x0201 => Ok(OpCode::Ne),
@@ -645,6 +1916,8 @@ impl TryFrom<u16> for OpCode {
             0x0402 => Ok(OpCode::Alloc),
             0x0403 => Ok(OpCode::Free),
             0x0404 => Ok(OpCode::LoadArg),
+            0x0405 => Ok(OpCode::PtrAdd),
+            0x0406 => Ok(OpCode::ArgCount),
             
             0x0500 => Ok(OpCode::ConstInt),
             0x0501 => Ok(OpCode::ConstFloat),
@@ -657,15 +1930,32 @@ impl TryFrom<u16> for OpCode {
             0x0603 => Ok(OpCode::ArraySet),
             0x0604 => Ok(OpCode::MapGet),
             0x0605 => Ok(OpCode::MapSet),
+            0x0606 => Ok(OpCode::ArrayLen),
+            0x0607 => Ok(OpCode::ArrayPush),
+            0x0608 => Ok(OpCode::ArrayPop),
+            0x0609 => Ok(OpCode::ArraySlice),
+            0x060A => Ok(OpCode::ArrayConcat),
+            0x060B => Ok(OpCode::MapContains),
+            0x060C => Ok(OpCode::MapKeys),
+            0x060D => Ok(OpCode::MapValues),
+            0x060E => Ok(OpCode::MapDelete),
             
             0x0700 => Ok(OpCode::DefineFunc),
             0x0701 => Ok(OpCode::CreateClosure),
+            0x0702 => Ok(OpCode::LoadParam),
             
             0x0800 => Ok(OpCode::Cast),
             0x0801 => Ok(OpCode::TypeOf),
             
             0x0900 => Ok(OpCode::Print),
             0x0901 => Ok(OpCode::Read),
+            0x0902 => Ok(OpCode::Random),
+            0x0903 => Ok(OpCode::Now),
+            0x0904 => Ok(OpCode::Sleep),
+            0x0905 => Ok(OpCode::FileRead),
+            0x0906 => Ok(OpCode::FileWrite),
+            0x0907 => Ok(OpCode::GetEnv),
+            0x0908 => Ok(OpCode::HttpGet),
             
             0x0A00 => Ok(OpCode::UICreateElement),
             0x0A01 => Ok(OpCode::UISetAttribute),
@@ -674,6 +1964,17 @@ impl TryFrom<u16> for OpCode {
             0x0B00 => Ok(OpCode::AsyncBegin),
             0x0B01 => Ok(OpCode::AsyncAwait),
             0x0B02 => Ok(OpCode::AsyncComplete),
+            0x0B03 => Ok(OpCode::AsyncTimeout),
+            0x0B04 => Ok(OpCode::AsyncJoin),
+            
+            0x0C00 => Ok(OpCode::StrConcat),
+            0x0C01 => Ok(OpCode::StrLen),
+            0x0C02 => Ok(OpCode::StrSlice),
+            0x0C03 => Ok(OpCode::Concat),
+            0x0C04 => Ok(OpCode::Len),
+            0x0C05 => Ok(OpCode::ParseInt),
+            0x0C06 => Ok(OpCode::ParseFloat),
+            0x0C07 => Ok(OpCode::Format),
             
             0x0F00 => Ok(OpCode::ExternalCall),
             
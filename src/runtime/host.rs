@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use crate::runtime::{Value, Result};
+
+/// A host function callable from a program through ExternalCall.
+pub type HostFunction = Box<dyn Fn(&[Value]) -> Result<Value>>;
+
+/// Host functions available to ExternalCall, looked up by name.
+#[derive(Default)]
+pub struct HostFunctionRegistry {
+    functions: HashMap<String, HostFunction>,
+}
+
+impl HostFunctionRegistry {
+    pub fn new() -> Self {
+        HostFunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Register `function` under `name`, replacing any earlier registration.
+    pub fn register(&mut self, name: &str, function: HostFunction) {
+        self.functions.insert(name.to_string(), function);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&HostFunction> {
+        self.functions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+}
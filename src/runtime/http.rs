@@ -0,0 +1,118 @@
+//! Minimal HTTP/1.1 client behind the HttpGet opcode. It speaks plain
+//! `http://` over std's TCP stream so the crate needs no extra dependencies;
+//! there is no TLS support.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Fetch `url`, failing if the whole exchange takes longer than `timeout`.
+/// Errors are plain descriptions; the caller attaches the URL.
+pub fn get(url: &str, timeout: Duration) -> std::result::Result<HttpResponse, String> {
+    let deadline = Instant::now() + timeout;
+    let rest = url.strip_prefix("http://")
+        .ok_or("only http:// URLs are supported")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err("missing host".to_string());
+    }
+
+    let has_port = authority.rsplit_once(':').is_some_and(|(_, port)| !port.contains(']'));
+    let address = if has_port { authority.to_string() } else { format!("{}:80", authority) };
+    let socket = address.to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", authority, e))?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", authority))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket, timeout)
+        .map_err(|e| format!("cannot connect: {}", e))?;
+    stream.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept-Encoding: identity\r\n\r\n",
+        path, authority
+    ).map_err(|e| format!("cannot send request: {}", e))?;
+
+    let mut raw = Vec::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(format!("timed out after {}ms", timeout.as_millis()));
+        }
+        stream.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => raw.extend_from_slice(&buffer[..n]),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                return Err(format!("timed out after {}ms", timeout.as_millis()));
+            }
+            Err(e) => return Err(format!("cannot read response: {}", e)),
+        }
+    }
+
+    parse_response(&raw)
+}
+
+fn parse_response(raw: &[u8]) -> std::result::Result<HttpResponse, String> {
+    let split = raw.windows(4).position(|w| w == b"\r\n\r\n")
+        .ok_or("malformed response: no end of headers")?;
+    let head = std::str::from_utf8(&raw[..split])
+        .map_err(|_| "malformed response: headers are not UTF-8")?;
+    let mut body = &raw[split + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or("malformed response: bad status line")?;
+
+    let mut chunked = false;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked") {
+                chunked = true;
+            } else if name.eq_ignore_ascii_case("content-length") {
+                let length: usize = value.parse()
+                    .map_err(|_| "malformed response: bad Content-Length")?;
+                body = &body[..length.min(body.len())];
+            }
+        }
+    }
+
+    let body = if chunked { decode_chunked(body)? } else { body.to_vec() };
+    let body = String::from_utf8(body)
+        .map_err(|_| "response body is not UTF-8".to_string())?;
+    Ok(HttpResponse { status, body })
+}
+
+fn decode_chunked(mut data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n")
+            .ok_or("malformed chunked body")?;
+        let size_line = std::str::from_utf8(&data[..line_end])
+            .map_err(|_| "malformed chunked body")?;
+        let size_text = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_text, 16)
+            .map_err(|_| "malformed chunked body")?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err("truncated chunked body".to_string());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or(&[]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::runtime::{Value, RuntimeError, Result};
 
 #[derive(Debug)]
@@ -13,9 +13,12 @@ pub struct MemoryManager {
 pub struct HeapObject {
     pub address: u64,
     pub size: usize,
-    pub value: Value,
+    /// One value per slot; `MemoryReference::offset` picks the slot
+    pub slots: Vec<Value>,
     pub ref_count: usize,
     pub is_freed: bool,
+    /// Bumped on every store or free, so cached reads can tell they are stale
+    pub generation: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -34,11 +37,18 @@ impl MemoryManager {
         }
     }
     
+    /// Cap the total bytes live allocations may hold. Existing blocks are
+    /// kept even if they already exceed the new limit.
+    pub fn set_allocation_limit(&mut self, limit: usize) {
+        self.allocation_limit = limit;
+    }
+    
     pub fn allocate(&mut self, size: usize, initial_value: Value) -> Result<u64> {
         if self.total_allocated + size > self.allocation_limit {
-            return Err(RuntimeError::InvalidOperation(
-                "Memory allocation limit exceeded".to_string()
-            ));
+            return Err(RuntimeError::MemoryLimitExceeded {
+                requested: size,
+                limit: self.allocation_limit,
+            });
         }
         
         let address = self.next_address;
@@ -48,16 +58,17 @@ impl MemoryManager {
         let heap_obj = HeapObject {
             address,
             size,
-            value: initial_value,
+            slots: vec![initial_value; size],
             ref_count: 1,
             is_freed: false,
+            generation: 0,
         };
         
         self.heap.insert(address, heap_obj);
         Ok(address)
     }
     
-    pub fn load(&self, address: u64) -> Result<Value> {
+    pub fn load(&self, address: u64, offset: usize) -> Result<Value> {
         let obj = self.heap.get(&address)
             .ok_or_else(|| RuntimeError::InvalidOperation(
                 format!("Invalid memory address: 0x{:x}", address)
@@ -69,10 +80,17 @@ impl MemoryManager {
             ));
         }
         
-        Ok(obj.value.clone())
+        obj.slots.get(offset)
+            .cloned()
+            .ok_or(RuntimeError::ArrayIndexOutOfBounds { index: offset, length: obj.size })
+    }
+    
+    /// Current write generation of the object at `address`.
+    pub fn generation(&self, address: u64) -> Option<u64> {
+        self.heap.get(&address).map(|obj| obj.generation)
     }
     
-    pub fn store(&mut self, address: u64, value: Value) -> Result<()> {
+    pub fn store(&mut self, address: u64, offset: usize, value: Value) -> Result<()> {
         let obj = self.heap.get_mut(&address)
             .ok_or_else(|| RuntimeError::InvalidOperation(
                 format!("Invalid memory address: 0x{:x}", address)
@@ -84,7 +102,11 @@ impl MemoryManager {
             ));
         }
         
-        obj.value = value;
+        let length = obj.size;
+        let slot = obj.slots.get_mut(offset)
+            .ok_or(RuntimeError::ArrayIndexOutOfBounds { index: offset, length })?;
+        *slot = value;
+        obj.generation += 1;
         Ok(())
     }
     
@@ -101,6 +123,7 @@ impl MemoryManager {
         }
         
         obj.is_freed = true;
+        obj.generation += 1;
         self.total_allocated -= obj.size;
         Ok(())
     }
@@ -179,6 +202,61 @@ impl MemoryManager {
         
         count
     }
+    
+    /// Mark every object reachable from `roots` through `MemoryRef`s held in
+    /// slots, then remove everything else along with freed objects. Unlike
+    /// reference counting this reclaims cycles. Returns the number removed.
+    pub fn collect_unreachable(&mut self, roots: &[u64]) -> usize {
+        let mut marked = HashSet::new();
+        let mut pending: Vec<u64> = roots.to_vec();
+        while let Some(address) = pending.pop() {
+            if !marked.insert(address) {
+                continue;
+            }
+            if let Some(obj) = self.heap.get(&address).filter(|obj| !obj.is_freed) {
+                for slot in &obj.slots {
+                    collect_memory_refs(slot, &mut pending);
+                }
+            }
+        }
+        
+        let unreachable: Vec<u64> = self.heap.iter()
+            .filter(|(address, obj)| obj.is_freed || !marked.contains(address))
+            .map(|(address, _)| *address)
+            .collect();
+        for address in &unreachable {
+            if let Some(obj) = self.heap.remove(address) {
+                if !obj.is_freed {
+                    self.total_allocated -= obj.size;
+                }
+            }
+        }
+        
+        unreachable.len()
+    }
+}
+
+/// Push the address of every `MemoryRef` inside `value`, however nested.
+pub fn collect_memory_refs(value: &Value, addresses: &mut Vec<u64>) {
+    match value {
+        Value::MemoryRef(r) => addresses.push(r.address),
+        Value::Array(items) => {
+            for item in items {
+                collect_memory_refs(item, addresses);
+            }
+        }
+        Value::Map(map) => {
+            for item in map.values() {
+                collect_memory_refs(item, addresses);
+            }
+        }
+        Value::Function(f) => {
+            for item in f.captured_values.values() {
+                collect_memory_refs(item, addresses);
+            }
+        }
+        _ => {}
+    }
 }
 
 #[derive(Debug)]
@@ -199,7 +277,7 @@ mod tests {
         let mut mem = MemoryManager::new();
         
         let addr = mem.allocate(8, Value::Int(42)).unwrap();
-        let value = mem.load(addr).unwrap();
+        let value = mem.load(addr, 0).unwrap();
         
         assert_eq!(value, Value::Int(42));
     }
@@ -209,8 +287,8 @@ mod tests {
         let mut mem = MemoryManager::new();
         
         let addr = mem.allocate(8, Value::Int(42)).unwrap();
-        mem.store(addr, Value::Int(100)).unwrap();
-        let value = mem.load(addr).unwrap();
+        mem.store(addr, 0, Value::Int(100)).unwrap();
+        let value = mem.load(addr, 0).unwrap();
         
         assert_eq!(value, Value::Int(100));
     }
@@ -223,8 +301,8 @@ mod tests {
         mem.free(addr).unwrap();
         
         // Should error when accessing freed memory
-        assert!(mem.load(addr).is_err());
-        assert!(mem.store(addr, Value::Int(100)).is_err());
+        assert!(mem.load(addr, 0).is_err());
+        assert!(mem.store(addr, 0, Value::Int(100)).is_err());
     }
     
     #[test]
@@ -254,11 +332,11 @@ mod tests {
         mem.release_ref(addr).unwrap();
         
         // Still accessible with 1 ref
-        assert!(mem.load(addr).is_ok());
+        assert!(mem.load(addr, 0).is_ok());
         
         // Final release should auto-free
         mem.release_ref(addr).unwrap();
-        assert!(mem.load(addr).is_err());
+        assert!(mem.load(addr, 0).is_err());
     }
     
     #[test]
@@ -278,7 +356,7 @@ mod tests {
         assert_eq!(mem.heap.len(), 1);
         
         // addr2 should still be accessible
-        assert!(mem.load(addr2).is_ok());
+        assert!(mem.load(addr2, 0).is_ok());
     }
     
     #[test]
@@ -291,6 +369,55 @@ mod tests {
         mem.allocate(40, Value::Nil).unwrap();
         
         // Should fail - would exceed limit
-        assert!(mem.allocate(20, Value::Nil).is_err());
+        assert!(matches!(
+            mem.allocate(20, Value::Nil),
+            Err(RuntimeError::MemoryLimitExceeded { requested: 20, limit: 100 })
+        ));
+    }
+    
+    #[test]
+    fn test_slots_by_offset() {
+        let mut mem = MemoryManager::new();
+        
+        let addr = mem.allocate(8, Value::Nil).unwrap();
+        mem.store(addr, 0, Value::Int(10)).unwrap();
+        mem.store(addr, 3, Value::Int(13)).unwrap();
+        
+        assert_eq!(mem.load(addr, 0).unwrap(), Value::Int(10));
+        assert_eq!(mem.load(addr, 3).unwrap(), Value::Int(13));
+        assert_eq!(mem.load(addr, 7).unwrap(), Value::Nil);
+        
+        assert!(matches!(
+            mem.load(addr, 8),
+            Err(RuntimeError::ArrayIndexOutOfBounds { index: 8, length: 8 })
+        ));
+        assert!(mem.store(addr, 8, Value::Int(1)).is_err());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_collect_unreachable_cycle() {
+        let mut mem = MemoryManager::new();
+        
+        let a = mem.allocate(2, Value::Nil).unwrap();
+        let b = mem.allocate(2, Value::Nil).unwrap();
+        let kept = mem.allocate(1, Value::Int(7)).unwrap();
+        let ref_to = |address| Value::MemoryRef(MemoryReference { address, offset: 0 });
+        
+        // a and b point at each other, b through a nested array
+        mem.store(a, 1, ref_to(b)).unwrap();
+        mem.store(b, 0, Value::Array(vec![Value::Int(1), ref_to(a)])).unwrap();
+        
+        // Reachable from a root, the cycle survives
+        assert_eq!(mem.collect_unreachable(&[a, kept]), 0);
+        assert!(mem.load(b, 0).is_ok());
+        
+        // With both roots dropped both blocks go, whatever their ref counts
+        assert_eq!(mem.collect_unreachable(&[kept]), 2);
+        assert!(mem.load(a, 0).is_err());
+        assert!(mem.load(b, 0).is_err());
+        assert_eq!(mem.get_stats().total_allocated, 1);
+        
+        assert_eq!(mem.collect_unreachable(&[]), 1);
+        assert_eq!(mem.get_stats().heap_size, 0);
+    }
+}
@@ -4,10 +4,20 @@ pub mod context;
 pub mod error;
 pub mod memory;
 pub mod async_runtime;
+pub mod host;
+pub mod trace;
+pub mod profile;
+pub mod debugger;
+#[cfg(feature = "http")]
+pub mod http;
 
 pub use executor::*;
 pub use value::*;
 pub use context::*;
 pub use error::*;
 pub use memory::*;
-pub use async_runtime::*;
\ No newline at end of file
+pub use async_runtime::*;
+pub use host::*;
+pub use trace::*;
+pub use profile::*;
+pub use debugger::*;
\ No newline at end of file
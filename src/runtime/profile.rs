@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::core::OpCode;
+
+/// Per-opcode and per-node counters collected while profiling is enabled.
+/// Durations are inclusive: an opcode that evaluates arguments lazily (a
+/// Branch arm, a Loop body, a Call) is also charged for that work.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    opcodes: HashMap<OpCode, (u64, Duration)>,
+    node_hits: HashMap<u32, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpcodeProfile {
+    pub opcode: OpCode,
+    pub count: u64,
+    pub total_time: Duration,
+}
+
+/// Snapshot of a `Profiler`, with opcodes ordered by total time and nodes by
+/// hit count, most expensive first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileReport {
+    pub opcodes: Vec<OpcodeProfile>,
+    /// (result id, evaluations)
+    pub node_hits: Vec<(u32, u64)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, node_id: u32, opcode: OpCode, duration: Duration) {
+        let entry = self.opcodes.entry(opcode).or_insert((0, Duration::ZERO));
+        entry.0 += 1;
+        entry.1 += duration;
+        *self.node_hits.entry(node_id).or_insert(0) += 1;
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        let mut opcodes: Vec<OpcodeProfile> = self.opcodes.iter()
+            .map(|(&opcode, &(count, total_time))| OpcodeProfile { opcode, count, total_time })
+            .collect();
+        opcodes.sort_by(|a, b| {
+            b.total_time.cmp(&a.total_time)
+                .then(b.count.cmp(&a.count))
+                .then((a.opcode as u16).cmp(&(b.opcode as u16)))
+        });
+
+        let mut node_hits: Vec<(u32, u64)> = self.node_hits.iter().map(|(&id, &hits)| (id, hits)).collect();
+        node_hits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        ProfileReport { opcodes, node_hits }
+    }
+}
+
+impl ProfileReport {
+    /// Number of times `opcode` was executed.
+    pub fn count(&self, opcode: OpCode) -> u64 {
+        self.opcodes.iter().find(|p| p.opcode == opcode).map_or(0, |p| p.count)
+    }
+
+    /// Number of times the node producing `node_id` was evaluated.
+    pub fn hits(&self, node_id: u32) -> u64 {
+        self.node_hits.iter().find(|(id, _)| *id == node_id).map_or(0, |(_, hits)| *hits)
+    }
+
+    /// Opcode table sorted by total time, followed by the ten hottest nodes.
+    pub fn render_text(&self) -> String {
+        let mut output = String::from("Profile\n");
+        output.push_str(&format!("{:<16} {:>10} {:>14}\n", "Opcode", "Calls", "Total (ms)"));
+        for profile in &self.opcodes {
+            output.push_str(&format!(
+                "{:<16} {:>10} {:>14.3}\n",
+                format!("{:?}", profile.opcode),
+                profile.count,
+                profile.total_time.as_secs_f64() * 1000.0
+            ));
+        }
+
+        output.push_str("\nHot nodes\n");
+        for (node_id, hits) in self.node_hits.iter().take(10) {
+            output.push_str(&format!("  node {:<10} {:>10} hits\n", node_id, hits));
+        }
+        output
+    }
+}
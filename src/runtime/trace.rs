@@ -0,0 +1,17 @@
+use std::time::Duration;
+use crate::core::OpCode;
+use crate::runtime::{Value, RuntimeError};
+
+/// One node evaluation recorded by `Executor::enable_tracing`. Events are
+/// recorded as evaluations finish, so a node's arguments come before it.
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    pub node_id: u32,
+    pub opcode: OpCode,
+    /// Values of the arguments evaluated before the opcode ran. Lazily
+    /// evaluated arguments, such as Branch arms, have events of their own.
+    pub args_resolved: Vec<Value>,
+    pub result: std::result::Result<Value, RuntimeError>,
+    /// Time spent in the opcode, including lazily evaluated arguments
+    pub duration: Duration,
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 use crate::runtime::{MemoryReference, AsyncHandle};
 
@@ -10,13 +11,51 @@ pub enum Value {
     Float(f64),
     String(String),
     Array(Vec<Value>),
-    Map(HashMap<String, Value>),
+    Map(HashMap<MapKey, Value>),
     Function(Arc<Function>),
     NodeRef(u32),
     MemoryRef(MemoryReference),
     AsyncHandle(AsyncHandle),
 }
 
+/// Key of a `Value::Map`. Only values with exact equality can be keys, so
+/// floats and compound values are excluded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MapKey {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Option<MapKey> {
+        match value {
+            Value::Bool(b) => Some(MapKey::Bool(*b)),
+            Value::Int(i) => Some(MapKey::Int(*i)),
+            Value::String(s) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Int(i) => Value::Int(*i),
+            MapKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapKey::Bool(b) => write!(f, "{}", b),
+            MapKey::Int(i) => write!(f, "{}", i),
+            MapKey::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     pub node_id: u32,
@@ -66,19 +105,27 @@ impl Value {
                 format!("[{}]", elements.join(", "))
             }
             Value::Map(map) => {
-                let pairs: Vec<String> = map.iter()
+                // Sorted by key so the output doesn't depend on hash order
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let pairs: Vec<String> = entries.iter()
                     .map(|(k, v)| format!("{}: {}", k, v.to_string()))
                     .collect();
                 format!("{{{}}}", pairs.join(", "))
             }
-            Value::Function(f) => format!("<function:{}>", f.node_id),
+            Value::Function(f) => format!("<fn@{}/{}>", f.node_id, f.arity),
             Value::NodeRef(id) => format!("<node:{}>", id),
-            Value::MemoryRef(r) => format!("<memory:0x{:x}+{}>", r.address, r.offset),
-            Value::AsyncHandle(h) => format!("<async:{}>", h.id),
+            Value::MemoryRef(r) => format!("<memref 0x{:x}+{}>", r.address, r.offset),
+            Value::AsyncHandle(h) => format!("<async #{}>", h.id),
         }
     }
 }
 
+/// Structural equality, as used by Eq and Ne. Arrays are equal when they
+/// have the same length and equal elements in order; maps when they have
+/// the same keys with equal values, whatever order they were built in.
+/// NaN is never equal to anything, itself included. Functions, memory
+/// references and async handles never compare equal.
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
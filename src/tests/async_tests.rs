@@ -64,19 +64,39 @@ fn test_async_await_pending() {
     let await_node = Node::new(OpCode::AsyncAwait, 2).with_args(&[1]);
     
     program.add_node(begin_node);
-    let result = program.add_node(await_node);
-    program.set_entry_point(result);
+    program.add_node(await_node);
+    program.set_entry_point(2);
     
     let mut executor = Executor::new(program);
-    let result = executor.execute().unwrap();
+    let result = executor.execute();
     
-    // Should return the handle since it's still pending
+    // Nothing in the graph completes the handle, so awaiting it fails
+    // rather than handing back the pending handle
     match result {
-        Value::AsyncHandle(_) => {},
-        _ => panic!("Expected AsyncHandle (pending), got {:?}", result),
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("never complete"), "{}", msg),
+        _ => panic!("Expected a never-completes error, got {:?}", result),
     }
 }
 
+#[test]
+fn test_await_before_complete_in_graph_order() {
+    let mut program = Program::new();
+    let value_idx = program.constants.add_int(42);
+    
+    // The await is scheduled before the node that completes its handle
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::AsyncAwait, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::AsyncComplete, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[3, 4]));
+    program.set_entry_point(5);
+    
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+    
+    assert_eq!(result, Value::Array(vec![Value::Int(42), Value::Nil]));
+}
+
 #[test]
 fn test_multiple_async_operations() {
     let mut program = Program::new();
@@ -237,4 +257,120 @@ fn test_async_chain() {
         Value::Int(20) => {},
         _ => panic!("Expected Int(20), got {:?}", result),
     }
-}
\ No newline at end of file
+}
+fn create_sleep_program(millis: i64, with_handle: bool) -> Program {
+    let mut program = Program::new();
+    let ms_idx = program.constants.add_int(millis);
+    
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[ms_idx]));
+    if with_handle {
+        program.add_node(Node::new(OpCode::Sleep, 3).with_args(&[2, 1]));
+        program.add_node(Node::new(OpCode::AsyncAwait, 4).with_args(&[3]));
+        program.set_entry_point(4);
+    } else {
+        program.add_node(Node::new(OpCode::Sleep, 3).with_args(&[2]));
+        program.set_entry_point(3);
+    }
+    program
+}
+
+#[test]
+fn test_sleep_blocks_for_duration() {
+    let mut executor = Executor::new(create_sleep_program(5, false));
+    executor.grant_capability(Capability::Clock);
+    
+    let started = std::time::Instant::now();
+    assert_eq!(executor.execute().unwrap(), Value::Nil);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(5));
+}
+
+#[test]
+fn test_await_sleep_backed_handle() {
+    let mut executor = Executor::new(create_sleep_program(5, true));
+    executor.grant_capability(Capability::Clock);
+    
+    let started = std::time::Instant::now();
+    assert_eq!(executor.execute().unwrap(), Value::Nil);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(5));
+}
+
+#[test]
+fn test_sleep_requires_clock_capability() {
+    let mut executor = Executor::new(create_sleep_program(1, false));
+    
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::Clock, .. })
+    ));
+}
+
+#[test]
+fn test_async_timeout_on_never_completed_handle() {
+    let mut program = Program::new();
+    let ms_idx = program.constants.add_int(50);
+    
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[ms_idx]));
+    program.add_node(Node::new(OpCode::AsyncTimeout, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+    
+    let mut executor = Executor::new(program);
+    let started = std::time::Instant::now();
+    let result = executor.execute();
+    
+    assert!(matches!(result, Err(RuntimeError::AsyncTimeout(_))), "{:?}", result);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+}
+
+#[test]
+fn test_async_timeout_returns_value_in_time() {
+    let mut program = Program::new();
+    let ms_idx = program.constants.add_int(1000);
+    let value_idx = program.constants.add_int(9);
+    
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[ms_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::AsyncTimeout, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::AsyncComplete, 5).with_args(&[1, 3]));
+    program.set_entry_point(4);
+    
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Int(9));
+}
+
+#[test]
+fn test_async_join() {
+    let mut program = Program::new();
+    let val1_idx = program.constants.add_int(100);
+    let val2_idx = program.constants.add_int(200);
+    
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::AsyncBegin, 2));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[val1_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[val2_idx]));
+    program.add_node(Node::new(OpCode::AsyncComplete, 5).with_args(&[1, 3]));
+    program.add_node(Node::new(OpCode::AsyncComplete, 6).with_args(&[2, 4]));
+    program.add_node(Node::new(OpCode::AsyncJoin, 7).with_args(&[1, 2]));
+    program.set_entry_point(7);
+    
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+    
+    assert_eq!(result, Value::Array(vec![Value::Int(100), Value::Int(200)]));
+}
+
+#[test]
+fn test_async_join_propagates_failure() {
+    let mut runtime = AsyncRuntime::new();
+    let ok = runtime.begin_async();
+    let failed = runtime.begin_async();
+    runtime.complete_async(&ok, Value::Int(1)).unwrap();
+    runtime.fail_async(&failed, RuntimeError::InvalidOperation("boom".to_string())).unwrap();
+    
+    match runtime.join(&[ok, failed]) {
+        Err(RuntimeError::InvalidOperation(msg)) => assert_eq!(msg, "boom"),
+        other => panic!("Expected the failure to propagate, got {:?}", other),
+    }
+}
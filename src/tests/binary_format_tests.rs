@@ -5,8 +5,8 @@ use std::io::Cursor;
 fn test_file_header_creation() {
     let header = FileHeader::new(3);
     assert_eq!(header.magic, DER_MAGIC);
-    assert_eq!(header.version, VERSION);
-    assert_eq!(header.chunk_count, 3);
+    assert_eq!({ header.version }, VERSION);
+    assert_eq!({ header.chunk_count }, 3);
 }
 
 #[test]
@@ -146,4 +146,176 @@ fn test_opcode_range() {
         let value = opcode as u16;
         assert!(value <= 0xFFFF);
     }
-}
\ No newline at end of file
+}
+
+fn build_sized_program() -> Program {
+    let mut program = Program::new();
+    let int_idx = program.constants.add_int(42);
+    program.constants.add_float(2.5);
+    let str_idx = program.constants.add_string("héllo".to_string());
+    program.constants.add_bool(true);
+
+    let n1 = Node::new(OpCode::ConstInt, 1).with_args(&[int_idx]);
+    let n2 = Node::new(OpCode::ConstString, 2).with_args(&[str_idx]);
+    let n3 = Node::new(OpCode::Print, 3).with_args(&[2]);
+    program.add_node(n1);
+    program.add_node(n2);
+    program.add_node(n3);
+    program.set_entry_point(3);
+
+    program.require_capability(Capability::UI);
+    program.metadata.traits.push(Trait {
+        name: "Printing".to_string(),
+        preconditions: vec!["string is valid".to_string()],
+        postconditions: vec!["text is shown".to_string(), "returns nil".to_string()],
+    });
+    program.header.chunk_count = 3;
+    program
+}
+
+#[test]
+fn test_serialized_size_matches_bytes() {
+    let empty = Program::new();
+    assert_eq!(empty.serialized_size(), empty.to_bytes().unwrap().len());
+
+    let program = build_sized_program();
+    let bytes = program.to_bytes().unwrap();
+    assert_eq!(program.serialized_size(), bytes.len());
+}
+
+#[test]
+fn test_serializer_size_limit() {
+    let program = build_sized_program();
+    let size = program.serialized_size();
+
+    let mut buffer = Vec::new();
+    let mut serializer = DERSerializer::new(&mut buffer).with_size_limit(size - 1);
+    assert!(serializer.write_program(&program).is_err());
+    assert!(buffer.is_empty());
+
+    let mut buffer = Vec::new();
+    let mut serializer = DERSerializer::new(&mut buffer).with_size_limit(size);
+    serializer.write_program(&program).unwrap();
+    assert_eq!(buffer.len(), size);
+}
+
+fn node_fields(node: &Node) -> (u16, u16, u32, u64, u8, [u32; 3]) {
+    (node.opcode, node.flags, node.result_id, node.timestamp, node.arg_count, node.args)
+}
+
+#[test]
+fn test_impl_chunk_records_node_count() {
+    let mut program = build_sized_program();
+    program.nodes[1].set_flag(NodeFlag::Volatile);
+    let bytes = program.to_bytes().unwrap();
+
+    // The IMPL chunk is the count followed by fixed-size records
+    let impl_at = bytes.windows(4).position(|w| w == b"IMPL").unwrap();
+    let size = u32::from_le_bytes(bytes[impl_at + 4..impl_at + 8].try_into().unwrap()) as usize;
+    let count = u32::from_le_bytes(bytes[impl_at + 16..impl_at + 20].try_into().unwrap()) as usize;
+    assert_eq!(count, 3);
+    assert_eq!(size, 4 + count * NODE_RECORD_SIZE);
+
+    let loaded = DERDeserializer::new(Cursor::new(bytes)).read_program().unwrap();
+    assert_eq!({ loaded.header.version }, VERSION);
+    assert_eq!(loaded.nodes.len(), program.nodes.len());
+    for (loaded, original) in loaded.nodes.iter().zip(&program.nodes) {
+        assert_eq!(node_fields(loaded), node_fields(original));
+    }
+}
+
+#[test]
+fn test_read_version_1_0_impl_chunk() {
+    let program = build_sized_program();
+    let mut bytes = program.to_bytes().unwrap();
+
+    // Rewrite as a 1.0 file: no node count, size declared as 32 bytes per node
+    bytes[4..6].copy_from_slice(&VERSION_1_0.to_le_bytes());
+    let impl_at = bytes.windows(4).position(|w| w == b"IMPL").unwrap();
+    let legacy_size = (program.nodes.len() * 32) as u32;
+    bytes[impl_at + 4..impl_at + 8].copy_from_slice(&legacy_size.to_le_bytes());
+    bytes.drain(impl_at + 16..impl_at + 20);
+
+    let loaded = DERDeserializer::new(Cursor::new(bytes)).read_program().unwrap();
+    assert_eq!(loaded.nodes.len(), 3);
+    for (loaded, original) in loaded.nodes.iter().zip(&program.nodes) {
+        assert_eq!(node_fields(loaded), node_fields(original));
+    }
+    assert_eq!(loaded.constants.get_string(0).map(String::as_str), Some("héllo"));
+}
+
+#[test]
+fn test_volatile_flag_round_trip() {
+    use crate::visualization::TextRenderer;
+
+    let mut program = Program::new();
+    let mut node = Node::new(OpCode::Nop, 0);
+    node.set_flag(NodeFlag::Volatile);
+    program.add_node(node);
+    program.header.chunk_count = 3;
+
+    let bytes = program.to_bytes().unwrap();
+    let loaded = DERDeserializer::new(Cursor::new(bytes)).read_program().unwrap();
+    assert!(loaded.nodes[0].has_flag(NodeFlag::Volatile));
+
+    let text = TextRenderer::new(loaded).render();
+    assert!(text.contains("(volatile)"));
+}
+
+#[test]
+fn test_declare_node_capabilities() {
+    let mut program = Program::new();
+    let text = program.constants.add_string("out.txt".to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[text]));
+    program.add_node(Node::new(OpCode::Print, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::FileRead, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Print, 4).with_args(&[3]));
+
+    program.declare_node_capabilities();
+    assert_eq!(
+        program.metadata.required_capabilities,
+        vec![Capability::IO, Capability::FileSystem]
+    );
+
+    assert_eq!(OpCode::Add.required_capability(), None);
+    assert_eq!(OpCode::ExternalCall.required_capability(), Some(Capability::ExternalCode));
+}
+
+fn create_hash_sample_program() -> Program {
+    let mut builder = ProgramBuilder::new();
+    let a = builder.const_int(6);
+    let b = builder.const_float(7.5);
+    let product = builder.mul(a, b);
+    builder.build(product)
+}
+
+#[test]
+fn test_content_hash_ignores_timestamps() {
+    let first = create_hash_sample_program();
+    let mut second = create_hash_sample_program();
+    for node in &mut second.nodes {
+        node.timestamp += 12345;
+    }
+    assert_eq!(first.content_hash(), second.content_hash());
+
+    let mut changed_constant = create_hash_sample_program();
+    changed_constant.constants.floats[0] = 7.25;
+    assert_ne!(first.content_hash(), changed_constant.content_hash());
+
+    let mut changed_node = create_hash_sample_program();
+    changed_node.nodes[2].opcode = OpCode::Add as u16;
+    assert_ne!(first.content_hash(), changed_node.content_hash());
+}
+
+#[test]
+fn test_semantic_file_hash_is_sha256() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), b"abc").unwrap();
+
+    let generator = SemanticAnnotationGenerator::new();
+    assert_eq!(
+        generator.calculate_file_hash(file.path().to_str().unwrap()),
+        "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(generator.calculate_file_hash("/nonexistent/missing.der"), "unknown");
+}
@@ -0,0 +1,86 @@
+use crate::core::*;
+use crate::runtime::*;
+
+/// Serialized form with node timestamps cleared, so programs built at
+/// different moments compare equal.
+fn normalized_bytes(mut program: Program) -> Vec<u8> {
+    for node in &mut program.nodes {
+        node.timestamp = 0;
+    }
+    program.to_bytes().unwrap()
+}
+
+#[test]
+fn test_builder_matches_hand_written_arithmetic() {
+    // (10 + 20) * (5 - 3), wired by hand
+    let mut expected = Program::new();
+    let c10 = expected.constants.add_int(10);
+    let c20 = expected.constants.add_int(20);
+    let c5 = expected.constants.add_int(5);
+    let c3 = expected.constants.add_int(3);
+    expected.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c10]));
+    expected.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c20]));
+    expected.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    expected.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[c5]));
+    expected.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[c3]));
+    expected.add_node(Node::new(OpCode::Sub, 6).with_args(&[4, 5]));
+    expected.add_node(Node::new(OpCode::Mul, 7).with_args(&[3, 6]));
+    expected.set_entry_point(7);
+
+    let mut builder = ProgramBuilder::new();
+    let a = builder.const_int(10);
+    let b = builder.const_int(20);
+    let sum = builder.add(a, b);
+    let c = builder.const_int(5);
+    let d = builder.const_int(3);
+    let difference = builder.sub(c, d);
+    let product = builder.mul(sum, difference);
+    let program = builder.build(product);
+
+    assert_eq!(product.result_id(), 7);
+    assert_eq!(normalized_bytes(program.clone()), normalized_bytes(expected));
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(60));
+}
+
+#[test]
+fn test_builder_matches_hand_written_array() {
+    let mut expected = Program::new();
+    let val1_idx = expected.constants.add_int(10);
+    let val2_idx = expected.constants.add_int(20);
+    let val3_idx = expected.constants.add_int(30);
+    let index_idx = expected.constants.add_int(1);
+    expected.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[val1_idx]));
+    expected.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[val2_idx]));
+    expected.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[val3_idx]));
+    expected.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[1, 2, 3]));
+    expected.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[index_idx]));
+    expected.add_node(Node::new(OpCode::ArrayGet, 6).with_args(&[4, 5]));
+    expected.set_entry_point(6);
+
+    let mut builder = ProgramBuilder::new();
+    let elements = [builder.const_int(10), builder.const_int(20), builder.const_int(30)];
+    let array = builder.create_array(&elements);
+    let index = builder.const_int(1);
+    let get = builder.array_get(array, index);
+    let program = builder.build(get);
+
+    assert_eq!(normalized_bytes(program.clone()), normalized_bytes(expected));
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(20));
+}
+
+#[test]
+fn test_builder_declares_capabilities() {
+    let mut builder = ProgramBuilder::new();
+    let message = builder.const_string("Hello, World!");
+    let print = builder.print(message);
+    let program = builder.build(print);
+
+    assert_eq!(program.metadata.entry_point, print.result_id());
+    assert_eq!(program.metadata.required_capabilities, vec![Capability::IO]);
+
+    let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    executor.execute().unwrap();
+    assert_eq!(executor.take_output(), "Hello, World!\n");
+}
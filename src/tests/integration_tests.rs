@@ -101,13 +101,20 @@ fn test_capabilities() {
     let print_node = Node::new(OpCode::Print, 2).with_args(&[1]);
     
     program.add_node(str_node);
-    let result = program.add_node(print_node);
-    program.set_entry_point(result);
+    program.add_node(print_node);
+    program.set_entry_point(2);
     
-    // This should work since Print doesn't require special capabilities
-    let mut executor = Executor::new(program);
+    // Print requires IO, and declaring it in the program grants nothing
+    program.require_capability(Capability::IO);
+    let mut executor = Executor::new(program.clone());
     let result = executor.execute();
-    assert!(result.is_ok());
+    assert!(matches!(result, Err(RuntimeError::CapabilityDenied { opcode: OpCode::Print, capability: Capability::IO })));
+    
+    let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    assert!(executor.execute().is_ok());
+    assert_eq!(executor.take_output(), "Hello\n");
 }
 
 #[test]
@@ -188,4 +195,346 @@ fn test_mixed_types() {
         Value::Float(f) if (f - 25.0).abs() < 0.001 => {},
         _ => panic!("Expected Float(25.0), got {:?}", result),
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_node_descriptions_consistent_across_renderers() {
+    use crate::compiler::AICodeGenerator;
+    use crate::visualization::{GraphRenderer, TextRenderer};
+
+    let mut generator = AICodeGenerator::new();
+    let (program, semantics) = generator
+        .generate_with_semantics("calculate 2 + 3", "hello.der")
+        .unwrap();
+
+    let const_node = program
+        .nodes
+        .iter()
+        .find(|n| n.opcode == OpCode::ConstInt as u16)
+        .copied()
+        .expect("program should contain an integer constant");
+
+    let description = NodeDescriber::new(&program)
+        .with_semantics(&semantics)
+        .describe(&const_node);
+    assert_eq!(description.short_label, "ConstInt");
+    assert!(description.semantic_role.is_some());
+    let preview = description.constant_preview.clone().unwrap();
+    let raw = program.constants.get_int(const_node.args[0]).unwrap();
+    assert_eq!(preview, raw.to_string());
+
+    let dot = GraphRenderer::new(program.clone()).render_to_dot();
+    assert!(dot.contains(&format!("Value: {}", preview)));
+
+    // The text renderer walks the graph by node index, so render the
+    // constant on its own to look at its line
+    let mut single = program.clone();
+    single.set_entry_point(
+        program.nodes.iter().position(|n| n.result_id == const_node.result_id).unwrap() as u32,
+    );
+    let text = TextRenderer::new(single).render();
+    assert!(text.contains(&preview));
+
+    let annotation = &semantics.node_annotations[&const_node.result_id];
+    assert!(annotation.description.contains(&preview));
+}
+
+#[test]
+fn test_string_constant_preview_escaped_in_dot() {
+    use crate::visualization::GraphRenderer;
+
+    let mut program = Program::new();
+    let idx = program.constants.add_string("say \"hi\"".to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[idx]));
+
+    let description = NodeDescriber::new(&program).describe(&program.nodes[0]);
+    assert_eq!(description.constant_preview.as_deref(), Some("\"say \"hi\"\""));
+
+    let dot = GraphRenderer::new(program).render_to_dot();
+    assert!(dot.contains(r#"Value: \"say \"hi\"\""#));
+}
+
+#[test]
+fn test_bounded_loop_pipeline() {
+    // sum = 0; i = 0; while i < 10 { i = i + 1; sum = sum + i } with at most 10 iterations
+    let mut program = Program::new();
+    let c0 = program.constants.add_int(0);
+    let c1 = program.constants.add_int(1);
+    let c10 = program.constants.add_int(10);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c0]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c1]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[c10]));
+    program.add_node(Node::new(OpCode::Alloc, 4).with_args(&[2, 1]));
+    program.add_node(Node::new(OpCode::Alloc, 5).with_args(&[2, 1]));
+    program.add_node(Node::new(OpCode::Load, 6).with_args(&[4]));
+    program.add_node(Node::new(OpCode::Lt, 7).with_args(&[6, 3]));
+    program.add_node(Node::new(OpCode::Load, 8).with_args(&[4]));
+    program.add_node(Node::new(OpCode::Add, 9).with_args(&[8, 2]));
+    program.add_node(Node::new(OpCode::Store, 10).with_args(&[4, 9]));
+    program.add_node(Node::new(OpCode::Load, 11).with_args(&[5]));
+    program.add_node(Node::new(OpCode::Add, 12).with_args(&[11, 10]));
+    program.add_node(Node::new(OpCode::Store, 13).with_args(&[5, 12]));
+    program.add_node(Node::new(OpCode::Loop, 14).with_args(&[7, 13, 3]));
+    program.set_entry_point(14);
+    program.header.chunk_count = 3; // META, IMPL, CNST
+
+    let mut buffer = Vec::new();
+    DERSerializer::new(&mut buffer).write_program(&program).unwrap();
+    let loaded = DERDeserializer::new(Cursor::new(buffer)).read_program().unwrap();
+
+    let mut executor = Executor::new(loaded);
+    assert_eq!(executor.execute().unwrap(), Value::Int(55));
+}
+
+#[test]
+fn test_type_checker_cast_result_type() {
+    use crate::types::{TypeChecker, Type};
+
+    let mut program = Program::new();
+    let value_idx = program.constants.add_int(5);
+    let target_idx = program.constants.add_string("string".to_string());
+    let code_idx = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[target_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[code_idx]));
+    program.add_node(Node::new(OpCode::Cast, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Cast, 5).with_args(&[1, 3]));
+    program.add_node(Node::new(OpCode::Cast, 6).with_args(&[1, 4]));
+    program.set_entry_point(4);
+
+    let mut checker = TypeChecker::new();
+    checker.check_program(&program).unwrap();
+    assert_eq!(checker.node_type(4), Some(&Type::String));
+    assert_eq!(checker.node_type(5), Some(&Type::Float));
+    // A computed target is only known at runtime
+    assert_eq!(checker.node_type(6), Some(&Type::Any));
+}
+
+#[test]
+fn test_text_disassembly_resolves_constants() {
+    use crate::visualization::TextRenderer;
+
+    // (10 + 20) * 3
+    let mut program = Program::new();
+    let ten = program.constants.add_int(10);
+    let twenty = program.constants.add_int(20);
+    let three = program.constants.add_int(3);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[twenty]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[three]));
+    program.add_node(Node::new(OpCode::Mul, 5).with_args(&[3, 4]));
+    program.set_entry_point(5);
+
+    let disassembly = TextRenderer::new(program).render_disassembly();
+    let lines: Vec<&str> = disassembly.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "   #1 ConstInt args=[0] ; 10");
+    assert_eq!(lines[2], "   #3 Add args=[#1 #2] ; 10, 20");
+    assert_eq!(lines[4], "-> #5 Mul args=[#3 #4] ; -> produced by #3, 3");
+}
+
+#[test]
+fn test_special_characters_escaped_in_graph_labels() {
+    use crate::visualization::{GraphRenderer, escape_mermaid_label};
+
+    let mut program = Program::new();
+    let idx = program.constants.add_string("a\"b\nc[d]".to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[idx]));
+    let renderer = GraphRenderer::new(program);
+
+    let dot = renderer.render_to_dot();
+    assert!(dot.contains(r#"label="Node 1\nConstString\nValue: \"a\"b\nc\[d\]\"""#), "{}", dot);
+
+    let mermaid = renderer.render_to_mermaid();
+    assert!(mermaid.contains(
+        r#"n1["Node 1<br/>ConstString<br/>Value: #quot;a#quot;b<br/>c#91;d#93;#quot;"]"#
+    ), "{}", mermaid);
+    // Every label stays on its own line with balanced quotes
+    for line in mermaid.lines().filter(|l| l.contains("[\"")) {
+        assert_eq!(line.matches('"').count(), 2, "{}", line);
+    }
+
+    assert_eq!(escape_mermaid_label("x#1\\y"), "x#35;1#92;y");
+}
+
+#[test]
+fn test_layout_levels_for_diamond_and_cycle() {
+    use crate::visualization::GraphRenderer;
+
+    // 1 feeds 2 and 3, which both feed the join 4; 5 lengthens one side
+    let mut program = Program::new();
+    let c = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c]));
+    program.add_node(Node::new(OpCode::Not, 5).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Add, 2).with_args(&[1, 1]));
+    program.add_node(Node::new(OpCode::Mul, 3).with_args(&[5, 1]));
+    program.add_node(Node::new(OpCode::Add, 4).with_args(&[2, 3]));
+    program.set_entry_point(4);
+
+    let layout = GraphRenderer::new(program).calculate_layout();
+    let y = |id: u32| layout.nodes.iter().find(|n| n.id == id).unwrap().y;
+    let level_height = y(2) - y(4);
+    assert!(level_height > 0.0);
+    assert_eq!(y(3), y(2));
+    assert_eq!(y(5), y(3) + level_height);
+    // The shared source sits below the longest path through 5
+    assert_eq!(y(1), y(5) + level_height);
+
+    let mut cyclic = Program::new();
+    cyclic.add_node(Node::new(OpCode::Add, 1).with_args(&[2, 2]));
+    cyclic.add_node(Node::new(OpCode::Not, 2).with_args(&[1]));
+    cyclic.set_entry_point(1);
+    let layout = GraphRenderer::new(cyclic).calculate_layout();
+    assert_eq!(layout.nodes.len(), 2);
+}
+
+#[test]
+fn test_svg_rendering() {
+    use crate::visualization::GraphRenderer;
+
+    let mut program = Program::new();
+    let a = program.constants.add_int(10);
+    let b = program.constants.add_string("<b & c>".to_string());
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::StrConcat, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::StrLen, 4).with_args(&[3]));
+    program.set_entry_point(4);
+
+    let svg = GraphRenderer::new(program).render_to_svg();
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains("viewBox=\"0 0 800 "));
+    assert_eq!(svg.matches("<rect ").count(), 4);
+    assert_eq!(svg.matches("<line ").count(), 3);
+    assert!(svg.contains("marker-end=\"url(#arrow)\""));
+    assert!(svg.contains("fill=\"#e0f2f1\""));
+    assert!(svg.contains("&quot;&lt;b &amp; c&gt;&quot;"));
+}
+
+#[test]
+fn test_json_round_trip_matches_binary() {
+    let mut program = Program::new();
+    let label = program.constants.add_string("area = \"".to_string());
+    let width = program.constants.add_float(2.5);
+    let height = program.constants.add_int(4);
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[label]));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[width]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[height]));
+    program.add_node(Node::new(OpCode::Mul, 4).with_args(&[2, 3]));
+    program.add_node(Node::new(OpCode::StrConcat, 5).with_args(&[1, 4]));
+    program.set_entry_point(5);
+    program.require_capability(Capability::IO);
+    program.metadata.traits.push(Trait {
+        name: "IsPure".to_string(),
+        preconditions: vec!["true".to_string()],
+        postconditions: vec![],
+    });
+
+    // Load a .der file, then go through JSON
+    let temp_file = NamedTempFile::new().unwrap();
+    DERSerializer::new(File::create(temp_file.path()).unwrap()).write_program(&program).unwrap();
+    let loaded = DERDeserializer::new(File::open(temp_file.path()).unwrap()).read_program().unwrap();
+
+    let json = loaded.to_json();
+    assert!(json.contains("\"name\": \"StrConcat\""));
+    let from_json = Program::from_json(&json).unwrap();
+
+    assert_eq!(from_json.to_bytes().unwrap(), loaded.to_bytes().unwrap());
+    assert_eq!(from_json.to_json(), json);
+
+    let expected = Executor::new(loaded).execute().unwrap();
+    assert_eq!(expected, Value::String("area = \"10".to_string()));
+    assert_eq!(Executor::new(from_json).execute().unwrap(), expected);
+}
+
+#[test]
+fn test_json_rejects_too_many_args() {
+    let mut program = Program::new();
+    program.add_node(Node::new(OpCode::Nop, 1));
+    let json = program.to_json().replace("\"args\": []", "\"args\": [1, 2, 3, 4]");
+
+    assert!(Program::from_json(&json).is_err());
+    assert!(Program::from_json("{").is_err());
+}
+
+#[test]
+fn test_type_checker_mod_promotes_to_float() {
+    use crate::types::{TypeChecker, Type};
+
+    let mut program = Program::new();
+    let a = program.constants.add_float(10.5);
+    let b = program.constants.add_int(3);
+    program.add_node(Node::new(OpCode::ConstFloat, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::Mod, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Mod, 4).with_args(&[2, 2]));
+    program.set_entry_point(3);
+
+    let mut checker = TypeChecker::new();
+    checker.check_program(&program).unwrap();
+    assert_eq!(checker.node_type(3), Some(&Type::Float));
+    assert_eq!(checker.node_type(4), Some(&Type::Int));
+}
+
+fn create_file_copy_program(source: &str, dest: &str) -> Program {
+    let mut program = Program::new();
+    let source_idx = program.constants.add_string(source.to_string());
+    let dest_idx = program.constants.add_string(dest.to_string());
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[source_idx]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[dest_idx]));
+    program.add_node(Node::new(OpCode::FileRead, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::FileWrite, 4).with_args(&[2, 3]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[3, 4]));
+    program.set_entry_point(5);
+    program
+}
+
+#[test]
+fn test_file_read_and_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("in.txt");
+    let dest = dir.path().join("out.txt");
+    std::fs::write(&source, "hello der").unwrap();
+
+    let program = create_file_copy_program(source.to_str().unwrap(), dest.to_str().unwrap());
+    let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::FileSystem);
+    let result = executor.execute().unwrap();
+
+    assert_eq!(result, Value::Array(vec![Value::String("hello der".to_string()), Value::Nil]));
+    assert_eq!(std::fs::read_to_string(&dest).unwrap(), "hello der");
+}
+
+#[test]
+fn test_file_read_missing_names_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing.txt");
+    let missing = missing.to_str().unwrap();
+    let dest = dir.path().join("out.txt");
+
+    let mut executor = Executor::new(create_file_copy_program(missing, dest.to_str().unwrap()));
+    executor.grant_capability(Capability::FileSystem);
+
+    match executor.execute() {
+        Err(RuntimeError::IOError(msg)) => assert!(msg.contains(missing), "{}", msg),
+        other => panic!("Expected IOError, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_file_access_requires_capability() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("in.txt");
+    std::fs::write(&source, "secret").unwrap();
+    let dest = dir.path().join("out.txt");
+
+    let mut executor = Executor::new(create_file_copy_program(source.to_str().unwrap(), dest.to_str().unwrap()));
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::FileSystem, .. })
+    ));
+    assert!(!dest.exists());
+}
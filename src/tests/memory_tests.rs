@@ -265,4 +265,175 @@ fn test_memory_type_operations() {
         }
         _ => panic!("Expected TypeMismatch error"),
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_load_reread_after_store() {
+    let mut program = Program::new();
+
+    let size_idx = program.constants.add_int(8);
+    let val1_idx = program.constants.add_int(10);
+    let val2_idx = program.constants.add_int(20);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[size_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[val1_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[val2_idx]));
+    program.add_node(Node::new(OpCode::Alloc, 4).with_args(&[1, 2]));
+    let first_load = Node::new(OpCode::Load, 5).with_args(&[4]);
+    let store = Node::new(OpCode::Store, 6).with_args(&[4, 3]);
+    let second_load = Node::new(OpCode::Load, 7).with_args(&[4]);
+    program.add_node(first_load);
+    program.add_node(store);
+    program.add_node(second_load);
+
+    // Load, Store, Load in argument order; then node 5 is used again
+    // after the store and has to see the new value
+    program.add_node(Node::new(OpCode::CreateArray, 8).with_args(&[5, 6, 7]));
+    program.add_node(Node::new(OpCode::CreateArray, 9).with_args(&[8, 5]));
+    program.set_entry_point(9);
+
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+
+    assert_eq!(result, Value::Array(vec![
+        Value::Array(vec![Value::Int(10), Value::Int(20), Value::Int(20)]),
+        Value::Int(20),
+    ]));
+}
+
+fn create_buffer_program() -> Program {
+    // An 8-slot buffer with values stored at offsets 0 and 3
+    let mut program = Program::new();
+    let size = program.constants.add_int(8);
+    let three = program.constants.add_int(3);
+    let eight = program.constants.add_int(8);
+    let first = program.constants.add_int(10);
+    let fourth = program.constants.add_int(13);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[size]));
+    program.add_node(Node::new(OpCode::Alloc, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[three]));
+    program.add_node(Node::new(OpCode::PtrAdd, 4).with_args(&[2, 3]));
+    program.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[first]));
+    program.add_node(Node::new(OpCode::ConstInt, 6).with_args(&[fourth]));
+    program.add_node(Node::new(OpCode::Store, 7).with_args(&[2, 5]));
+    program.add_node(Node::new(OpCode::Store, 8).with_args(&[4, 6]));
+    program.add_node(Node::new(OpCode::Load, 9).with_args(&[2]));
+    program.add_node(Node::new(OpCode::Load, 10).with_args(&[4]));
+    program.add_node(Node::new(OpCode::CreateArray, 11).with_args(&[9, 10]));
+    // Stores run before the loads because arguments evaluate in order
+    program.add_node(Node::new(OpCode::CreateArray, 12).with_args(&[7, 8, 11]));
+
+    program.add_node(Node::new(OpCode::ConstInt, 13).with_args(&[eight]));
+    program.add_node(Node::new(OpCode::PtrAdd, 14).with_args(&[2, 13]));
+    program.add_node(Node::new(OpCode::Load, 15).with_args(&[14]));
+    program
+}
+
+#[test]
+fn test_memory_offsets_are_independent() {
+    let mut program = create_buffer_program();
+    program.set_entry_point(12);
+
+    let result = Executor::new(program).execute().unwrap();
+    assert_eq!(result, Value::Array(vec![
+        Value::Int(10),
+        Value::Int(13),
+        Value::Array(vec![Value::Int(10), Value::Int(13)]),
+    ]));
+}
+
+#[test]
+fn test_memory_offset_out_of_bounds() {
+    let mut program = create_buffer_program();
+    program.set_entry_point(15);
+
+    match Executor::new(program).execute() {
+        Err(RuntimeError::ArrayIndexOutOfBounds { index, length }) => {
+            assert_eq!((index, length), (8, 8));
+        }
+        other => panic!("Expected ArrayIndexOutOfBounds, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_executor_collect_garbage() {
+    let mut program = Program::new();
+    let size = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[size]));
+    program.add_node(Node::new(OpCode::Alloc, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Alloc, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Free, 4).with_args(&[3]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[2, 4]));
+    program.set_entry_point(5);
+
+    let mut executor = Executor::new(program);
+    executor.execute().unwrap();
+
+    // The freed block goes; the live one is still held by node 2
+    assert_eq!(executor.collect_garbage(), 1);
+    assert_eq!(executor.collect_garbage(), 0);
+}
+
+#[test]
+fn test_executor_memory_limit() {
+    let mut program = Program::new();
+    let size_idx = program.constants.add_int(40);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[size_idx]));
+    program.add_node(Node::new(OpCode::Alloc, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Alloc, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Alloc, 4).with_args(&[1]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[2, 3, 4]));
+    program.set_entry_point(5);
+
+    let mut executor = Executor::new(program);
+    executor.set_memory_limit(100);
+    let result = executor.execute();
+
+    assert!(matches!(
+        result,
+        Err(RuntimeError::MemoryLimitExceeded { requested: 40, limit: 100 })
+    ));
+    let stats = executor.memory_stats();
+    assert_eq!(stats.total_allocated, 80);
+    assert_eq!(stats.active_objects, 2);
+}
+
+/// Stores 1, then stores that value plus 10, then reads the slot. With
+/// `ordered` each Load names the Store it must follow; without it both
+/// Stores dangle because no node on the entry path reads them.
+fn create_two_store_program(ordered: bool) -> Program {
+    let mut program = Program::new();
+    let size = program.constants.add_int(8);
+    let zero = program.constants.add_int(0);
+    let one = program.constants.add_int(1);
+    let ten = program.constants.add_int(10);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[size]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[zero]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[one]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::Alloc, 5).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Store, 6).with_args(&[5, 3]));
+    let first_load = if ordered { vec![5, 6] } else { vec![5] };
+    program.add_node(Node::new(OpCode::Load, 7).with_args(&first_load));
+    program.add_node(Node::new(OpCode::Add, 8).with_args(&[7, 4]));
+    program.add_node(Node::new(OpCode::Store, 9).with_args(&[5, 8]));
+    let final_load = if ordered { vec![5, 9] } else { vec![5] };
+    program.add_node(Node::new(OpCode::Load, 10).with_args(&final_load));
+    program.set_entry_point(10);
+    program
+}
+
+#[test]
+fn test_dangling_stores_never_run() {
+    let result = Executor::new(create_two_store_program(false)).execute().unwrap();
+    assert_eq!(result, Value::Int(0));
+}
+
+#[test]
+fn test_load_runs_its_after_argument_first() {
+    let program = create_two_store_program(true);
+    assert!(crate::verification::Verifier::new(program.clone()).verify_program().is_valid);
+
+    let result = Executor::new(program).execute().unwrap();
+    assert_eq!(result, Value::Int(11));
+}
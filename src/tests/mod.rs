@@ -14,4 +14,10 @@ mod verification_tests;
 mod memory_tests;
 
 #[cfg(test)]
-mod async_tests;
\ No newline at end of file
+mod async_tests;
+
+#[cfg(test)]
+mod optimize_tests;
+
+#[cfg(test)]
+mod builder_tests;
\ No newline at end of file
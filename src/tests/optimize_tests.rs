@@ -0,0 +1,131 @@
+use crate::core::*;
+use crate::runtime::*;
+use crate::optimize::*;
+
+fn create_arithmetic_program() -> Program {
+    let mut program = Program::new();
+    
+    // (10 + 20) * (5 - 3)
+    let c10 = program.constants.add_int(10);
+    let c20 = program.constants.add_int(20);
+    let c5 = program.constants.add_int(5);
+    let c3 = program.constants.add_int(3);
+    
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c10]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c20]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[c5]));
+    program.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[c3]));
+    program.add_node(Node::new(OpCode::Sub, 6).with_args(&[4, 5]));
+    program.add_node(Node::new(OpCode::Mul, 7).with_args(&[3, 6]));
+    program.set_entry_point(7);
+    program
+}
+
+#[test]
+fn test_constant_fold_arithmetic() {
+    let original = create_arithmetic_program();
+    let mut program = original.clone();
+    
+    assert_eq!(constant_fold(&mut program), 3);
+    
+    assert_eq!(program.nodes.len(), 1);
+    assert_eq!(program.nodes[0].result_id, 7);
+    assert_eq!(program.nodes[0].opcode, OpCode::ConstInt as u16);
+    
+    let expected = Executor::new(original).execute().unwrap();
+    let folded = Executor::new(program).execute().unwrap();
+    assert_eq!(expected, Value::Int(60));
+    assert_eq!(folded, expected);
+}
+
+#[test]
+fn test_constant_fold_keeps_impure_consumers() {
+    let mut program = create_arithmetic_program();
+    program.add_node(Node::new(OpCode::Print, 8).with_args(&[3]));
+    program.add_node(Node::new(OpCode::CreateArray, 9).with_args(&[8, 7]));
+    program.set_entry_point(9);
+    
+    constant_fold(&mut program);
+    
+    // The Print still reads node 3, now a constant
+    let ids: Vec<u32> = program.nodes.iter().map(|n| n.result_id).collect();
+    assert_eq!(ids, vec![3, 7, 8, 9]);
+    
+    let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    assert_eq!(executor.execute().unwrap(), Value::Array(vec![Value::Nil, Value::Int(60)]));
+    assert_eq!(executor.take_output(), "30\n");
+}
+
+#[test]
+fn test_constant_fold_leaves_runtime_errors() {
+    let mut program = Program::new();
+    let c1 = program.constants.add_int(1);
+    let c0 = program.constants.add_int(0);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c1]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c0]));
+    program.add_node(Node::new(OpCode::Div, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+    
+    assert_eq!(constant_fold(&mut program), 0);
+    assert_eq!(program.nodes.len(), 3);
+    assert!(matches!(Executor::new(program).execute(), Err(RuntimeError::DivisionByZero)));
+}
+
+#[test]
+fn test_eliminate_dead_nodes() {
+    let mut program = create_arithmetic_program();
+    let unused = program.constants.add_int(99);
+    program.add_node(Node::new(OpCode::ConstInt, 8).with_args(&[unused]));
+    program.add_node(Node::new(OpCode::Add, 9).with_args(&[8, 1]));
+    
+    let expected = Executor::new(program.clone()).execute().unwrap();
+    assert_eq!(eliminate_dead_nodes(&mut program), 2);
+    
+    let ids: Vec<u32> = program.nodes.iter().map(|n| n.result_id).collect();
+    assert_eq!(ids, vec![1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(Executor::new(program).execute().unwrap(), expected);
+}
+
+#[test]
+fn test_eliminate_dead_nodes_keeps_async_completions() {
+    let mut program = Program::new();
+    let value_idx = program.constants.add_int(5);
+    program.add_node(Node::new(OpCode::AsyncBegin, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::AsyncComplete, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::AsyncAwait, 4).with_args(&[1]));
+    program.set_entry_point(4);
+    
+    assert_eq!(eliminate_dead_nodes(&mut program), 0);
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(5));
+}
+
+#[test]
+fn test_eliminate_common_subexpressions() {
+    let mut program = Program::new();
+    let c10 = program.constants.add_int(10);
+    let c20 = program.constants.add_int(20);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c10]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c20]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Add, 4).with_args(&[2, 1]));
+    program.add_node(Node::new(OpCode::Sub, 5).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::Sub, 6).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Sub, 7).with_args(&[2, 1]));
+    program.add_node(Node::new(OpCode::Mul, 8).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::CreateArray, 9).with_args(&[8, 6, 7]));
+    program.set_entry_point(9);
+    
+    let expected = Executor::new(program.clone()).execute().unwrap();
+    assert_eq!(eliminate_common_subexpressions(&mut program), 1);
+    
+    // The swapped Add collapses; Subs with swapped operands stay distinct
+    let ids: Vec<u32> = program.nodes.iter().map(|n| n.result_id).collect();
+    assert_eq!(ids, vec![1, 2, 3, 5, 6, 7, 8, 9]);
+    let mul = program.nodes.iter().find(|n| n.result_id == 8).unwrap();
+    assert_eq!(mul.args[..2], [3, 3]);
+    assert_eq!(Executor::new(program).execute().unwrap(), expected);
+}
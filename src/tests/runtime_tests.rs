@@ -372,4 +372,2298 @@ fn test_complex_expression() {
         Value::Int(60) => {},
         _ => panic!("Expected Int(60), got {:?}", result),
     }
-}
\ No newline at end of file
+}
+fn create_sum_loop_program(limit: i64) -> Program {
+    let mut program = create_test_program();
+
+    // i = 0; sum = 0; while i < limit { i = i + 1; sum = sum + i }
+    let zero = program.constants.add_int(0);
+    let one = program.constants.add_int(1);
+    let limit = program.constants.add_int(limit);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[zero]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[one]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[limit]));
+    program.add_node(Node::new(OpCode::Alloc, 4).with_args(&[2, 1])); // i
+    program.add_node(Node::new(OpCode::Alloc, 5).with_args(&[2, 1])); // sum
+
+    // Condition: i < limit
+    program.add_node(Node::new(OpCode::Load, 6).with_args(&[4]));
+    program.add_node(Node::new(OpCode::Lt, 7).with_args(&[6, 3]));
+
+    // Body: i = i + 1; sum = sum + i
+    program.add_node(Node::new(OpCode::Load, 8).with_args(&[4]));
+    program.add_node(Node::new(OpCode::Add, 9).with_args(&[8, 2]));
+    program.add_node(Node::new(OpCode::Store, 10).with_args(&[4, 9]));
+    program.add_node(Node::new(OpCode::Load, 11).with_args(&[5]));
+    program.add_node(Node::new(OpCode::Add, 12).with_args(&[11, 10]));
+    program.add_node(Node::new(OpCode::Store, 13).with_args(&[5, 12]));
+
+    program.add_node(Node::new(OpCode::Loop, 14).with_args(&[7, 13]));
+    program.set_entry_point(14);
+    program
+}
+
+#[test]
+fn test_loop_sum() {
+    let program = create_sum_loop_program(10);
+
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+
+    match result {
+        Value::Int(55) => {},
+        _ => panic!("Expected Int(55), got {:?}", result),
+    }
+}
+
+#[test]
+fn test_loop_within_bound() {
+    let mut program = create_sum_loop_program(10);
+    let bound = program.constants.add_int(10);
+    program.add_node(Node::new(OpCode::ConstInt, 15).with_args(&[bound]));
+    let loop_node = program.nodes.iter_mut().find(|n| n.result_id == 14).unwrap();
+    *loop_node = Node::new(OpCode::Loop, 14).with_args(&[7, 13, 15]);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Int(55));
+}
+
+#[test]
+fn test_loop_bound_exceeded() {
+    let mut program = create_sum_loop_program(1_000_000);
+    let bound = program.constants.add_int(100);
+    program.add_node(Node::new(OpCode::ConstInt, 15).with_args(&[bound]));
+    let loop_node = program.nodes.iter_mut().find(|n| n.result_id == 14).unwrap();
+    *loop_node = Node::new(OpCode::Loop, 14).with_args(&[7, 13, 15]);
+
+    let mut executor = Executor::new(program);
+    match executor.execute() {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("bound of 100")),
+        other => panic!("Expected bound error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_loop_zero_iterations() {
+    let program = create_sum_loop_program(0);
+
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+
+    assert_eq!(result, Value::Nil);
+}
+
+fn execute_int_binary(opcode: OpCode, a: i64, b: i64) -> Result<Value> {
+    let mut program = create_test_program();
+    let a_idx = program.constants.add_int(a);
+    let b_idx = program.constants.add_int(b);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[b_idx]));
+    program.add_node(Node::new(opcode, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_large_integer_arithmetic_is_exact() {
+    // 2^53 + 1 is not representable as f64
+    let result = execute_int_binary(OpCode::Add, 9_007_199_254_740_993, 1).unwrap();
+    assert_eq!(result, Value::Int(9_007_199_254_740_994));
+
+    let result = execute_int_binary(OpCode::Sub, 9_007_199_254_740_993, 0).unwrap();
+    assert_eq!(result, Value::Int(9_007_199_254_740_993));
+
+    let result = execute_int_binary(OpCode::Mul, 3_037_000_499, 3_037_000_499).unwrap();
+    assert_eq!(result, Value::Int(9_223_372_030_926_249_001));
+}
+
+#[test]
+fn test_bitwise_operations() {
+    assert_eq!(execute_int_binary(OpCode::BitAnd, 0b1100, 0b1010).unwrap(), Value::Int(0b1000));
+    assert_eq!(execute_int_binary(OpCode::BitOr, 0b1100, 0b1010).unwrap(), Value::Int(0b1110));
+    assert_eq!(execute_int_binary(OpCode::BitXor, 0b1100, 0b1010).unwrap(), Value::Int(0b0110));
+    assert_eq!(execute_int_binary(OpCode::Shl, 1, 10).unwrap(), Value::Int(1024));
+    assert_eq!(execute_int_binary(OpCode::Shr, -16, 2).unwrap(), Value::Int(-4));
+    assert_eq!(execute_int_binary(OpCode::BitAnd, 6, 3).unwrap(), Value::Int(2));
+    assert_eq!(execute_int_binary(OpCode::Shl, 1, 4).unwrap(), Value::Int(16));
+
+    let mut program = create_test_program();
+    let idx = program.constants.add_int(0);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[idx]));
+    program.add_node(Node::new(OpCode::BitNot, 2).with_args(&[1]));
+    program.set_entry_point(2);
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(-1));
+}
+
+#[test]
+fn test_shift_count_out_of_range() {
+    assert_eq!(execute_int_binary(OpCode::Shl, 1, 63).unwrap(), Value::Int(i64::MIN));
+    for count in [64, -1] {
+        match execute_int_binary(OpCode::Shl, 1, count) {
+            Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("Shift count")),
+            other => panic!("Expected shift count error, got {:?}", other),
+        }
+    }
+    assert!(execute_int_binary(OpCode::Shr, 1, 64).is_err());
+    assert!(execute_int_binary(OpCode::Shr, 16, -2).is_err());
+}
+
+#[test]
+fn test_bitwise_type_mismatch() {
+    let mut program = create_test_program();
+    let a = program.constants.add_int(1);
+    let b = program.constants.add_float(1.0);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::BitOr, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    assert!(matches!(
+        Executor::new(program).execute(),
+        Err(RuntimeError::TypeMismatch { .. })
+    ));
+}
+
+fn execute_unary(opcode: OpCode, value: Value) -> Result<Value> {
+    let mut program = create_test_program();
+    let value_node = match value {
+        Value::Int(i) => Node::new(OpCode::ConstInt, 1).with_args(&[program.constants.add_int(i)]),
+        Value::Float(f) => Node::new(OpCode::ConstFloat, 1).with_args(&[program.constants.add_float(f)]),
+        Value::String(s) => Node::new(OpCode::ConstString, 1).with_args(&[program.constants.add_string(s)]),
+        other => panic!("unsupported test value {:?}", other),
+    };
+    program.add_node(value_node);
+    program.add_node(Node::new(opcode, 2).with_args(&[1]));
+    program.set_entry_point(2);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_unary_math_functions() {
+    assert_eq!(execute_unary(OpCode::Sqrt, Value::Int(16)).unwrap(), Value::Float(4.0));
+    assert_eq!(execute_unary(OpCode::Sqrt, Value::Float(2.25)).unwrap(), Value::Float(1.5));
+    assert_eq!(execute_unary(OpCode::Abs, Value::Int(-7)).unwrap(), Value::Int(7));
+    assert_eq!(execute_unary(OpCode::Abs, Value::Float(-0.5)).unwrap(), Value::Float(0.5));
+    assert_eq!(execute_unary(OpCode::Floor, Value::Float(-1.5)).unwrap(), Value::Float(-2.0));
+    assert_eq!(execute_unary(OpCode::Ceil, Value::Float(1.2)).unwrap(), Value::Float(2.0));
+    assert_eq!(execute_unary(OpCode::Floor, Value::Int(3)).unwrap(), Value::Int(3));
+}
+
+#[test]
+fn test_maps_equal_regardless_of_insertion_order() {
+    let mut builder = ProgramBuilder::new();
+    let (x, y) = (builder.const_string("x"), builder.const_string("y"));
+    let (one, two) = (builder.const_int(1), builder.const_int(2));
+    let empty = builder.create_map();
+    let with_x = builder.map_set(empty, x, one);
+    let first = builder.map_set(with_x, y, two);
+    let with_y = builder.map_set(empty, y, two);
+    let second = builder.map_set(with_y, x, one);
+    let nested_first = builder.create_array(&[first, one]);
+    let nested_second = builder.create_array(&[second, one]);
+    let maps_equal = builder.eq(first, second);
+    let nested_equal = builder.eq(nested_first, nested_second);
+    let partial_differs = builder.node(OpCode::Ne, &[with_x, first]);
+    let all = builder.create_array(&[maps_equal, nested_equal, partial_differs]);
+    let program = builder.build(all);
+
+    assert_eq!(
+        Executor::new(program).execute().unwrap(),
+        Value::Array(vec![Value::Bool(true); 3])
+    );
+}
+
+#[test]
+fn test_arrays_containing_nan_are_never_equal() {
+    let mut builder = ProgramBuilder::new();
+    let nan = builder.const_float(f64::NAN);
+    let one = builder.const_int(1);
+    let first = builder.create_array(&[one, nan]);
+    let second = builder.create_array(&[one, nan]);
+    let equal = builder.eq(first, second);
+    let same_node = builder.eq(first, first);
+    let differs = builder.node(OpCode::Ne, &[first, second]);
+    let all = builder.create_array(&[equal, same_node, differs]);
+    let program = builder.build(all);
+
+    assert_eq!(
+        Executor::new(program).execute().unwrap(),
+        Value::Array(vec![Value::Bool(false), Value::Bool(false), Value::Bool(true)])
+    );
+    assert_ne!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+    assert_ne!(Value::Array(vec![Value::Int(1)]), Value::Array(vec![Value::Int(1), Value::Int(1)]));
+}
+
+#[test]
+fn test_neg_preserves_type() {
+    assert_eq!(execute_unary(OpCode::Neg, Value::Int(5)).unwrap(), Value::Int(-5));
+    assert_eq!(execute_unary(OpCode::Neg, Value::Int(i64::MAX)).unwrap(), Value::Int(-i64::MAX));
+    assert_eq!(execute_unary(OpCode::Neg, Value::Float(-2.5)).unwrap(), Value::Float(2.5));
+}
+
+#[test]
+fn test_neg_of_min_int_overflows() {
+    assert!(matches!(
+        execute_unary(OpCode::Neg, Value::Int(i64::MIN)),
+        Err(RuntimeError::IntegerOverflow(_))
+    ));
+}
+
+#[test]
+fn test_neg_of_string_is_type_mismatch() {
+    assert!(matches!(
+        execute_unary(OpCode::Neg, Value::String("5".to_string())),
+        Err(RuntimeError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_sqrt_of_negative_is_an_error() {
+    match execute_unary(OpCode::Sqrt, Value::Int(-4)) {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("negative")),
+        other => panic!("Expected negative sqrt error, got {:?}", other),
+    }
+    assert!(matches!(
+        execute_unary(OpCode::Abs, Value::Int(i64::MIN)),
+        Err(RuntimeError::IntegerOverflow(_))
+    ));
+}
+
+#[test]
+fn test_pow_min_max() {
+    assert_eq!(execute_int_binary(OpCode::Pow, 3, 4).unwrap(), Value::Int(81));
+    assert_eq!(execute_int_binary(OpCode::Pow, 2, -2).unwrap(), Value::Float(0.25));
+    assert_eq!(execute_int_binary(OpCode::Min, 3, -4).unwrap(), Value::Int(-4));
+    assert_eq!(execute_int_binary(OpCode::Max, 3, -4).unwrap(), Value::Int(3));
+
+    // Mixed operands promote to float like the basic arithmetic ops
+    let mut program = create_test_program();
+    let a = program.constants.add_int(2);
+    let b = program.constants.add_float(0.5);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::Max, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Pow, 4).with_args(&[1, 2]));
+    program.set_entry_point(3);
+    assert_eq!(Executor::new(program.clone()).execute().unwrap(), Value::Float(2.0));
+    program.set_entry_point(4);
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Float(2f64.sqrt()));
+}
+
+#[test]
+fn test_pow_overflow_is_an_error() {
+    match execute_int_binary(OpCode::Pow, 2, 63) {
+        Err(RuntimeError::IntegerOverflow(msg)) => assert_eq!(msg, "2 ** 63"),
+        other => panic!("Expected IntegerOverflow, got {:?}", other),
+    }
+    assert_eq!(execute_int_binary(OpCode::Pow, 2, 62).unwrap(), Value::Int(1 << 62));
+}
+
+#[test]
+fn test_extract_byte_with_shift_and_mask() {
+    // (0x12345678 >> 16) & 0xFF == 0x34
+    let mut program = create_test_program();
+    let word = program.constants.add_int(0x1234_5678);
+    let shift = program.constants.add_int(16);
+    let mask = program.constants.add_int(0xFF);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[word]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[shift]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[mask]));
+    program.add_node(Node::new(OpCode::Shr, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::BitAnd, 5).with_args(&[4, 3]));
+    program.set_entry_point(5);
+
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(0x34));
+}
+
+#[test]
+fn test_integer_overflow() {
+    match execute_int_binary(OpCode::Add, i64::MAX, 1) {
+        Err(RuntimeError::IntegerOverflow(_)) => {},
+        other => panic!("Expected IntegerOverflow, got {:?}", other),
+    }
+    match execute_int_binary(OpCode::Sub, i64::MIN, 1) {
+        Err(RuntimeError::IntegerOverflow(_)) => {},
+        other => panic!("Expected IntegerOverflow, got {:?}", other),
+    }
+    match execute_int_binary(OpCode::Mul, i64::MAX, 2) {
+        Err(RuntimeError::IntegerOverflow(_)) => {},
+        other => panic!("Expected IntegerOverflow, got {:?}", other),
+    }
+    match execute_int_binary(OpCode::Div, i64::MIN, -1) {
+        Err(RuntimeError::IntegerOverflow(_)) => {},
+        other => panic!("Expected IntegerOverflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_integer_arithmetic_at_boundaries() {
+    assert_eq!(execute_int_binary(OpCode::Add, i64::MAX - 1, 1).unwrap(), Value::Int(i64::MAX));
+    assert_eq!(execute_int_binary(OpCode::Add, i64::MAX, i64::MIN).unwrap(), Value::Int(-1));
+    assert_eq!(execute_int_binary(OpCode::Sub, i64::MIN + 1, 1).unwrap(), Value::Int(i64::MIN));
+    assert_eq!(execute_int_binary(OpCode::Mul, i64::MAX, -1).unwrap(), Value::Int(-i64::MAX));
+
+    // Around 2^53, where f64 stops representing every integer
+    let two_53 = 1i64 << 53;
+    assert_eq!(execute_int_binary(OpCode::Add, two_53 - 1, 2).unwrap(), Value::Int(two_53 + 1));
+    assert_eq!(execute_int_binary(OpCode::Sub, two_53 + 1, 1).unwrap(), Value::Int(two_53));
+    assert_eq!(execute_int_binary(OpCode::Mul, two_53 + 1, 3).unwrap(), Value::Int(3 * two_53 + 3));
+
+    // A float operand still falls back to f64
+    let mut program = create_test_program();
+    let a = program.constants.add_int(two_53);
+    let b = program.constants.add_float(1.0);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Float(two_53 as f64));
+}
+
+fn execute_numeric_binary(opcode: OpCode, a: Value, b: Value) -> Result<Value> {
+    let mut program = create_test_program();
+    for (id, value) in [(1, a), (2, b)] {
+        let node = match value {
+            Value::Int(i) => Node::new(OpCode::ConstInt, id).with_args(&[program.constants.add_int(i)]),
+            Value::Float(f) => Node::new(OpCode::ConstFloat, id).with_args(&[program.constants.add_float(f)]),
+            other => panic!("unsupported test value {:?}", other),
+        };
+        program.add_node(node);
+    }
+    program.add_node(Node::new(opcode, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_float_modulo() {
+    let rem = |a, b| execute_numeric_binary(OpCode::Mod, a, b);
+    assert_eq!(rem(Value::Float(10.5), Value::Int(3)).unwrap(), Value::Float(1.5));
+    assert_eq!(rem(Value::Float(10.5), Value::Float(3.0)).unwrap(), Value::Float(1.5));
+    assert_eq!(rem(Value::Float(5.5), Value::Float(2.0)).unwrap(), Value::Float(1.5));
+    assert_eq!(rem(Value::Int(7), Value::Float(2.5)).unwrap(), Value::Float(2.0));
+    assert_eq!(rem(Value::Float(-7.5), Value::Int(2)).unwrap(), Value::Float(-1.5));
+    assert_eq!(rem(Value::Int(-7), Value::Int(3)).unwrap(), Value::Int(-1));
+
+    assert!(matches!(rem(Value::Float(5.0), Value::Float(0.0)), Err(RuntimeError::DivisionByZero)));
+    assert!(matches!(rem(Value::Float(5.0), Value::Int(0)), Err(RuntimeError::DivisionByZero)));
+    assert!(matches!(rem(Value::Int(5), Value::Int(0)), Err(RuntimeError::DivisionByZero)));
+}
+
+#[test]
+fn test_integer_division_results() {
+    assert_eq!(execute_int_binary(OpCode::Div, 10, 2).unwrap(), Value::Int(5));
+    assert_eq!(execute_int_binary(OpCode::Div, 10, 4).unwrap(), Value::Float(2.5));
+}
+
+#[test]
+fn test_deep_arithmetic_chain() {
+    let mut program = create_test_program();
+    let one = program.constants.add_int(1);
+
+    // n1 = 1, n(k) = n(k-1) + n1
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[one]));
+    let depth = 100_000u32;
+    for id in 2..=depth {
+        program.add_node(Node::new(OpCode::Add, id).with_args(&[id - 1, 1]));
+    }
+    program.set_entry_point(depth);
+
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+
+    assert_eq!(result, Value::Int(depth as i64));
+}
+
+fn create_add_chain_program(length: u32) -> Program {
+    let mut program = create_test_program();
+    let one = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[one]));
+    for id in 2..=length {
+        program.add_node(Node::new(OpCode::Add, id).with_args(&[id - 1, 1]));
+    }
+    program.set_entry_point(length);
+    program
+}
+
+#[test]
+fn test_step_limit() {
+    let mut executor = Executor::new(create_add_chain_program(1000));
+    executor.set_step_limit(100);
+    match executor.execute() {
+        Err(RuntimeError::LimitExceeded(message)) => assert_eq!(message, "step limit of 100 reached"),
+        other => panic!("Expected LimitExceeded, got {:?}", other),
+    }
+
+    // Each node is evaluated once, so the chain fits a budget of its length
+    let mut executor = Executor::new(create_add_chain_program(1000));
+    executor.set_step_limit(1000);
+    assert_eq!(executor.execute().unwrap(), Value::Int(1000));
+    assert_eq!(executor.steps(), 1000);
+}
+
+#[test]
+fn test_trace_follows_data_dependencies() {
+    let mut builder = ProgramBuilder::new();
+    let a = builder.const_int(10);
+    let b = builder.const_int(20);
+    let sum = builder.add(a, b);
+    let c = builder.const_int(5);
+    let d = builder.const_int(3);
+    let difference = builder.sub(c, d);
+    let product = builder.mul(sum, difference);
+    let program = builder.build(product);
+
+    let mut executor = Executor::new(program.clone());
+    executor.enable_tracing();
+    assert_eq!(executor.execute().unwrap(), Value::Int(60));
+    let trace = executor.take_trace();
+
+    let order: Vec<u32> = trace.iter().map(|event| event.node_id).collect();
+    assert_eq!(order, vec![1, 2, 3, 4, 5, 6, 7]);
+    for (position, event) in trace.iter().enumerate() {
+        let node = program.nodes.iter().find(|n| n.result_id == event.node_id).unwrap();
+        for dep in node.dependencies() {
+            assert!(order[..position].contains(&dep), "node {} traced before its argument {}", event.node_id, dep);
+        }
+    }
+
+    let last = trace.last().unwrap();
+    assert_eq!(last.opcode, OpCode::Mul);
+    assert_eq!(last.args_resolved, vec![Value::Int(30), Value::Int(2)]);
+    assert_eq!(last.result.as_ref().unwrap(), &Value::Int(60));
+    assert!(executor.take_trace().is_empty());
+}
+
+#[test]
+fn test_trace_records_only_taken_branch_arm() {
+    let mut builder = ProgramBuilder::new();
+    let condition = builder.const_bool(false);
+    let ten = builder.const_int(10);
+    let zero = builder.const_int(0);
+    let failing = builder.div(ten, zero);
+    let branch = builder.branch(condition, failing, zero);
+    let program = builder.build(branch);
+
+    let mut executor = Executor::new(program);
+    executor.enable_tracing();
+    assert_eq!(executor.execute().unwrap(), Value::Int(0));
+
+    let order: Vec<u32> = executor.take_trace().iter().map(|event| event.node_id).collect();
+    assert_eq!(order, vec![condition.result_id(), zero.result_id(), branch.result_id()]);
+}
+
+#[test]
+fn test_trace_records_failures() {
+    let mut builder = ProgramBuilder::new();
+    let ten = builder.const_int(10);
+    let zero = builder.const_int(0);
+    let failing = builder.div(ten, zero);
+    let program = builder.build(failing);
+
+    let mut executor = Executor::new(program);
+    executor.enable_tracing();
+    assert!(executor.execute().is_err());
+
+    let trace = executor.take_trace();
+    let last = trace.last().unwrap();
+    assert_eq!(last.node_id, failing.result_id());
+    assert!(matches!(last.result, Err(RuntimeError::DivisionByZero)));
+}
+
+#[test]
+fn test_profile_counts_opcodes_and_nodes() {
+    // (1 + 2) * 3
+    let mut builder = ProgramBuilder::new();
+    let a = builder.const_int(1);
+    let b = builder.const_int(2);
+    let sum = builder.add(a, b);
+    let c = builder.const_int(3);
+    let product = builder.mul(sum, c);
+    let program = builder.build(product);
+
+    let mut executor = Executor::new(program);
+    assert!(executor.profile_report().is_none());
+    executor.enable_profiling();
+    assert_eq!(executor.execute().unwrap(), Value::Int(9));
+
+    let report = executor.profile_report().unwrap();
+    assert_eq!(report.count(OpCode::ConstInt), 3);
+    assert_eq!(report.count(OpCode::Add), 1);
+    assert_eq!(report.count(OpCode::Mul), 1);
+    assert_eq!(report.count(OpCode::Sub), 0);
+    assert_eq!(report.opcodes.len(), 3);
+    assert_eq!(report.hits(sum.result_id()), 1);
+
+    let text = report.render_text();
+    for name in ["ConstInt", "Add", "Mul", "Hot nodes"] {
+        assert!(text.contains(name), "missing {} in\n{}", name, text);
+    }
+}
+
+#[test]
+fn test_profile_counts_loop_iterations() {
+    let mut executor = Executor::new(create_sum_loop_program(3));
+    executor.enable_profiling();
+    assert_eq!(executor.execute().unwrap(), Value::Int(6));
+
+    let report = executor.profile_report().unwrap();
+    // The condition runs once more than the body
+    assert_eq!(report.hits(7), 4);
+    assert_eq!(report.hits(13), 3);
+    assert_eq!(report.count(OpCode::Loop), 1);
+    assert_eq!(report.node_hits[0].1, 4);
+}
+
+fn create_debug_sample_program() -> (Program, NodeId) {
+    // (10 + 20) * (5 - 3)
+    let mut builder = ProgramBuilder::new();
+    let a = builder.const_int(10);
+    let b = builder.const_int(20);
+    let sum = builder.add(a, b);
+    let c = builder.const_int(5);
+    let d = builder.const_int(3);
+    let difference = builder.sub(c, d);
+    let product = builder.mul(sum, difference);
+    (builder.build(product), sum)
+}
+
+#[test]
+fn test_debugger_breaks_before_add() {
+    let (program, sum) = create_debug_sample_program();
+    let expected = Executor::new(program.clone()).execute().unwrap();
+
+    let mut debugger = DebugExecutor::new(program);
+    debugger.set_breakpoint(sum.result_id());
+
+    match debugger.run_until_break().unwrap() {
+        DebugEvent::Paused(pause) => assert_eq!(pause.node_id, sum.result_id()),
+        other => panic!("Expected a pause, got {:?}", other),
+    }
+    // The Add's inputs are computed, the Add itself is not
+    assert_eq!(debugger.inspect(1), Some(&Value::Int(10)));
+    assert_eq!(debugger.inspect(2), Some(&Value::Int(20)));
+    assert_eq!(debugger.inspect(sum.result_id()), None);
+
+    match debugger.resume().unwrap() {
+        DebugEvent::Finished(value) => assert_eq!(value, expected),
+        other => panic!("Expected the run to finish, got {:?}", other),
+    }
+    assert!(debugger.resume().is_err());
+}
+
+#[test]
+fn test_debugger_pauses_each_loop_iteration() {
+    let mut debugger = DebugExecutor::new(create_sum_loop_program(3));
+    debugger.set_breakpoint(13);
+
+    let mut sums_before_store = Vec::new();
+    loop {
+        match debugger.run_until_break().unwrap() {
+            DebugEvent::Paused(pause) => {
+                assert_eq!(pause.node_id, 13);
+                sums_before_store.push(debugger.inspect(12).cloned().unwrap());
+            }
+            DebugEvent::Finished(value) => {
+                assert_eq!(value, Value::Int(6));
+                break;
+            }
+        }
+    }
+    assert_eq!(sums_before_store, vec![Value::Int(1), Value::Int(3), Value::Int(6)]);
+}
+
+#[test]
+fn test_debugger_without_breakpoints_runs_to_completion() {
+    let (program, _) = create_debug_sample_program();
+    let mut debugger = DebugExecutor::new(program);
+    assert!(debugger.resume().is_err());
+    assert!(matches!(debugger.run_until_break(), Ok(DebugEvent::Finished(Value::Int(60)))));
+    assert!(debugger.run_until_break().is_err());
+}
+
+#[test]
+fn test_timeout_stops_long_loop() {
+    let mut executor = Executor::new(create_sum_loop_program(i64::MAX));
+    executor.set_timeout(std::time::Duration::from_millis(20));
+
+    let started = std::time::Instant::now();
+    assert!(matches!(executor.execute(), Err(RuntimeError::LimitExceeded(_))));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+}
+
+#[test]
+fn test_lazy_arms_not_evaluated() {
+    let mut program = create_test_program();
+    let t = program.constants.add_bool(true);
+    let f = program.constants.add_bool(false);
+    let ten = program.constants.add_int(10);
+    let zero = program.constants.add_int(0);
+
+    program.add_node(Node::new(OpCode::ConstBool, 1).with_args(&[t]));
+    program.add_node(Node::new(OpCode::ConstBool, 2).with_args(&[f]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[zero]));
+    // 10 / 0 fails if it is ever evaluated
+    program.add_node(Node::new(OpCode::Div, 5).with_args(&[3, 4]));
+
+    program.add_node(Node::new(OpCode::Branch, 6).with_args(&[1, 3, 5]));
+    program.add_node(Node::new(OpCode::Branch, 7).with_args(&[2, 5, 3]));
+    program.add_node(Node::new(OpCode::And, 8).with_args(&[2, 5]));
+    program.add_node(Node::new(OpCode::Or, 9).with_args(&[1, 5]));
+    program.add_node(Node::new(OpCode::CreateArray, 10).with_args(&[6, 7, 8]));
+    program.add_node(Node::new(OpCode::CreateArray, 11).with_args(&[10, 9]));
+    program.set_entry_point(11);
+
+    let mut executor = Executor::new(program);
+    let result = executor.execute().unwrap();
+
+    assert_eq!(result, Value::Array(vec![
+        Value::Array(vec![Value::Int(10), Value::Int(10), Value::Bool(false)]),
+        Value::Bool(true),
+    ]));
+}
+
+#[test]
+fn test_cyclic_dependency_is_an_error() {
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::Add, 1).with_args(&[2, 2]));
+    program.add_node(Node::new(OpCode::Not, 2).with_args(&[1]));
+    program.set_entry_point(1);
+
+    let mut executor = Executor::new(program);
+    assert!(matches!(executor.execute(), Err(RuntimeError::InvalidOperation(_))));
+}
+
+fn execute_cast(value: Value, target: &str) -> Result<Value> {
+    let mut program = create_test_program();
+    let value_node = match value {
+        Value::Int(i) => Node::new(OpCode::ConstInt, 1).with_args(&[program.constants.add_int(i)]),
+        Value::Float(f) => Node::new(OpCode::ConstFloat, 1).with_args(&[program.constants.add_float(f)]),
+        Value::String(s) => Node::new(OpCode::ConstString, 1).with_args(&[program.constants.add_string(s)]),
+        Value::Bool(b) => Node::new(OpCode::ConstBool, 1).with_args(&[program.constants.add_bool(b)]),
+        Value::Array(_) => Node::new(OpCode::CreateArray, 1),
+        other => panic!("unsupported test value {:?}", other),
+    };
+    let target_idx = program.constants.add_string(target.to_string());
+
+    program.add_node(value_node);
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[target_idx]));
+    program.add_node(Node::new(OpCode::Cast, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_cast_to_int() {
+    assert_eq!(execute_cast(Value::Float(3.9), "int").unwrap(), Value::Int(3));
+    assert_eq!(execute_cast(Value::Float(-3.9), "int").unwrap(), Value::Int(-3));
+    assert_eq!(execute_cast(Value::String("42".to_string()), "int").unwrap(), Value::Int(42));
+    assert_eq!(execute_cast(Value::Bool(true), "int").unwrap(), Value::Int(1));
+    assert_eq!(execute_cast(Value::Int(7), "int").unwrap(), Value::Int(7));
+
+    match execute_cast(Value::String("forty-two".to_string()), "int") {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("forty-two")),
+        other => panic!("Expected parse failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cast_to_float() {
+    assert_eq!(execute_cast(Value::Int(2), "float").unwrap(), Value::Float(2.0));
+    assert_eq!(execute_cast(Value::String("2.5".to_string()), "float").unwrap(), Value::Float(2.5));
+    assert!(execute_cast(Value::String("abc".to_string()), "float").is_err());
+}
+
+#[test]
+fn test_cast_to_string() {
+    assert_eq!(execute_cast(Value::Int(42), "string").unwrap(), Value::String("42".to_string()));
+    assert_eq!(execute_cast(Value::Float(1.5), "string").unwrap(), Value::String("1.5".to_string()));
+    assert_eq!(execute_cast(Value::Bool(false), "string").unwrap(), Value::String("false".to_string()));
+}
+
+#[test]
+fn test_cast_to_bool() {
+    assert_eq!(execute_cast(Value::Int(0), "bool").unwrap(), Value::Bool(false));
+    assert_eq!(execute_cast(Value::String("x".to_string()), "bool").unwrap(), Value::Bool(true));
+    assert_eq!(execute_cast(Value::Array(vec![]), "bool").unwrap(), Value::Bool(false));
+}
+
+#[test]
+fn test_cast_impossible() {
+    assert!(matches!(
+        execute_cast(Value::Array(vec![]), "int"),
+        Err(RuntimeError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        execute_cast(Value::Int(1), "widget"),
+        Err(RuntimeError::InvalidOperation(_))
+    ));
+}
+
+#[test]
+fn test_cast_round_trips() {
+    let via = |value: Value, through: &str, back: &str| {
+        execute_cast(execute_cast(value, through).unwrap(), back).unwrap()
+    };
+    assert_eq!(via(Value::Int(-12), "float", "int"), Value::Int(-12));
+    assert_eq!(via(Value::Int(-12), "string", "int"), Value::Int(-12));
+    assert_eq!(via(Value::Float(0.25), "string", "float"), Value::Float(0.25));
+    assert_eq!(via(Value::Bool(true), "int", "bool"), Value::Bool(true));
+    assert_eq!(via(Value::Bool(false), "float", "bool"), Value::Bool(false));
+    assert_eq!(via(Value::Bool(true), "string", "bool"), Value::Bool(true));
+}
+
+#[test]
+fn test_cast_by_type_code() {
+    let mut program = create_test_program();
+    let value_idx = program.constants.add_float(2.75);
+    let int_code = program.constants.add_int(0);
+    let string_code = program.constants.add_int(2);
+    let bad_code = program.constants.add_int(9);
+    program.add_node(Node::new(OpCode::ConstFloat, 1).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[int_code]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[string_code]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[bad_code]));
+    program.add_node(Node::new(OpCode::Cast, 5).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Cast, 6).with_args(&[1, 3]));
+    program.add_node(Node::new(OpCode::Cast, 7).with_args(&[1, 4]));
+
+    let run = |entry: u32| {
+        let mut program = program.clone();
+        program.set_entry_point(entry);
+        Executor::new(program).execute()
+    };
+    assert_eq!(run(5).unwrap(), Value::Int(2));
+    assert_eq!(run(6).unwrap(), Value::String("2.75".to_string()));
+    match run(7) {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("9")),
+        other => panic!("Expected unknown cast target, got {:?}", other),
+    }
+}
+
+fn create_shared_print_program(volatile: bool) -> Program {
+    let mut program = create_test_program();
+    let msg = program.constants.add_string("tick".to_string());
+
+    let mut print = Node::new(OpCode::Print, 2).with_args(&[1]);
+    if volatile {
+        print.set_flag(NodeFlag::Volatile);
+    }
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[msg]));
+    program.add_node(print);
+    program.add_node(Node::new(OpCode::CreateArray, 3).with_args(&[2]));
+    program.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[2]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[3, 4]));
+    program.set_entry_point(5);
+    program
+}
+
+#[test]
+fn test_shared_print_runs_once() {
+    let mut executor = Executor::new(create_shared_print_program(false));
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    executor.execute().unwrap();
+
+    assert_eq!(executor.take_output(), "tick\n");
+}
+
+/// Prints "first" and "second" from separate nodes and sequences them with
+/// `order`, given as result ids 3 (first) and 4 (second), then a final Int.
+fn create_seq_print_program(order: [u32; 2]) -> Program {
+    let mut program = create_test_program();
+    let first = program.constants.add_string("first".to_string());
+    let second = program.constants.add_string("second".to_string());
+    let done = program.constants.add_int(7);
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[first]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[second]));
+    program.add_node(Node::new(OpCode::Print, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Print, 4).with_args(&[2]));
+    program.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[done]));
+    program.add_node(Node::new(OpCode::Seq, 6).with_args(&[order[0], order[1], 5]));
+    program.set_entry_point(6);
+    program
+}
+
+#[test]
+fn test_seq_runs_steps_left_to_right() {
+    for (order, expected) in [([3, 4], "first\nsecond\n"), ([4, 3], "second\nfirst\n")] {
+        let mut executor = Executor::new(create_seq_print_program(order));
+        executor.grant_capability(Capability::IO);
+        executor.capture_output();
+
+        assert_eq!(executor.execute().unwrap(), Value::Int(7));
+        assert_eq!(executor.take_output(), expected);
+    }
+}
+
+#[test]
+fn test_volatile_print_runs_per_use() {
+    let mut executor = Executor::new(create_shared_print_program(true));
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    executor.execute().unwrap();
+
+    assert_eq!(executor.take_output(), "tick\ntick\n");
+}
+
+#[test]
+fn test_type_of_each_value() {
+    let mut program = create_test_program();
+    let int_idx = program.constants.add_int(1);
+    let float_idx = program.constants.add_float(1.5);
+    let str_idx = program.constants.add_string("s".to_string());
+    let bool_idx = program.constants.add_bool(true);
+    let size_idx = program.constants.add_int(1);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[int_idx]));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[float_idx]));
+    program.add_node(Node::new(OpCode::ConstString, 3).with_args(&[str_idx]));
+    program.add_node(Node::new(OpCode::ConstBool, 4).with_args(&[bool_idx]));
+    program.add_node(Node::new(OpCode::CreateArray, 5));
+    program.add_node(Node::new(OpCode::CreateMap, 6));
+    program.add_node(Node::new(OpCode::Nop, 7));
+    program.add_node(Node::new(OpCode::DefineFunc, 8).with_args(&[1, 0]));
+    program.add_node(Node::new(OpCode::ConstInt, 9).with_args(&[size_idx]));
+    program.add_node(Node::new(OpCode::Alloc, 10).with_args(&[9]));
+    program.add_node(Node::new(OpCode::AsyncBegin, 11));
+
+    let expected = [
+        (1, "int"), (2, "float"), (3, "string"), (4, "bool"), (5, "array"), (6, "map"),
+        (7, "nil"), (8, "function"), (10, "memoryref"), (11, "asynchandle"),
+    ];
+    for (i, (value_id, _)) in expected.iter().enumerate() {
+        program.add_node(Node::new(OpCode::TypeOf, 100 + i as u32).with_args(&[*value_id]));
+    }
+
+    for (i, (_, name)) in expected.iter().enumerate() {
+        let mut program = program.clone();
+        program.set_entry_point(100 + i as u32);
+
+        let mut executor = Executor::new(program);
+        assert_eq!(executor.execute().unwrap(), Value::String(name.to_string()));
+    }
+}
+
+#[test]
+fn test_type_of_feeds_comparison() {
+    let mut program = create_test_program();
+    let value_idx = program.constants.add_int(42);
+    let name_idx = program.constants.add_string("int".to_string());
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::TypeOf, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::ConstString, 3).with_args(&[name_idx]));
+    program.add_node(Node::new(OpCode::Eq, 4).with_args(&[2, 3]));
+    program.set_entry_point(4);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn test_str_concat_greeting() {
+    let mut program = create_test_program();
+    let greeting_idx = program.constants.add_string("Hello, ".to_string());
+    let slot_idx = program.constants.add_int(0);
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[greeting_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[slot_idx]));
+    program.add_node(Node::new(OpCode::LoadArg, 3).with_args(&[2]));
+    program.add_node(Node::new(OpCode::StrConcat, 4).with_args(&[1, 3]));
+    program.add_node(Node::new(OpCode::Print, 5).with_args(&[4]));
+    program.set_entry_point(5);
+
+    let mut executor = Executor::new(program);
+    executor.set_argument(0, Value::String("Ada".to_string()));
+    executor.grant_capability(Capability::IO);
+    executor.capture_output();
+    executor.execute().unwrap();
+
+    assert_eq!(executor.take_output(), "Hello, Ada\n");
+}
+
+#[test]
+fn test_str_concat_converts_and_rejects_nil() {
+    let mut program = create_test_program();
+    let label_idx = program.constants.add_string("n=".to_string());
+    let value_idx = program.constants.add_int(7);
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[label_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[value_idx]));
+    program.add_node(Node::new(OpCode::StrConcat, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Nop, 4));
+    program.add_node(Node::new(OpCode::StrConcat, 5).with_args(&[1, 4]));
+
+    program.set_entry_point(3);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::String("n=7".to_string()));
+
+    program.set_entry_point(5);
+    let mut executor = Executor::new(program);
+    assert!(matches!(executor.execute(), Err(RuntimeError::TypeMismatch { .. })));
+}
+
+fn execute_str_slice(s: &str, start: i64, end: i64) -> Result<Value> {
+    let mut program = create_test_program();
+    let s_idx = program.constants.add_string(s.to_string());
+    let start_idx = program.constants.add_int(start);
+    let end_idx = program.constants.add_int(end);
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[s_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[start_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[end_idx]));
+    program.add_node(Node::new(OpCode::StrSlice, 4).with_args(&[1, 2, 3]));
+    program.set_entry_point(4);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_str_len_counts_characters() {
+    for (s, expected) in [("", 0), ("hello", 5), ("héllo", 5), ("日本語", 3), ("a🦀b", 3)] {
+        let mut program = create_test_program();
+        let s_idx = program.constants.add_string(s.to_string());
+        program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[s_idx]));
+        program.add_node(Node::new(OpCode::StrLen, 2).with_args(&[1]));
+        program.set_entry_point(2);
+
+        let mut executor = Executor::new(program);
+        assert_eq!(executor.execute().unwrap(), Value::Int(expected), "length of {:?}", s);
+    }
+}
+
+#[test]
+fn test_str_slice_character_boundaries() {
+    assert_eq!(execute_str_slice("hello", 1, 4).unwrap(), Value::String("ell".to_string()));
+    assert_eq!(execute_str_slice("日本語", 1, 2).unwrap(), Value::String("本".to_string()));
+    assert_eq!(execute_str_slice("a🦀b", 1, 3).unwrap(), Value::String("🦀b".to_string()));
+    assert_eq!(execute_str_slice("héllo", 0, 5).unwrap(), Value::String("héllo".to_string()));
+    assert_eq!(execute_str_slice("日本語", 3, 3).unwrap(), Value::String(String::new()));
+}
+
+#[test]
+fn test_str_slice_out_of_range() {
+    // 3 characters but 9 bytes: a byte-based bound check would accept end = 4
+    for (start, end) in [(0, 4), (2, 1), (-1, 2)] {
+        match execute_str_slice("日本語", start, end) {
+            Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("out of range")),
+            other => panic!("Expected range error for {}..{}, got {:?}", start, end, other),
+        }
+    }
+}
+
+#[test]
+fn test_array_len_empty() {
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::CreateArray, 1));
+    program.add_node(Node::new(OpCode::ArrayLen, 2).with_args(&[1]));
+    program.set_entry_point(2);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Int(0));
+}
+
+#[test]
+fn test_array_len_of_arguments() {
+    let mut program = create_test_program();
+    let slot0 = program.constants.add_int(0);
+    let slot1 = program.constants.add_int(1);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[slot0]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[slot1]));
+    program.add_node(Node::new(OpCode::LoadArg, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::LoadArg, 4).with_args(&[2]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[3, 4, 3]));
+    program.add_node(Node::new(OpCode::ArrayLen, 6).with_args(&[5]));
+    program.add_node(Node::new(OpCode::ArrayLen, 7).with_args(&[4]));
+
+    program.set_entry_point(6);
+    let mut executor = Executor::new(program.clone());
+    executor.set_argument(0, Value::Int(7));
+    executor.set_argument(1, Value::Array(vec![Value::Int(1), Value::Int(2)]));
+    assert_eq!(executor.execute().unwrap(), Value::Int(3));
+
+    // An array passed in as an argument
+    program.set_entry_point(7);
+    let mut executor = Executor::new(program);
+    executor.set_argument(0, Value::Int(7));
+    executor.set_argument(1, Value::Array(vec![Value::Int(1), Value::Int(2)]));
+    assert_eq!(executor.execute().unwrap(), Value::Int(2));
+}
+
+/// LoadArg of slot 1, defaulting to the string "none" when `with_default`.
+fn create_load_arg_program(with_default: bool) -> Program {
+    let mut program = create_test_program();
+    let slot = program.constants.add_int(1);
+    let fallback = program.constants.add_string("none".to_string());
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[slot]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[fallback]));
+    let args: &[u32] = if with_default { &[1, 2] } else { &[1] };
+    program.add_node(Node::new(OpCode::LoadArg, 3).with_args(args));
+    program.set_entry_point(3);
+    program
+}
+
+#[test]
+fn test_load_arg_default() {
+    let mut executor = Executor::new(create_load_arg_program(true));
+    executor.set_argument(0, Value::Int(7));
+    assert_eq!(executor.execute().unwrap(), Value::String("none".to_string()));
+
+    let mut executor = Executor::new(create_load_arg_program(true));
+    executor.set_argument(1, Value::Int(8));
+    assert_eq!(executor.execute().unwrap(), Value::Int(8));
+}
+
+#[test]
+fn test_missing_load_arg_without_default_errors() {
+    let mut executor = Executor::new(create_load_arg_program(false));
+    executor.set_argument(0, Value::Int(7));
+    match executor.execute() {
+        Err(RuntimeError::InvalidOperation(msg)) => assert_eq!(msg, "Argument 1 not found"),
+        other => panic!("Expected missing argument error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_arg_count_reflects_set_argc() {
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::ArgCount, 1));
+    program.set_entry_point(1);
+
+    let mut executor = Executor::new(program.clone());
+    executor.set_argc(3);
+    assert_eq!(executor.execute().unwrap(), Value::Int(3));
+
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(0));
+}
+
+#[test]
+fn test_array_len_type_mismatch() {
+    let mut program = create_test_program();
+    let s_idx = program.constants.add_string("abc".to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[s_idx]));
+    program.add_node(Node::new(OpCode::ArrayLen, 2).with_args(&[1]));
+    program.set_entry_point(2);
+
+    let mut executor = Executor::new(program);
+    match executor.execute() {
+        Err(RuntimeError::TypeMismatch { expected, actual }) => {
+            assert_eq!(expected, "array");
+            assert_eq!(actual, "string");
+        }
+        other => panic!("Expected TypeMismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_external_call_requires_capability() {
+    let mut program = create_test_program();
+    let name_idx = program.constants.add_string("host".to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[name_idx]));
+    program.add_node(Node::new(OpCode::ExternalCall, 2).with_args(&[1]));
+    program.set_entry_point(2);
+
+    let mut executor = Executor::new(program);
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::ExternalCode, .. })
+    ));
+}
+
+#[test]
+fn test_array_push_builds_array() {
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::CreateArray, 1));
+
+    // Each push appends i to the array produced by the previous push
+    let mut array_id = 1;
+    for i in 0..10 {
+        let idx = program.constants.add_int(i);
+        let value_id = 100 + i as u32;
+        let push_id = 200 + i as u32;
+        program.add_node(Node::new(OpCode::ConstInt, value_id).with_args(&[idx]));
+        program.add_node(Node::new(OpCode::ArrayPush, push_id).with_args(&[array_id, value_id]));
+        array_id = push_id;
+    }
+    program.add_node(Node::new(OpCode::ArrayLen, 300).with_args(&[array_id]));
+    program.add_node(Node::new(OpCode::CreateArray, 301).with_args(&[1, array_id, 300]));
+    program.set_entry_point(301);
+
+    let mut executor = Executor::new(program);
+    let expected: Vec<Value> = (0..10).map(Value::Int).collect();
+    assert_eq!(
+        executor.execute().unwrap(),
+        // The original empty array is untouched by the pushes
+        Value::Array(vec![Value::Array(vec![]), Value::Array(expected), Value::Int(10)])
+    );
+}
+
+#[test]
+fn test_array_pop() {
+    let mut program = create_test_program();
+    let a = program.constants.add_int(1);
+    let b = program.constants.add_int(2);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::CreateArray, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::ArrayPop, 4).with_args(&[3]));
+    program.add_node(Node::new(OpCode::ArrayPop, 5).with_args(&[4]));
+    program.add_node(Node::new(OpCode::ArrayPop, 6).with_args(&[5]));
+
+    program.set_entry_point(4);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::Array(vec![Value::Int(1)]));
+
+    program.set_entry_point(5);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::Array(vec![]));
+
+    // Popping an empty array is an error rather than a silent no-op
+    program.set_entry_point(6);
+    let mut executor = Executor::new(program);
+    match executor.execute() {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("empty array")),
+        other => panic!("Expected empty array error, got {:?}", other),
+    }
+}
+
+fn create_external_call_program(name: &str) -> Program {
+    let mut program = create_test_program();
+    let name_idx = program.constants.add_string(name.to_string());
+    let a = program.constants.add_int(3);
+    let b = program.constants.add_int(8);
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[name_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[b]));
+    program.add_node(Node::new(OpCode::ExternalCall, 4).with_args(&[1, 2, 3]));
+    program.set_entry_point(4);
+    program
+}
+
+fn host_max(args: &[Value]) -> Result<Value> {
+    match args {
+        [Value::Int(a), Value::Int(b)] => Ok(Value::Int(*a.max(b))),
+        _ => Err(RuntimeError::ExternalCallFailed("max expects two ints".to_string())),
+    }
+}
+
+#[test]
+fn test_external_call_host_function() {
+    let mut executor = Executor::new(create_external_call_program("max"));
+    executor.grant_capability(Capability::ExternalCode);
+    executor.register_external("max", host_max);
+
+    assert_eq!(executor.execute().unwrap(), Value::Int(8));
+}
+
+#[test]
+fn test_external_call_unknown_function() {
+    let mut executor = Executor::new(create_external_call_program("min"));
+    executor.grant_capability(Capability::ExternalCode);
+    executor.register_external("max", host_max);
+
+    match executor.execute() {
+        Err(RuntimeError::ExternalCallFailed(msg)) => assert!(msg.contains("'min'")),
+        other => panic!("Expected ExternalCallFailed, got {:?}", other),
+    }
+}
+
+fn create_double_program() -> Program {
+    let mut program = create_test_program();
+    let name_idx = program.constants.add_string("double".to_string());
+    let value = program.constants.add_int(21);
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[name_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[value]));
+    program.add_node(Node::new(OpCode::ExternalCall, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Add, 4).with_args(&[3, 2]));
+    program.set_entry_point(4);
+    program
+}
+
+fn register_double(executor: &mut Executor) {
+    executor.register_external("double", |args: &[Value]| match args {
+        [Value::Int(n)] => Ok(Value::Int(n * 2)),
+        _ => Err(RuntimeError::ExternalCallFailed("double expects one int".to_string())),
+    });
+}
+
+#[test]
+fn test_external_call_double_in_graph() {
+    let mut executor = Executor::new(create_double_program());
+    executor.grant_capability(Capability::ExternalCode);
+    register_double(&mut executor);
+
+    assert_eq!(executor.execute().unwrap(), Value::Int(63));
+}
+
+#[test]
+fn test_external_call_double_needs_capability() {
+    let mut executor = Executor::new(create_double_program());
+    register_double(&mut executor);
+
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::ExternalCode, .. })
+    ));
+}
+
+fn create_self_call_program() -> Program {
+    // A function whose body unconditionally calls the function again
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::DefineFunc, 1).with_args(&[2, 0]));
+    program.add_node(Node::new(OpCode::Call, 2).with_args(&[1]));
+    program.set_entry_point(2);
+    program
+}
+
+#[test]
+fn test_unbounded_recursion_is_an_error() {
+    let mut executor = Executor::new(create_self_call_program());
+    executor.set_max_depth(200);
+
+    match executor.execute() {
+        Err(RuntimeError::StackOverflow { depth, function: None }) => assert_eq!(depth, 200),
+        other => panic!("Expected StackOverflow, got {:?}", other),
+    }
+}
+
+fn create_countdown_recursion_program(calls: i64) -> Program {
+    // f() { counter -= 1; if counter > 0 { f() } else { 0 } }, with the
+    // counter in memory. It is allocated at the top level before the first
+    // call so that all calls share it; inside a call it would be per call.
+    let mut program = create_test_program();
+    let zero = program.constants.add_int(0);
+    let one = program.constants.add_int(1);
+    let start = program.constants.add_int(calls);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[zero]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[one]));
+    program.add_node(Node::new(OpCode::ConstInt, 3).with_args(&[start]));
+    program.add_node(Node::new(OpCode::Alloc, 4).with_args(&[2, 3]));
+    let body = [
+        Node::new(OpCode::Load, 5).with_args(&[4]),
+        Node::new(OpCode::Sub, 6).with_args(&[5, 2]),
+        Node::new(OpCode::Store, 7).with_args(&[4, 6]),
+        Node::new(OpCode::Gt, 8).with_args(&[7, 1]),
+        Node::new(OpCode::Call, 9).with_args(&[10]),
+        Node::new(OpCode::Branch, 11).with_args(&[8, 9, 1]),
+    ];
+    for mut node in body {
+        node.set_flag(NodeFlag::Volatile);
+        program.add_node(node);
+    }
+    program.add_node(Node::new(OpCode::DefineFunc, 10).with_args(&[11, 0]));
+    program.add_node(Node::new(OpCode::Call, 12).with_args(&[10]));
+    program.add_node(Node::new(OpCode::Seq, 13).with_args(&[4, 12]));
+    program.set_entry_point(13);
+    program
+}
+
+#[test]
+fn test_call_depth_limit_names_the_function() {
+    let mut executor = Executor::new(create_countdown_recursion_program(20));
+    executor.set_max_call_depth(10);
+
+    match executor.execute() {
+        Err(e @ RuntimeError::StackOverflow { depth: 10, function: Some(11) }) => {
+            assert_eq!(e.to_string(), "Maximum call depth exceeded at depth 10 calling the function at node 11");
+        }
+        other => panic!("Expected StackOverflow, got {:?}", other),
+    }
+}
+
+/// Nodes 1-3 hold the constants 0, 1 and 10; nodes 4 and 5 read
+/// parameters 0 and 1 of whichever call is running.
+fn create_param_program() -> Program {
+    let mut program = create_test_program();
+    for (id, value) in [(1, 0), (2, 1), (3, 10)] {
+        let index = program.constants.add_int(value);
+        program.add_node(Node::new(OpCode::ConstInt, id).with_args(&[index]));
+    }
+    program.add_node(Node::new(OpCode::LoadParam, 4).with_args(&[1]));
+    program.add_node(Node::new(OpCode::LoadParam, 5).with_args(&[2]));
+    program
+}
+
+fn add_int_node(program: &mut Program, id: u32, value: i64) {
+    let index = program.constants.add_int(value);
+    program.add_node(Node::new(OpCode::ConstInt, id).with_args(&[index]));
+}
+
+#[test]
+fn test_call_binds_parameters() {
+    // add(a, b) = a + b, called as add(3, 4) and add(5, 6)
+    let mut program = create_param_program();
+    program.add_node(Node::new(OpCode::Add, 6).with_args(&[4, 5]));
+    program.add_node(Node::new(OpCode::DefineFunc, 7).with_args(&[6, 2]));
+    for (id, value) in [(8, 3), (9, 4), (10, 5), (11, 6)] {
+        add_int_node(&mut program, id, value);
+    }
+    program.add_node(Node::new(OpCode::Call, 12).with_args(&[7, 8, 9]));
+    program.add_node(Node::new(OpCode::Call, 13).with_args(&[7, 10, 11]));
+    program.add_node(Node::new(OpCode::CreateArray, 14).with_args(&[12, 13]));
+
+    program.set_entry_point(12);
+    assert_eq!(Executor::new(program.clone()).execute().unwrap(), Value::Int(7));
+
+    program.set_entry_point(14);
+    assert_eq!(
+        Executor::new(program).execute().unwrap(),
+        Value::Array(vec![Value::Int(7), Value::Int(11)])
+    );
+}
+
+#[test]
+fn test_nested_call_parameters_do_not_collide() {
+    // inner(z) = z + 1; outer(x, y) = x + inner(y * 10). Both read
+    // parameter 0 through the same node.
+    let mut program = create_param_program();
+    program.add_node(Node::new(OpCode::Add, 6).with_args(&[4, 2]));
+    program.add_node(Node::new(OpCode::DefineFunc, 7).with_args(&[6, 1]));
+    program.add_node(Node::new(OpCode::Mul, 8).with_args(&[5, 3]));
+    program.add_node(Node::new(OpCode::Call, 9).with_args(&[7, 8]));
+    program.add_node(Node::new(OpCode::Add, 10).with_args(&[4, 9]));
+    program.add_node(Node::new(OpCode::DefineFunc, 11).with_args(&[10, 2]));
+    add_int_node(&mut program, 12, 3);
+    add_int_node(&mut program, 13, 4);
+    program.add_node(Node::new(OpCode::Call, 14).with_args(&[11, 12, 13]));
+    program.set_entry_point(14);
+
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(44));
+}
+
+#[test]
+fn test_function_called_twice_computes_each_result() {
+    // double(x) = x * 2, called with 3 and then 5
+    let mut program = create_param_program();
+    add_int_node(&mut program, 6, 2);
+    program.add_node(Node::new(OpCode::Mul, 7).with_args(&[4, 6]));
+    program.add_node(Node::new(OpCode::DefineFunc, 8).with_args(&[7, 1]));
+    add_int_node(&mut program, 9, 3);
+    add_int_node(&mut program, 10, 5);
+    program.add_node(Node::new(OpCode::Call, 11).with_args(&[8, 9]));
+    program.add_node(Node::new(OpCode::Call, 12).with_args(&[8, 10]));
+    program.add_node(Node::new(OpCode::CreateArray, 13).with_args(&[11, 12]));
+    program.set_entry_point(13);
+
+    assert_eq!(
+        Executor::new(program).execute().unwrap(),
+        Value::Array(vec![Value::Int(6), Value::Int(10)])
+    );
+}
+
+#[test]
+fn test_recursive_factorial() {
+    // fact(n) = if n <= 1 { 1 } else { n * fact(n - 1) }
+    let mut program = create_param_program();
+    program.add_node(Node::new(OpCode::Le, 6).with_args(&[4, 2]));
+    program.add_node(Node::new(OpCode::Sub, 7).with_args(&[4, 2]));
+    program.add_node(Node::new(OpCode::Call, 8).with_args(&[10, 7]));
+    program.add_node(Node::new(OpCode::Mul, 9).with_args(&[4, 8]));
+    program.add_node(Node::new(OpCode::Branch, 11).with_args(&[6, 2, 9]));
+    program.add_node(Node::new(OpCode::DefineFunc, 10).with_args(&[11, 1]));
+    add_int_node(&mut program, 12, 5);
+    add_int_node(&mut program, 13, 3);
+    program.add_node(Node::new(OpCode::Call, 14).with_args(&[10, 12]));
+    program.add_node(Node::new(OpCode::Call, 15).with_args(&[10, 13]));
+    program.add_node(Node::new(OpCode::CreateArray, 16).with_args(&[14, 15]));
+    program.set_entry_point(16);
+
+    assert_eq!(
+        Executor::new(program).execute().unwrap(),
+        Value::Array(vec![Value::Int(120), Value::Int(6)])
+    );
+}
+
+/// count(n, acc) = if n <= 0 { acc } else { count(n - 1, acc + 1) } when
+/// `tail`, or sum(n) = if n <= 0 { 0 } else { n + sum(n - 1) } otherwise,
+/// called with `n`.
+fn create_counting_recursion_program(n: i64, tail: bool) -> Program {
+    let mut program = create_param_program();
+    program.add_node(Node::new(OpCode::Le, 6).with_args(&[4, 1]));
+    program.add_node(Node::new(OpCode::Sub, 7).with_args(&[4, 2]));
+    if tail {
+        program.add_node(Node::new(OpCode::Add, 8).with_args(&[5, 2]));
+        program.add_node(Node::new(OpCode::Call, 9).with_args(&[11, 7, 8]));
+        program.add_node(Node::new(OpCode::Branch, 12).with_args(&[6, 5, 9]));
+    } else {
+        program.add_node(Node::new(OpCode::Call, 8).with_args(&[11, 7]));
+        program.add_node(Node::new(OpCode::Add, 9).with_args(&[4, 8]));
+        program.add_node(Node::new(OpCode::Branch, 12).with_args(&[6, 1, 9]));
+    }
+    program.add_node(Node::new(OpCode::DefineFunc, 11).with_args(&[12, 2]));
+    add_int_node(&mut program, 13, n);
+    program.add_node(Node::new(OpCode::Call, 14).with_args(&[11, 13, 1]));
+    program.set_entry_point(14);
+    program
+}
+
+#[test]
+fn test_tail_recursion_reuses_its_frame() {
+    let mut executor = Executor::new(create_counting_recursion_program(100_000, true));
+    executor.set_max_call_depth(8);
+    assert_eq!(executor.execute().unwrap(), Value::Int(100_000));
+}
+
+#[test]
+fn test_non_tail_recursion_still_uses_frames() {
+    let mut executor = Executor::new(create_counting_recursion_program(20, false));
+    executor.set_max_call_depth(8);
+    assert!(matches!(executor.execute(), Err(RuntimeError::StackOverflow { depth: 8, .. })));
+
+    let mut executor = Executor::new(create_counting_recursion_program(20, false));
+    assert_eq!(executor.execute().unwrap(), Value::Int(210));
+}
+
+#[test]
+fn test_load_param_outside_a_call_is_an_error() {
+    let mut program = create_param_program();
+    program.set_entry_point(4);
+    match Executor::new(program.clone()).execute() {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("outside any function call"), "{}", msg),
+        other => panic!("Expected an error, got {:?}", other),
+    }
+
+    // A parameter the call did not pass
+    program.add_node(Node::new(OpCode::DefineFunc, 6).with_args(&[5, 0]));
+    program.add_node(Node::new(OpCode::Call, 7).with_args(&[6]));
+    program.set_entry_point(7);
+    match Executor::new(program).execute() {
+        Err(RuntimeError::InvalidOperation(msg)) => assert_eq!(msg, "Parameter 1 not passed; the call has 0 parameters"),
+        other => panic!("Expected an error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_raised_call_depth_allows_deeper_recursion() {
+    let mut executor = Executor::new(create_countdown_recursion_program(20));
+    executor.set_max_call_depth(20);
+    assert_eq!(executor.execute().unwrap(), Value::Int(0));
+}
+
+fn execute_array_slice(start: i64, end: i64) -> Result<Value> {
+    let mut program = create_test_program();
+    for (i, v) in [10, 20, 30].iter().enumerate() {
+        let idx = program.constants.add_int(*v);
+        program.add_node(Node::new(OpCode::ConstInt, i as u32 + 1).with_args(&[idx]));
+    }
+    let start_idx = program.constants.add_int(start);
+    let end_idx = program.constants.add_int(end);
+
+    program.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[1, 2, 3]));
+    program.add_node(Node::new(OpCode::ConstInt, 5).with_args(&[start_idx]));
+    program.add_node(Node::new(OpCode::ConstInt, 6).with_args(&[end_idx]));
+    program.add_node(Node::new(OpCode::ArraySlice, 7).with_args(&[4, 5, 6]));
+    program.set_entry_point(7);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_array_slice() {
+    assert_eq!(
+        execute_array_slice(1, 3).unwrap(),
+        Value::Array(vec![Value::Int(20), Value::Int(30)])
+    );
+    assert_eq!(execute_array_slice(0, 3).unwrap().to_string(), "[10, 20, 30]");
+    assert_eq!(execute_array_slice(2, 2).unwrap(), Value::Array(vec![]));
+}
+
+#[test]
+fn test_array_slice_bounds() {
+    assert!(matches!(
+        execute_array_slice(0, 4),
+        Err(RuntimeError::ArrayIndexOutOfBounds { index: 4, length: 3 })
+    ));
+    assert!(matches!(
+        execute_array_slice(-1, 2),
+        Err(RuntimeError::ArrayIndexOutOfBounds { length: 3, .. })
+    ));
+    assert!(matches!(
+        execute_array_slice(2, 1),
+        Err(RuntimeError::InvalidOperation(_))
+    ));
+}
+
+#[test]
+fn test_array_concat() {
+    let mut program = create_test_program();
+    let a = program.constants.add_int(1);
+    let b = program.constants.add_int(2);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::CreateArray, 3));
+    program.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::CreateArray, 5).with_args(&[4]));
+    program.add_node(Node::new(OpCode::ArrayConcat, 6).with_args(&[3, 3]));
+    program.add_node(Node::new(OpCode::ArrayConcat, 7).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::ArrayConcat, 8).with_args(&[4, 3]));
+    program.add_node(Node::new(OpCode::ArrayConcat, 9).with_args(&[4, 5]));
+
+    let one_two = Value::Array(vec![Value::Int(1), Value::Int(2)]);
+    let expected = [
+        (6, Value::Array(vec![])),
+        (7, one_two.clone()),
+        (8, one_two.clone()),
+        // Nested arrays are kept as single elements, not flattened
+        (9, Value::Array(vec![Value::Int(1), Value::Int(2), one_two])),
+    ];
+    for (entry, value) in expected {
+        let mut program = program.clone();
+        program.set_entry_point(entry);
+        let mut executor = Executor::new(program);
+        assert_eq!(executor.execute().unwrap(), value);
+    }
+}
+
+#[test]
+fn test_array_concat_type_mismatch() {
+    let mut program = create_test_program();
+    let a = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::CreateArray, 2));
+    program.add_node(Node::new(OpCode::ArrayConcat, 3).with_args(&[2, 1]));
+    program.set_entry_point(3);
+
+    let mut executor = Executor::new(program);
+    match executor.execute() {
+        Err(RuntimeError::TypeMismatch { actual, .. }) => assert_eq!(actual, "array and int"),
+        other => panic!("Expected TypeMismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_concat_strings() {
+    let mut program = create_test_program();
+    let hello = program.constants.add_string("Hello".to_string());
+    let world = program.constants.add_string(" World".to_string());
+
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[hello]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[world]));
+    program.add_node(Node::new(OpCode::Concat, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Len, 4).with_args(&[3]));
+
+    program.set_entry_point(3);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::String("Hello World".to_string()));
+
+    program.set_entry_point(4);
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Int(11));
+}
+
+#[test]
+fn test_concat_arrays() {
+    let mut program = create_test_program();
+    let a = program.constants.add_int(1);
+    let b = program.constants.add_int(2);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[a]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[b]));
+    program.add_node(Node::new(OpCode::CreateArray, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::CreateArray, 4).with_args(&[2, 1]));
+    program.add_node(Node::new(OpCode::Concat, 5).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::Concat, 6).with_args(&[3, 1]));
+
+    program.set_entry_point(5);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap().to_string(), "[1, 2, 1]");
+
+    program.set_entry_point(6);
+    let mut executor = Executor::new(program);
+    assert!(matches!(executor.execute(), Err(RuntimeError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_len_of_map() {
+    let mut program = create_test_program();
+    let key_a = program.constants.add_string("a".to_string());
+    let key_b = program.constants.add_string("b".to_string());
+    let one = program.constants.add_int(1);
+
+    program.add_node(Node::new(OpCode::CreateMap, 1));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[key_a]));
+    program.add_node(Node::new(OpCode::ConstString, 3).with_args(&[key_b]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[one]));
+    program.add_node(Node::new(OpCode::MapSet, 5).with_args(&[1, 2, 4]));
+    program.add_node(Node::new(OpCode::MapSet, 6).with_args(&[5, 3, 4]));
+    program.add_node(Node::new(OpCode::Len, 7).with_args(&[6]));
+    program.add_node(Node::new(OpCode::Len, 8).with_args(&[4]));
+
+    program.set_entry_point(7);
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::Int(2));
+
+    program.set_entry_point(8);
+    let mut executor = Executor::new(program);
+    assert!(matches!(executor.execute(), Err(RuntimeError::TypeMismatch { .. })));
+}
+
+fn create_map_program() -> Program {
+    // {"b": 2, "c": 3, "a": 1} built in non-sorted key order
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::CreateMap, 1));
+    let mut map_id = 1;
+    for (i, (key, value)) in [("b", 2), ("c", 3), ("a", 1)].iter().enumerate() {
+        let key_idx = program.constants.add_string(key.to_string());
+        let value_idx = program.constants.add_int(*value);
+        let base = 10 + 3 * i as u32;
+        program.add_node(Node::new(OpCode::ConstString, base).with_args(&[key_idx]));
+        program.add_node(Node::new(OpCode::ConstInt, base + 1).with_args(&[value_idx]));
+        program.add_node(Node::new(OpCode::MapSet, base + 2).with_args(&[map_id, base, base + 1]));
+        map_id = base + 2;
+    }
+    program.add_node(Node::new(OpCode::MapKeys, 100).with_args(&[map_id]));
+    program.add_node(Node::new(OpCode::MapValues, 101).with_args(&[map_id]));
+    program.add_node(Node::new(OpCode::MapContains, 102).with_args(&[map_id, 10]));
+    program.add_node(Node::new(OpCode::MapContains, 103).with_args(&[1, 10]));
+    program.add_node(Node::new(OpCode::MapContains, 104).with_args(&[map_id, 1]));
+    program.add_node(Node::new(OpCode::MapDelete, 105).with_args(&[map_id, 10]));
+    program.add_node(Node::new(OpCode::MapKeys, 106).with_args(&[105]));
+    program.add_node(Node::new(OpCode::MapDelete, 107).with_args(&[105, 10]));
+    program.add_node(Node::new(OpCode::MapKeys, 108).with_args(&[107]));
+    program
+}
+
+fn execute_map_program(entry: u32) -> Result<Value> {
+    let mut program = create_map_program();
+    program.set_entry_point(entry);
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_map_keys_and_values_sorted() {
+    let keys = ["a", "b", "c"].iter().map(|k| Value::String(k.to_string())).collect();
+    assert_eq!(execute_map_program(100).unwrap(), Value::Array(keys));
+    assert_eq!(
+        execute_map_program(101).unwrap(),
+        Value::Array(vec![Value::Int(1), Value::Int(2), Value::Int(3)])
+    );
+}
+
+#[test]
+fn test_map_contains() {
+    assert_eq!(execute_map_program(102).unwrap(), Value::Bool(true));
+    // The empty map the entries were added to is unchanged
+    assert_eq!(execute_map_program(103).unwrap(), Value::Bool(false));
+    assert!(matches!(execute_map_program(104), Err(RuntimeError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_map_int_and_mixed_keys() {
+    let mut program = create_test_program();
+    let int_key = program.constants.add_int(7);
+    let str_key = program.constants.add_string("seven".to_string());
+    let bool_key = program.constants.add_bool(true);
+    let value_a = program.constants.add_string("int".to_string());
+    let value_b = program.constants.add_string("string".to_string());
+
+    program.add_node(Node::new(OpCode::CreateMap, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[int_key]));
+    program.add_node(Node::new(OpCode::ConstString, 3).with_args(&[str_key]));
+    program.add_node(Node::new(OpCode::ConstBool, 4).with_args(&[bool_key]));
+    program.add_node(Node::new(OpCode::ConstString, 5).with_args(&[value_a]));
+    program.add_node(Node::new(OpCode::ConstString, 6).with_args(&[value_b]));
+    program.add_node(Node::new(OpCode::MapSet, 7).with_args(&[1, 2, 5]));
+    program.add_node(Node::new(OpCode::MapSet, 8).with_args(&[7, 3, 6]));
+    program.add_node(Node::new(OpCode::MapSet, 9).with_args(&[8, 4, 2]));
+    program.add_node(Node::new(OpCode::MapGet, 10).with_args(&[9, 2]));
+    program.add_node(Node::new(OpCode::MapGet, 11).with_args(&[9, 3]));
+    program.add_node(Node::new(OpCode::MapGet, 12).with_args(&[9, 4]));
+    program.add_node(Node::new(OpCode::MapKeys, 13).with_args(&[9]));
+
+    let expected = [
+        (10, Value::String("int".to_string())),
+        (11, Value::String("string".to_string())),
+        (12, Value::Int(7)),
+        // Keys sort bools first, then ints, then strings
+        (13, Value::Array(vec![
+            Value::Bool(true),
+            Value::Int(7),
+            Value::String("seven".to_string()),
+        ])),
+    ];
+    for (entry, value) in expected {
+        let mut program = program.clone();
+        program.set_entry_point(entry);
+        let mut executor = Executor::new(program);
+        assert_eq!(executor.execute().unwrap(), value);
+    }
+}
+
+#[test]
+fn test_map_delete() {
+    let keys = |names: &[&str]| Value::Array(
+        names.iter().map(|k| Value::String(k.to_string())).collect()
+    );
+    // Node 10 is the key "b"
+    assert_eq!(execute_map_program(106).unwrap(), keys(&["a", "c"]));
+    // Deleting it again is a no-op
+    assert_eq!(execute_map_program(108).unwrap(), keys(&["a", "c"]));
+}
+
+#[test]
+fn test_map_display_is_sorted() {
+    let mut map = std::collections::HashMap::new();
+    map.insert(MapKey::String("b".to_string()), Value::Int(2));
+    map.insert(MapKey::Int(10), Value::Int(3));
+    map.insert(MapKey::String("a".to_string()), Value::Int(1));
+    map.insert(MapKey::Int(2), Value::Int(4));
+    map.insert(MapKey::Bool(false), Value::Nil);
+
+    assert_eq!(Value::Map(map).to_string(), "{false: nil, 2: 4, 10: 3, a: 1, b: 2}");
+}
+
+fn execute_parse(opcode: OpCode, text: &str) -> Result<Value> {
+    let mut program = create_test_program();
+    let idx = program.constants.add_string(text.to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[idx]));
+    program.add_node(Node::new(opcode, 2).with_args(&[1]));
+    program.set_entry_point(2);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_parse_numbers() {
+    assert_eq!(execute_parse(OpCode::ParseInt, "-17").unwrap(), Value::Int(-17));
+    assert_eq!(execute_parse(OpCode::ParseInt, "  42\n").unwrap(), Value::Int(42));
+    assert_eq!(execute_parse(OpCode::ParseFloat, "\t2.5 ").unwrap(), Value::Float(2.5));
+    assert_eq!(execute_parse(OpCode::ParseFloat, "3").unwrap(), Value::Float(3.0));
+
+    match execute_parse(OpCode::ParseInt, "4.2") {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("\"4.2\"")),
+        other => panic!("Expected parse failure, got {:?}", other),
+    }
+    match execute_parse(OpCode::ParseFloat, "two") {
+        Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("\"two\"")),
+        other => panic!("Expected parse failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_int_from_map_value() {
+    // {"n": "42"}["n"] parsed and incremented
+    let mut program = create_test_program();
+    let key = program.constants.add_string("n".to_string());
+    let text = program.constants.add_string("42".to_string());
+    let one = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::CreateMap, 1));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[key]));
+    program.add_node(Node::new(OpCode::ConstString, 3).with_args(&[text]));
+    program.add_node(Node::new(OpCode::MapSet, 4).with_args(&[1, 2, 3]));
+    program.add_node(Node::new(OpCode::MapGet, 5).with_args(&[4, 2]));
+    program.add_node(Node::new(OpCode::ParseInt, 6).with_args(&[5]));
+    program.add_node(Node::new(OpCode::ConstInt, 7).with_args(&[one]));
+    program.add_node(Node::new(OpCode::Add, 8).with_args(&[6, 7]));
+    program.set_entry_point(8);
+
+    assert_eq!(Executor::new(program).execute().unwrap(), Value::Int(43));
+}
+
+fn execute_format(value: Value, spec: &str) -> Result<Value> {
+    let mut program = create_test_program();
+    let value_node = match value {
+        Value::Int(i) => Node::new(OpCode::ConstInt, 1).with_args(&[program.constants.add_int(i)]),
+        Value::Float(f) => Node::new(OpCode::ConstFloat, 1).with_args(&[program.constants.add_float(f)]),
+        Value::String(s) => Node::new(OpCode::ConstString, 1).with_args(&[program.constants.add_string(s)]),
+        other => panic!("unsupported test value {:?}", other),
+    };
+    let spec_idx = program.constants.add_string(spec.to_string());
+    program.add_node(value_node);
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[spec_idx]));
+    program.add_node(Node::new(OpCode::Format, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    Executor::new(program).execute()
+}
+
+#[test]
+fn test_format_values() {
+    let format = |value, spec| match execute_format(value, spec).unwrap() {
+        Value::String(s) => s,
+        other => panic!("Expected a string, got {:?}", other),
+    };
+    assert_eq!(format(Value::Float(1.23456), "{:.2}"), "1.23");
+    assert_eq!(format(Value::Float(2.5), "{:.0}"), "2");
+    assert_eq!(format(Value::Int(7), "{:.1}"), "7.0");
+    assert_eq!(format(Value::Int(42), "{:05}"), "00042");
+    assert_eq!(format(Value::Int(-42), "{:05}"), "-0042");
+    assert_eq!(format(Value::Float(-1.5), "{:07.2}"), "-001.50");
+    assert_eq!(format(Value::Int(255), "{:x}"), "ff");
+    assert_eq!(format(Value::Int(255), "{:04X}"), "00FF");
+    assert_eq!(format(Value::Int(5), "{:b}"), "101");
+    assert_eq!(format(Value::Int(42), "{:4}"), "  42");
+    assert_eq!(format(Value::String("ab".to_string()), "{:4}"), "ab  ");
+    assert_eq!(format(Value::String("ab".to_string()), "{}"), "ab");
+}
+
+#[test]
+fn test_format_invalid_spec() {
+    for (value, spec) in [
+        (Value::Int(1), "{:q}"),
+        (Value::Int(1), "{:.}"),
+        (Value::Int(1), "{:>5}"),
+        (Value::Int(1), "x = {}"),
+        (Value::Float(1.5), "{:x}"),
+        (Value::String("s".to_string()), "{:.2}"),
+    ] {
+        match execute_format(value, spec) {
+            Err(RuntimeError::InvalidOperation(msg)) => {
+                assert!(msg.contains(&format!("{:?}", spec)), "{}", msg)
+            }
+            other => panic!("Expected invalid spec for {:?}, got {:?}", spec, other),
+        }
+    }
+}
+
+#[test]
+fn test_nested_array_of_maps_display_is_stable() {
+    let build = |keys: &[&str]| {
+        let mut map = std::collections::HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(MapKey::String(key.to_string()), Value::Int(i as i64));
+        }
+        map
+    };
+    let inner = Value::Array(vec![Value::Bool(true), Value::Nil]);
+    let mut first = build(&["y", "x"]);
+    first.insert(MapKey::Int(1), inner.clone());
+    let mut second = build(&["x", "y"]);
+    second.insert(MapKey::Int(1), inner);
+
+    let a = Value::Array(vec![Value::Map(first), Value::Map(build(&[])), Value::Float(0.5)]);
+    let b = Value::Array(vec![Value::Map(second), Value::Map(build(&[])), Value::Float(0.5)]);
+    assert_eq!(a.to_string(), "[{1: [true, nil], x: 1, y: 0}, {}, 0.5]");
+    assert_eq!(b.to_string(), "[{1: [true, nil], x: 0, y: 1}, {}, 0.5]");
+}
+
+#[test]
+fn test_opaque_value_display() {
+    let function = Value::Function(std::sync::Arc::new(Function {
+        node_id: 7,
+        arity: 2,
+        captured_values: std::collections::HashMap::new(),
+    }));
+    assert_eq!(function.to_string(), "<fn@7/2>");
+
+    let memref = Value::MemoryRef(MemoryReference { address: 0x1000, offset: 3 });
+    assert_eq!(memref.to_string(), "<memref 0x1000+3>");
+
+    let mut runtime = AsyncRuntime::new();
+    let first = runtime.begin_async();
+    let handle = Value::AsyncHandle(runtime.begin_async());
+    assert_eq!(handle.to_string(), format!("<async #{}>", first.id + 1));
+}
+
+#[test]
+fn test_map_float_key_rejected() {
+    let mut program = create_test_program();
+    let key = program.constants.add_float(1.5);
+    program.add_node(Node::new(OpCode::CreateMap, 1));
+    program.add_node(Node::new(OpCode::ConstFloat, 2).with_args(&[key]));
+    program.add_node(Node::new(OpCode::MapSet, 3).with_args(&[1, 2, 2]));
+    program.set_entry_point(3);
+
+    let mut executor = Executor::new(program);
+    match executor.execute() {
+        Err(RuntimeError::TypeMismatch { expected, actual }) => {
+            assert_eq!(expected, "map and string, int or bool key");
+            assert_eq!(actual, "map and float");
+        }
+        other => panic!("Expected TypeMismatch, got {:?}", other),
+    }
+}
+
+fn create_random_program(range: Option<(i64, i64)>) -> Program {
+    let mut program = create_test_program();
+    match range {
+        Some((low, high)) => {
+            let low = program.constants.add_int(low);
+            let high = program.constants.add_int(high);
+            program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[low]));
+            program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[high]));
+            program.add_node(Node::new(OpCode::Random, 3).with_args(&[1, 2]));
+        }
+        None => {
+            program.add_node(Node::new(OpCode::Random, 3));
+        }
+    }
+    program.set_entry_point(3);
+    program
+}
+
+fn seeded_random_run(range: Option<(i64, i64)>, seed: u64) -> Value {
+    let mut executor = Executor::new(create_random_program(range));
+    executor.grant_capability(Capability::Entropy);
+    executor.set_random_seed(seed);
+    executor.execute().unwrap()
+}
+
+#[test]
+fn test_random_is_reproducible_with_seed() {
+    let first = seeded_random_run(None, 42);
+    assert_eq!(first, seeded_random_run(None, 42));
+    assert_ne!(first, seeded_random_run(None, 43));
+
+    for seed in 0..50 {
+        match seeded_random_run(None, seed) {
+            Value::Float(f) => assert!((0.0..1.0).contains(&f), "{}", f),
+            other => panic!("expected float, got {:?}", other),
+        }
+        match seeded_random_run(Some((-3, 4)), seed) {
+            Value::Int(n) => assert!((-3..4).contains(&n), "{}", n),
+            other => panic!("expected int, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_random_rejects_empty_range() {
+    let mut executor = Executor::new(create_random_program(Some((5, 5))));
+    executor.grant_capability(Capability::Entropy);
+    assert!(matches!(executor.execute(), Err(RuntimeError::InvalidOperation(_))));
+}
+
+#[test]
+fn test_random_requires_entropy_capability() {
+    let mut executor = Executor::new(create_random_program(None));
+    executor.grant_capability(Capability::IO);
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::Entropy, .. })
+    ));
+}
+
+#[test]
+fn test_volatile_now_reads_fake_clock() {
+    let mut program = create_test_program();
+    for id in [1, 2] {
+        let mut now = Node::new(OpCode::Now, id);
+        now.set_flag(NodeFlag::Volatile);
+        program.add_node(now);
+    }
+    program.add_node(Node::new(OpCode::CreateArray, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    let ticks = std::rc::Rc::new(std::cell::Cell::new(1_700_000_000_000i64));
+    let clock = ticks.clone();
+    let mut executor = Executor::new(program);
+    executor.grant_capability(Capability::Clock);
+    executor.set_clock(Box::new(move || {
+        clock.set(clock.get() + 5);
+        clock.get()
+    }));
+
+    let times = match executor.execute().unwrap() {
+        Value::Array(items) => items.iter().map(|item| match item {
+            Value::Int(ms) => *ms,
+            other => panic!("expected int, got {:?}", other),
+        }).collect::<Vec<_>>(),
+        other => panic!("expected array, got {:?}", other),
+    };
+    assert_eq!(times.len(), 2);
+    assert!(times[0] <= times[1]);
+    assert_eq!(times[1], ticks.get());
+}
+
+#[test]
+fn test_now_requires_clock_capability() {
+    let mut program = create_test_program();
+    program.add_node(Node::new(OpCode::Now, 1));
+    program.set_entry_point(1);
+
+    let mut executor = Executor::new(program);
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::Clock, .. })
+    ));
+}
+
+fn create_get_env_program(name: &str) -> Program {
+    let mut program = create_test_program();
+    let name_idx = program.constants.add_string(name.to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[name_idx]));
+    program.add_node(Node::new(OpCode::GetEnv, 2).with_args(&[1]));
+    program.set_entry_point(2);
+    program
+}
+
+#[test]
+fn test_get_env_override_and_real_environment() {
+    let mut executor = Executor::new(create_get_env_program("DER_TEST_OVERRIDE"));
+    executor.grant_capability(Capability::Process);
+    executor.set_env_override("DER_TEST_OVERRIDE", "from override");
+    assert_eq!(executor.execute().unwrap(), Value::String("from override".to_string()));
+
+    // Cargo sets this for every test process
+    let mut executor = Executor::new(create_get_env_program("CARGO_PKG_NAME"));
+    executor.grant_capability(Capability::Process);
+    assert_eq!(executor.execute().unwrap(), Value::String(std::env::var("CARGO_PKG_NAME").unwrap()));
+
+    let mut executor = Executor::new(create_get_env_program("DER_TEST_SURELY_UNSET"));
+    executor.grant_capability(Capability::Process);
+    assert_eq!(executor.execute().unwrap(), Value::Nil);
+}
+
+#[test]
+fn test_get_env_requires_process_capability() {
+    let mut executor = Executor::new(create_get_env_program("CARGO_PKG_NAME"));
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { capability: Capability::Process, .. })
+    ));
+}
+
+fn create_http_get_program(url: &str) -> Program {
+    let mut program = create_test_program();
+    let url_idx = program.constants.add_string(url.to_string());
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[url_idx]));
+    program.add_node(Node::new(OpCode::HttpGet, 2).with_args(&[1]));
+    program.set_entry_point(2);
+    program
+}
+
+#[test]
+fn test_http_get_requires_network_capability() {
+    let mut executor = Executor::new(create_http_get_program("http://127.0.0.1:1/"));
+    assert!(matches!(
+        executor.execute(),
+        Err(RuntimeError::CapabilityDenied { opcode: OpCode::HttpGet, capability: Capability::Network })
+    ));
+}
+
+/// Serve `response` verbatim to the first connection on a local port, after
+/// reading the request headers. Returns the port.
+#[cfg(feature = "http")]
+fn serve_once(response: &'static str) -> u16 {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buffer).unwrap();
+            if n == 0 {
+                return;
+            }
+            request.extend_from_slice(&buffer[..n]);
+        }
+        let _ = stream.write_all(response.as_bytes());
+    });
+    port
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_http_get_local_server() {
+    let port = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let mut executor = Executor::new(create_http_get_program(&format!("http://127.0.0.1:{}/greeting", port)));
+    executor.grant_capability(Capability::Network);
+    assert_eq!(
+        executor.execute().unwrap(),
+        Value::Array(vec![Value::Int(200), Value::String("hello".to_string())])
+    );
+
+    let port = serve_once("HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nnope\r\n0\r\n\r\n");
+    let mut executor = Executor::new(create_http_get_program(&format!("http://127.0.0.1:{}/", port)));
+    executor.grant_capability(Capability::Network);
+    assert_eq!(
+        executor.execute().unwrap(),
+        Value::Array(vec![Value::Int(404), Value::String("nope".to_string())])
+    );
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_http_get_failures_name_the_url() {
+    // Nothing listens on a port whose listener was just dropped
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let url = format!("http://127.0.0.1:{}/", port);
+    let mut executor = Executor::new(create_http_get_program(&url));
+    executor.grant_capability(Capability::Network);
+    match executor.execute() {
+        Err(e @ RuntimeError::NetworkError { .. }) => assert!(e.to_string().contains(&url)),
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+
+    // A server that accepts but never answers runs into the timeout
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+    let mut executor = Executor::new(create_http_get_program(&url));
+    executor.grant_capability(Capability::Network);
+    executor.set_http_timeout(std::time::Duration::from_millis(100));
+    match executor.execute() {
+        Err(RuntimeError::NetworkError { url: failed, message }) => {
+            assert_eq!(failed, url);
+            assert!(message.contains("timed out"), "{}", message);
+        }
+        other => panic!("Expected NetworkError, got {:?}", other),
+    }
+    drop(listener);
+}
+
+fn create_assert_program(condition: bool, message: Option<&str>) -> Program {
+    let mut program = create_test_program();
+    let cond_idx = program.constants.add_bool(condition);
+    program.add_node(Node::new(OpCode::ConstBool, 1).with_args(&[cond_idx]));
+    match message {
+        Some(text) => {
+            let text_idx = program.constants.add_string(text.to_string());
+            program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[text_idx]));
+            program.add_node(Node::new(OpCode::Assert, 3).with_args(&[1, 2]));
+        }
+        None => {
+            program.add_node(Node::new(OpCode::Assert, 3).with_args(&[1]));
+        }
+    }
+    program.set_entry_point(3);
+    program
+}
+
+#[test]
+fn test_assert_passes() {
+    let mut executor = Executor::new(create_assert_program(true, Some("never shown")));
+    assert_eq!(executor.execute().unwrap(), Value::Nil);
+}
+
+#[test]
+fn test_assert_fails_with_message() {
+    let mut executor = Executor::new(create_assert_program(false, Some("balance must be positive")));
+    let err = executor.execute().unwrap_err();
+
+    assert!(matches!(
+        &err,
+        RuntimeError::AssertionFailed { node_id: 3, message } if message == "balance must be positive"
+    ));
+    assert_eq!(err.to_string(), "Assertion failed at node 3: balance must be positive");
+}
+
+#[test]
+fn test_assert_fails_without_message() {
+    let mut executor = Executor::new(create_assert_program(false, None));
+    let err = executor.execute().unwrap_err();
+
+    assert!(matches!(err, RuntimeError::AssertionFailed { node_id: 3, .. }));
+    assert!(err.to_string().contains("node 3"), "{}", err);
+}
+
+#[test]
+fn test_try_recovers_division_by_zero() {
+    let mut program = create_test_program();
+    let ten = program.constants.add_int(10);
+    let zero = program.constants.add_int(0);
+    let fallback = program.constants.add_int(-1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[zero]));
+    program.add_node(Node::new(OpCode::Div, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[fallback]));
+    program.add_node(Node::new(OpCode::Try, 5).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::CreateArray, 6).with_args(&[5, CAUGHT_ERROR_SLOT]));
+    program.set_entry_point(6);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Array(vec![
+        Value::Int(-1),
+        Value::String("Division by zero".to_string()),
+    ]));
+}
+
+#[test]
+fn test_try_recovers_map_miss() {
+    let mut program = create_map_program();
+    let missing = program.constants.add_string("zzz".to_string());
+    let fallback = program.constants.add_int(0);
+    program.add_node(Node::new(OpCode::ConstString, 200).with_args(&[missing]));
+    program.add_node(Node::new(OpCode::MapGet, 201).with_args(&[16, 200]));
+    program.add_node(Node::new(OpCode::ConstInt, 202).with_args(&[fallback]));
+    program.add_node(Node::new(OpCode::Try, 203).with_args(&[201, 202]));
+    program.set_entry_point(203);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(executor.execute().unwrap(), Value::Int(0));
+}
+
+#[test]
+fn test_nested_try_with_failing_handler() {
+    let mut program = create_test_program();
+    let ten = program.constants.add_int(10);
+    let zero = program.constants.add_int(0);
+    let fallback = program.constants.add_int(42);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[zero]));
+    program.add_node(Node::new(OpCode::Div, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Mod, 4).with_args(&[1, 2]));
+    // The inner handler fails too, so the outer handler takes over
+    program.add_node(Node::new(OpCode::Try, 5).with_args(&[3, 4]));
+    program.add_node(Node::new(OpCode::ConstInt, 6).with_args(&[fallback]));
+    program.add_node(Node::new(OpCode::Try, 7).with_args(&[5, 6]));
+    program.set_entry_point(7);
+
+    let mut executor = Executor::new(program.clone());
+    assert_eq!(executor.execute().unwrap(), Value::Int(42));
+
+    // Without the outer Try the handler's error escapes
+    program.set_entry_point(5);
+    let mut executor = Executor::new(program);
+    assert!(matches!(executor.execute(), Err(RuntimeError::DivisionByZero)));
+}
+
+fn create_throw_program(condition: bool) -> Program {
+    // Branch(condition, Throw("insufficient funds"), 7)
+    let mut program = create_test_program();
+    let cond_idx = program.constants.add_bool(condition);
+    let msg_idx = program.constants.add_string("insufficient funds".to_string());
+    let seven = program.constants.add_int(7);
+    program.add_node(Node::new(OpCode::ConstBool, 1).with_args(&[cond_idx]));
+    program.add_node(Node::new(OpCode::ConstString, 2).with_args(&[msg_idx]));
+    program.add_node(Node::new(OpCode::Throw, 3).with_args(&[2]));
+    program.add_node(Node::new(OpCode::ConstInt, 4).with_args(&[seven]));
+    program.add_node(Node::new(OpCode::Branch, 5).with_args(&[1, 3, 4]));
+    program.set_entry_point(5);
+    program
+}
+
+#[test]
+fn test_uncaught_throw_surfaces_user_error() {
+    let mut executor = Executor::new(create_throw_program(true));
+    let err = executor.execute().unwrap_err();
+
+    assert!(matches!(&err, RuntimeError::UserError(msg) if msg == "insufficient funds"));
+    assert_eq!(err.to_string(), "User error: insufficient funds");
+}
+
+#[test]
+fn test_throw_in_untaken_branch_does_not_fire() {
+    let mut executor = Executor::new(create_throw_program(false));
+    assert_eq!(executor.execute().unwrap(), Value::Int(7));
+}
+
+#[test]
+fn test_try_catches_throw() {
+    let mut program = create_throw_program(true);
+    program.add_node(Node::new(OpCode::Try, 6).with_args(&[5, CAUGHT_ERROR_SLOT]));
+    program.set_entry_point(6);
+
+    let mut executor = Executor::new(program);
+    assert_eq!(
+        executor.execute().unwrap(),
+        Value::String("User error: insufficient funds".to_string())
+    );
+}
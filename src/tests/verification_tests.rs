@@ -61,6 +61,22 @@ fn test_proof_generation_impure_operation() {
     assert!(proof.is_err());
 }
 
+#[test]
+fn test_seq_is_pure_only_if_its_steps_are() {
+    let mut program = Program::new();
+    let msg = program.constants.add_string("Hello".to_string());
+    let one = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[msg]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[one]));
+    program.add_node(Node::new(OpCode::Print, 3).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Seq, 4).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Seq, 5).with_args(&[3, 2]));
+
+    let generator = ProofGenerator::new(program);
+    assert!(generator.generate_proof(4, "IsPure").is_ok());
+    assert!(generator.generate_proof(5, "IsPure").is_err());
+}
+
 #[test]
 fn test_constraint_checker_type_constraints() {
     let mut checker = ConstraintChecker::new();
@@ -84,6 +100,28 @@ fn test_constraint_checker_type_constraints() {
     assert_eq!(violations[0].constraint_name, "x_is_integer");
 }
 
+#[test]
+fn test_constraint_checker_map_key_types() {
+    let mut checker = ConstraintChecker::new();
+    checker.add_constraint(Constraint {
+        name: "counts".to_string(),
+        expression: ConstraintExpression::TypeIs(
+            "m".to_string(),
+            TypeConstraint::Map(Box::new(TypeConstraint::Integer), Box::new(TypeConstraint::Integer)),
+        ),
+        severity: ConstraintSeverity::Error,
+    });
+    
+    let mut map = std::collections::HashMap::new();
+    map.insert(MapKey::Int(3), Value::Int(1));
+    checker.set_value("m".to_string(), Value::Map(map.clone()));
+    assert!(checker.check_all().is_empty());
+    
+    map.insert(MapKey::String("3".to_string()), Value::Int(1));
+    checker.set_value("m".to_string(), Value::Map(map));
+    assert_eq!(checker.check_all().len(), 1);
+}
+
 #[test]
 fn test_constraint_checker_range_constraints() {
     let mut checker = ConstraintChecker::new();
@@ -155,6 +193,120 @@ fn test_constraint_checker_array_constraints() {
     assert_eq!(violations.len(), 1);
 }
 
+fn single_constraint_checker(expression: ConstraintExpression) -> ConstraintChecker {
+    let mut checker = ConstraintChecker::new();
+    checker.add_constraint(Constraint {
+        name: "under_test".to_string(),
+        expression,
+        severity: ConstraintSeverity::Error,
+    });
+    checker
+}
+
+#[test]
+fn test_constraint_checker_unique() {
+    let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let mut checker = single_constraint_checker(ConstraintExpression::Unique(names));
+    
+    checker.set_value("a".to_string(), Value::Int(1));
+    checker.set_value("b".to_string(), Value::Int(2));
+    checker.set_value("c".to_string(), Value::String("1".to_string()));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("c".to_string(), Value::Int(1));
+    let violations = checker.check_all();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].message, "a and c hold the same value");
+}
+
+#[test]
+fn test_constraint_checker_not_equal() {
+    let mut checker = single_constraint_checker(
+        ConstraintExpression::NotEqual("x".to_string(), "y".to_string())
+    );
+    
+    checker.set_value("x".to_string(), Value::Int(1));
+    checker.set_value("y".to_string(), Value::Int(2));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("y".to_string(), Value::Int(1));
+    assert_eq!(checker.check_all().len(), 1);
+}
+
+#[test]
+fn test_constraint_checker_array_contains() {
+    let mut checker = single_constraint_checker(
+        ConstraintExpression::ArrayContains("arr".to_string(), Value::Int(3))
+    );
+    
+    checker.set_value("arr".to_string(), Value::Array(vec![Value::Int(1), Value::Int(3)]));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("arr".to_string(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
+    let violations = checker.check_all();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].message, "arr does not contain 3");
+    
+    checker.set_value("arr".to_string(), Value::Int(3));
+    assert_eq!(checker.check_all()[0].message, "arr is not an array");
+}
+
+#[test]
+fn test_constraint_checker_type_compatible() {
+    let mut checker = single_constraint_checker(
+        ConstraintExpression::TypeCompatible("x".to_string(), "y".to_string())
+    );
+    
+    // Int and Float are compatible numeric types
+    checker.set_value("x".to_string(), Value::Int(1));
+    checker.set_value("y".to_string(), Value::Float(2.5));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("x".to_string(), Value::Array(vec![Value::Int(1)]));
+    checker.set_value("y".to_string(), Value::Array(vec![]));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("y".to_string(), Value::String("2".to_string()));
+    let violations = checker.check_all();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].message, "x (array<int>) is not compatible with y (string)");
+}
+
+#[test]
+fn test_constraint_checker_array_sorted_mixed_types() {
+    let mut checker = single_constraint_checker(
+        ConstraintExpression::ArraySorted("arr".to_string(), SortOrder::Ascending)
+    );
+    
+    checker.set_value("arr".to_string(), Value::Array(vec![
+        Value::Int(1),
+        Value::Float(2.0),
+        Value::Int(3),
+    ]));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("arr".to_string(), Value::Array(vec![Value::Float(2.5), Value::Int(2)]));
+    assert_eq!(checker.check_all()[0].message, "Array is not sorted in ascending order");
+    
+    // Strings only compare with strings
+    checker.set_value("arr".to_string(), Value::Array(vec![Value::Int(1), Value::String("2".to_string())]));
+    assert_eq!(checker.check_all()[0].message, "Cannot compare array elements");
+}
+
+#[test]
+fn test_constraint_checker_array_sorted_strings() {
+    let mut checker = single_constraint_checker(
+        ConstraintExpression::ArraySorted("words".to_string(), SortOrder::Descending)
+    );
+    
+    let words = |items: &[&str]| Value::Array(items.iter().map(|w| Value::String(w.to_string())).collect());
+    checker.set_value("words".to_string(), words(&["pear", "apple", "apple", "Zebra"]));
+    assert!(checker.check_all().is_empty());
+    
+    checker.set_value("words".to_string(), words(&["apple", "pear"]));
+    assert_eq!(checker.check_all().len(), 1);
+}
+
 #[test]
 fn test_constraint_checker_sorted_array() {
     let mut checker = ConstraintChecker::new();
@@ -275,6 +427,89 @@ fn test_verifier_invalid_arg_count() {
     assert!(result.errors[0].message.contains("expects 2 arguments"));
 }
 
+fn first_verification_error(node: Node) -> String {
+    let mut program = Program::new();
+    let c = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c]));
+    program.add_node(node);
+    program.set_entry_point(node.result_id);
+
+    let result = Verifier::new(program).verify_program();
+    assert!(!result.is_valid);
+    result.errors[0].message.clone()
+}
+
+#[test]
+fn test_verifier_flags_wrong_arg_counts() {
+    let msg = first_verification_error(Node::new(OpCode::Mul, 3).with_args(&[1]));
+    assert!(msg.contains("Mul expects 2 arguments, got 1"), "{}", msg);
+
+    let msg = first_verification_error(Node::new(OpCode::Not, 3).with_args(&[1, 2]));
+    assert!(msg.contains("Not expects 1 arguments, got 2"), "{}", msg);
+
+    let msg = first_verification_error(Node::new(OpCode::ArraySet, 3).with_args(&[1, 2]));
+    assert!(msg.contains("ArraySet expects 3 arguments, got 2"), "{}", msg);
+
+    let msg = first_verification_error(Node::new(OpCode::Print, 3));
+    assert!(msg.contains("Print expects 1 to 3 arguments, got 0"), "{}", msg);
+
+    let msg = first_verification_error(Node::new(OpCode::Seq, 3).with_args(&[1]));
+    assert!(msg.contains("Seq expects 2 to 3 arguments, got 1"), "{}", msg);
+}
+
+#[test]
+fn test_verifier_rejects_duplicate_result_ids() {
+    let mut program = Program::new();
+    let c1 = program.constants.add_int(1);
+    let c2 = program.constants.add_int(2);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c1]));
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c2]));
+    program.add_node(Node::new(OpCode::Add, 2).with_args(&[1, 1]));
+    program.set_entry_point(2);
+
+    let result = Verifier::new(program).verify_program();
+    assert!(!result.is_valid);
+    let error = result.errors.iter()
+        .find(|e| e.message.contains("Duplicate result id"))
+        .expect("duplicate id not reported");
+    assert_eq!(error.node_id, 1);
+}
+
+#[test]
+fn test_verifier_rejects_zero_result_id() {
+    let mut program = Program::new();
+    let c = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 0).with_args(&[c]));
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c]));
+    program.set_entry_point(1);
+
+    let result = Verifier::new(program).verify_program();
+    assert!(!result.is_valid);
+    assert!(result.errors.iter().any(|e| e.message.contains("result id 0")), "{:?}", result.errors);
+}
+
+#[test]
+fn test_verifier_rejects_missing_entry_point() {
+    let mut program = Program::new();
+    let c = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 10).with_args(&[c]));
+    program.add_node(Node::new(OpCode::Neg, 11).with_args(&[10]));
+
+    program.set_entry_point(99);
+    let result = Verifier::new(program.clone()).verify_program();
+    assert!(!result.is_valid);
+    assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+    assert_eq!(result.errors[0].node_id, 99);
+    assert!(result.errors[0].message.contains("Entry point 99 is not the result_id of any node"));
+
+    // Index 1 instead of result id 11
+    program.set_entry_point(1);
+    let result = Verifier::new(program).verify_program();
+    assert!(!result.is_valid);
+    assert!(result.errors[0].message.contains("node at index 1 has result_id 11"), "{}", result.errors[0].message);
+}
+
 #[test]
 fn test_verifier_invalid_arg_reference() {
     let mut program = Program::new();
@@ -291,6 +526,38 @@ fn test_verifier_invalid_arg_reference() {
     assert!(result.errors[0].message.contains("Invalid argument reference"));
 }
 
+#[test]
+fn test_verifier_detects_cycle() {
+    let mut program = Program::new();
+    let c1 = program.constants.add_int(1);
+    
+    // Node 3 reads node 4 and node 4 reads node 3
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c1]));
+    program.add_node(Node::new(OpCode::Add, 2).with_args(&[1, 3]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 4]));
+    program.add_node(Node::new(OpCode::Mul, 4).with_args(&[3, 1]));
+    program.set_entry_point(2);
+    
+    let verifier = Verifier::new(program);
+    assert_eq!(verifier.detect_cycles(), Err(vec![3, 4]));
+    
+    let result = verifier.verify_program();
+    assert!(!result.is_valid);
+    assert!(result.errors.iter().any(|e| e.message == "Cyclic dependency: 3 -> 4 -> 3"));
+}
+
+#[test]
+fn test_verifier_accepts_recursive_function() {
+    // A function body calling its own DefineFunc is recursion, not a data cycle
+    let mut program = Program::new();
+    program.add_node(Node::new(OpCode::DefineFunc, 1).with_args(&[2, 0]));
+    program.add_node(Node::new(OpCode::Call, 2).with_args(&[1]));
+    program.add_node(Node::new(OpCode::Call, 3).with_args(&[1]));
+    program.set_entry_point(3);
+    
+    assert_eq!(Verifier::new(program).detect_cycles(), Ok(()));
+}
+
 #[test]
 fn test_safety_analysis() {
     let mut program = Program::new();
@@ -373,4 +640,153 @@ fn test_proof_checker_invalid_reference() {
     let checker = ProofChecker::new();
     let result = checker.verify_proof(&proof);
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+fn create_gate_sample_program() -> Program {
+    let mut program = Program::new();
+    let c10 = program.constants.add_int(10);
+    let c20 = program.constants.add_int(20);
+
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c10]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[c20]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+    program.header.chunk_count = 3;
+    program.metadata.traits.push(Trait {
+        name: "IsPure".to_string(),
+        preconditions: vec![],
+        postconditions: vec![],
+    });
+    program
+}
+
+#[test]
+fn test_gate_permissive_policy_passes() {
+    let policy = GatePolicy::from_toml_str(r#"
+        max_nodes = 100
+        max_file_size = 4096
+        required_traits = ["IsPure"]
+        forbidden_opcodes = ["ExternalCall"]
+        require_verification = true
+        require_type_check = true
+    "#).unwrap();
+
+    let program = create_gate_sample_program();
+    let report = GateEvaluator::new(policy).evaluate(&program);
+
+    assert!(report.passed, "unexpected failures: {:?}", report.failures().collect::<Vec<_>>());
+    assert_eq!(report.rules.len(), 6);
+}
+
+#[test]
+fn test_gate_strict_policy_fails() {
+    let policy = GatePolicy::from_toml_str(r#"
+        max_nodes = 2
+        max_file_size = 64
+        required_traits = ["IsSorted"]
+        require_signature = true
+    "#).unwrap();
+
+    let program = create_gate_sample_program();
+    let report = GateEvaluator::new(policy).evaluate(&program);
+
+    assert!(!report.passed);
+    let failed: Vec<&str> = report.failures().map(|r| r.rule.as_str()).collect();
+    assert_eq!(failed, vec!["max_nodes", "max_file_size", "required_trait:IsSorted", "signature"]);
+}
+
+#[test]
+fn test_gate_forbidden_opcode() {
+    let policy = GatePolicy::from_toml_str(r#"forbidden_opcodes = ["ExternalCall"]"#).unwrap();
+
+    let mut program = create_gate_sample_program();
+    program.add_node(Node::new(OpCode::ExternalCall, 4).with_args(&[3]));
+    let report = GateEvaluator::new(policy).evaluate(&program);
+
+    assert!(!report.passed);
+    assert_eq!(report.rules[0].rule, "forbidden_opcode:ExternalCall");
+    assert!(report.rules[0].message.contains("node(s) 4"));
+}
+
+#[test]
+fn test_gate_policy_rejects_unknown_names() {
+    assert!(GatePolicy::from_toml_str(r#"forbidden_opcodes = ["Teleport"]"#).is_err());
+    assert!(GatePolicy::from_toml_str(r#"forbidden_capabilities = ["Telepathy"]"#).is_err());
+    assert!(GatePolicy::from_toml_str("max_nodez = 3").is_err());
+}
+
+#[test]
+fn test_proof_chunk_round_trip() {
+    let mut program = create_gate_sample_program();
+    // Proofs address nodes by position; the Add node is the third
+    let proof = ProofGenerator::new(program.clone())
+        .generate_proof(2, "IsPure")
+        .unwrap();
+    program.proofs.push(proof.clone());
+
+    let bytes = program.to_bytes().unwrap();
+    assert_eq!(bytes.len(), program.serialized_size());
+
+    let loaded = DERDeserializer::new(std::io::Cursor::new(bytes)).read_program().unwrap();
+    assert_eq!({ loaded.header.chunk_count }, 4);
+    assert_eq!(loaded.nodes.len(), 3);
+    assert_eq!(loaded.proofs.len(), 1);
+
+    let reloaded = &loaded.proofs[0];
+    assert_eq!(reloaded.theorem, proof.theorem);
+    assert_eq!(reloaded.trait_kind, TraitKind::IsPure);
+    assert_eq!(reloaded.steps.len(), proof.steps.len());
+    assert!(ProofChecker::new().verify_proof(reloaded).unwrap());
+}
+
+#[test]
+fn test_deterministic_proof_rejects_random_dependency() {
+    let mut program = Program::new();
+    let c1 = program.constants.add_int(1);
+    program.add_node(Node::new(OpCode::ConstInt, 1).with_args(&[c1]));
+    program.add_node(Node::new(OpCode::Add, 2).with_args(&[1, 1]));
+    program.add_node(Node::new(OpCode::Random, 3));
+    program.add_node(Node::new(OpCode::Add, 4).with_args(&[2, 3]));
+
+    let generator = ProofGenerator::new(program.clone());
+    assert!(generator.generate_proof(1, "IsDeterministic").is_ok());
+    let err = generator.generate_proof(3, "IsDeterministic").unwrap_err();
+    assert!(err.contains("non-deterministic node 3"), "{}", err);
+
+    let safety = Verifier::new(program).verify_safety();
+    assert!(!safety.deterministic);
+}
+
+#[test]
+fn test_verifier_rejects_add_of_string_and_int() {
+    let mut program = Program::new();
+    let text = program.constants.add_string("ten".to_string());
+    let ten = program.constants.add_int(10);
+    program.add_node(Node::new(OpCode::ConstString, 1).with_args(&[text]));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.add_node(Node::new(OpCode::Mul, 4).with_args(&[3, 2]));
+    program.set_entry_point(4);
+
+    let verifier = Verifier::new(program);
+    let type_errors = verifier.verify_types();
+    // Only the Add is reported; the Mul consuming it is not blamed again
+    assert_eq!(type_errors.len(), 1);
+    assert_eq!(type_errors[0].node_id, 3);
+    assert!(type_errors[0].message.contains("String"), "{}", type_errors[0].message);
+
+    let result = verifier.verify_program();
+    assert!(!result.is_valid);
+    assert!(result.errors.iter().any(|e| e.node_id == 3));
+}
+
+#[test]
+fn test_verifier_accepts_untyped_arithmetic_operands() {
+    let mut program = Program::new();
+    let ten = program.constants.add_int(10);
+    program.add_node(Node::new(OpCode::LoadArg, 1));
+    program.add_node(Node::new(OpCode::ConstInt, 2).with_args(&[ten]));
+    program.add_node(Node::new(OpCode::Add, 3).with_args(&[1, 2]));
+    program.set_entry_point(3);
+
+    assert!(Verifier::new(program).verify_types().is_empty());
+}
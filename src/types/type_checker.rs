@@ -1,11 +1,21 @@
-use crate::core::{Program, Node, OpCode};
+use crate::core::{Program, Node, OpCode, CAST_TARGETS};
 use crate::types::type_system::*;
 use crate::runtime::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub struct TypeChecker {
     env: TypeEnvironment,
     node_types: HashMap<u32, Type>,
+    errors: Vec<TypeError>,
+    /// Nodes being typed right now; meeting one again means a cycle
+    in_progress: HashSet<u32>,
+}
+
+/// A node whose operands don't fit its opcode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub node_id: u32,
+    pub message: String,
 }
 
 impl TypeChecker {
@@ -16,13 +26,15 @@ impl TypeChecker {
         TypeChecker {
             env,
             node_types: HashMap::new(),
+            errors: Vec::new(),
+            in_progress: HashSet::new(),
         }
     }
     
     pub fn check_program(&mut self, program: &Program) -> Result<(), String> {
         // Type check each node
-        for node in &program.nodes {
-            self.check_node(node, program)?;
+        if let Some(error) = self.check_nodes(program).first() {
+            return Err(error.message.clone());
         }
         
         // Verify entry point exists
@@ -32,6 +44,39 @@ impl TypeChecker {
         Ok(())
     }
     
+    /// Type every node, collecting one error per ill-typed node. A node that
+    /// fails is treated as `Any` from then on so its consumers aren't
+    /// reported for the same mistake.
+    pub fn check_nodes(&mut self, program: &Program) -> &[TypeError] {
+        for node in &program.nodes {
+            self.type_node(node, program);
+        }
+        &self.errors
+    }
+    
+    fn type_node(&mut self, node: &Node, program: &Program) -> Type {
+        // Cycles are the verifier's to report; type the back edge as Any
+        if !self.in_progress.insert(node.result_id) {
+            return Type::Any;
+        }
+        let checked = self.check_node(node, program);
+        self.in_progress.remove(&node.result_id);
+        
+        match checked {
+            Ok(ty) => ty,
+            Err(message) => {
+                self.errors.push(TypeError { node_id: node.result_id, message });
+                self.node_types.insert(node.result_id, Type::Any);
+                Type::Any
+            }
+        }
+    }
+    
+    /// Type inferred for a node by the last `check_program`.
+    pub fn node_type(&self, result_id: u32) -> Option<&Type> {
+        self.node_types.get(&result_id)
+    }
+    
     fn check_node(&mut self, node: &Node, program: &Program) -> Result<Type, String> {
         // Check if already typed
         if let Some(ty) = self.node_types.get(&node.result_id) {
@@ -51,23 +96,44 @@ impl TypeChecker {
             Ok(OpCode::ConstBool) => {
                 Type::Bool
             }
-            Ok(OpCode::Add) | Ok(OpCode::Sub) | Ok(OpCode::Mul) | Ok(OpCode::Div) => {
+            Ok(OpCode::Add) | Ok(OpCode::Sub) | Ok(OpCode::Mul) | Ok(OpCode::Div) | Ok(OpCode::Mod) |
+            Ok(OpCode::Min) | Ok(OpCode::Max) => {
                 // Arithmetic operations preserve numeric type
                 let left_type = self.get_arg_type(node, 0, program)?;
                 let right_type = self.get_arg_type(node, 1, program)?;
                 
+                let numeric = |ty: &Type| ty.is_compatible_with(&Type::Float);
                 match (&left_type, &right_type) {
                     (Type::Int, Type::Int) => Type::Int,
-                    (Type::Float, _) | (_, Type::Float) => Type::Float,
+                    (Type::Float, other) | (other, Type::Float) if numeric(other) => Type::Float,
+                    _ if numeric(&left_type) && numeric(&right_type) => Type::Any,
                     _ => return Err(format!("Type error: cannot apply arithmetic to {:?} and {:?}", left_type, right_type)),
                 }
             }
             Ok(OpCode::Eq) | Ok(OpCode::Ne) | Ok(OpCode::Lt) | Ok(OpCode::Le) | Ok(OpCode::Gt) | Ok(OpCode::Ge) => {
                 Type::Bool
             }
-            Ok(OpCode::Print) => {
+            Ok(OpCode::Print) | Ok(OpCode::FileWrite) | Ok(OpCode::Assert) => {
                 Type::Nil
             }
+            Ok(OpCode::Sleep) => {
+                if node.arg_count == 2 { Type::AsyncHandle(Box::new(Type::Nil)) } else { Type::Nil }
+            }
+            Ok(OpCode::HttpGet) => {
+                Type::Array(Box::new(Type::Union(vec![Type::Int, Type::String])))
+            }
+            Ok(OpCode::Random) => {
+                if node.arg_count == 2 { Type::Int } else { Type::Float }
+            }
+            Ok(OpCode::TypeOf) | Ok(OpCode::StrConcat) | Ok(OpCode::StrSlice) | Ok(OpCode::Format) |
+            Ok(OpCode::FileRead) => {
+                Type::String
+            }
+            Ok(OpCode::StrLen) | Ok(OpCode::ArrayLen) | Ok(OpCode::Len) | Ok(OpCode::Now) | Ok(OpCode::ArgCount) |
+            Ok(OpCode::BitAnd) | Ok(OpCode::BitOr) | Ok(OpCode::BitXor) | Ok(OpCode::BitNot) |
+            Ok(OpCode::Shl) | Ok(OpCode::Shr) => {
+                Type::Int
+            }
             Ok(OpCode::CreateArray) => {
                 // Infer array element type from first element
                 if node.arg_count > 0 {
@@ -77,10 +143,36 @@ impl TypeChecker {
                     Type::Array(Box::new(Type::Any))
                 }
             }
+            Ok(OpCode::ArrayPush) | Ok(OpCode::ArrayPop) | Ok(OpCode::ArraySlice) |
+            Ok(OpCode::ArrayConcat) | Ok(OpCode::MapDelete) => {
+                self.get_arg_type(node, 0, program)?
+            }
+            Ok(OpCode::Cast) => {
+                self.cast_result_type(node, program)
+            }
+            Ok(OpCode::Sqrt) | Ok(OpCode::ParseFloat) => {
+                Type::Float
+            }
+            Ok(OpCode::ParseInt) => {
+                Type::Int
+            }
+            Ok(OpCode::Abs) | Ok(OpCode::Floor) | Ok(OpCode::Ceil) | Ok(OpCode::Neg) => {
+                self.get_arg_type(node, 0, program)?
+            }
+            Ok(OpCode::MapContains) => {
+                Type::Bool
+            }
+            Ok(OpCode::Concat) => {
+                self.get_arg_type(node, 0, program)?
+            }
+            Ok(OpCode::Seq) if node.arg_count > 0 => {
+                self.get_arg_type(node, node.arg_count as usize - 1, program)?
+            }
             Ok(OpCode::ArrayGet) => {
                 let array_type = self.get_arg_type(node, 0, program)?;
                 match array_type {
                     Type::Array(elem_type) => *elem_type,
+                    Type::Any => Type::Any,
                     _ => return Err("Type error: ArrayGet requires array type".to_string()),
                 }
             }
@@ -91,6 +183,28 @@ impl TypeChecker {
         Ok(node_type)
     }
     
+    /// Cast produces the type its target names. The target is only known
+    /// statically when it is a constant; otherwise the result is Any.
+    fn cast_result_type(&self, node: &Node, program: &Program) -> Type {
+        let target_node = program.nodes.iter().find(|n| n.result_id == node.args[1]);
+        let target = target_node.and_then(|target| match OpCode::try_from(target.opcode) {
+            Ok(OpCode::ConstString) => program.constants.get_string(target.args[0]).cloned(),
+            Ok(OpCode::ConstInt) => program.constants.get_int(target.args[0])
+                .and_then(|code| usize::try_from(code).ok())
+                .and_then(|i| CAST_TARGETS.get(i))
+                .map(|name| name.to_string()),
+            _ => None,
+        });
+
+        match target.as_deref() {
+            Some("int") => Type::Int,
+            Some("float") => Type::Float,
+            Some("string") => Type::String,
+            Some("bool") => Type::Bool,
+            _ => Type::Any,
+        }
+    }
+    
     fn get_arg_type(&mut self, node: &Node, arg_idx: usize, program: &Program) -> Result<Type, String> {
         if arg_idx >= node.arg_count as usize {
             return Err("Invalid argument index".to_string());
@@ -106,6 +220,6 @@ impl TypeChecker {
             .find(|n| n.result_id == arg_id)
             .ok_or(format!("Node {} not found", arg_id))?;
         
-        self.check_node(arg_node, program)
+        Ok(self.type_node(arg_node, program))
     }
 }
\ No newline at end of file
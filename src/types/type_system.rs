@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt;
+use crate::runtime::Value;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Type {
@@ -127,6 +128,31 @@ impl Type {
         }
     }
     
+    /// Type of a runtime value. Collections take the common type of their
+    /// elements; empty ones have `Any` elements.
+    pub fn of_value(value: &Value) -> Type {
+        fn common(types: impl Iterator<Item = Type>) -> Type {
+            types.reduce(|a, b| a.common_type(&b).unwrap_or(Type::Any)).unwrap_or(Type::Any)
+        }
+
+        match value {
+            Value::Nil => Type::Nil,
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::String(_) => Type::String,
+            Value::Array(items) => Type::Array(Box::new(common(items.iter().map(Type::of_value)))),
+            Value::Map(map) => Type::Map(
+                Box::new(common(map.keys().map(|k| Type::of_value(&k.to_value())))),
+                Box::new(common(map.values().map(Type::of_value))),
+            ),
+            Value::Function(func) => Type::Function(vec![Type::Any; func.arity], Box::new(Type::Any)),
+            Value::NodeRef(_) => Type::NodeRef,
+            Value::MemoryRef(_) => Type::MemoryRef(Box::new(Type::Any)),
+            Value::AsyncHandle(_) => Type::AsyncHandle(Box::new(Type::Any)),
+        }
+    }
+    
     pub fn common_type(&self, other: &Type) -> Option<Type> {
         match (self, other) {
             // Same type
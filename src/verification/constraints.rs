@@ -1,4 +1,5 @@
 use crate::runtime::Value;
+use crate::types::Type;
 use crate::verification::traits::*;
 use std::collections::HashMap;
 
@@ -140,6 +141,36 @@ impl ConstraintChecker {
                 }
             }
             
+            ConstraintExpression::NotEqual(left, right) => {
+                let left_val = self.values.get(left)
+                    .ok_or(format!("{} not found", left))?;
+                let right_val = self.values.get(right)
+                    .ok_or(format!("{} not found", right))?;
+                
+                if left_val != right_val {
+                    Ok(())
+                } else {
+                    Err(format!("{} == {}", left, right))
+                }
+            }
+            
+            ConstraintExpression::Unique(var_names) => {
+                self.check_unique(var_names)
+            }
+            
+            ConstraintExpression::TypeCompatible(left, right) => {
+                let left_type = Type::of_value(self.values.get(left)
+                    .ok_or(format!("{} not found", left))?);
+                let right_type = Type::of_value(self.values.get(right)
+                    .ok_or(format!("{} not found", right))?);
+                
+                if left_type.is_compatible_with(&right_type) {
+                    Ok(())
+                } else {
+                    Err(format!("{} ({}) is not compatible with {} ({})", left, left_type, right, right_type))
+                }
+            }
+            
             ConstraintExpression::ArrayLength(var_name, length_constraint) => {
                 self.check_array_length(var_name, length_constraint)
             }
@@ -148,6 +179,17 @@ impl ConstraintChecker {
                 self.check_array_sorted(var_name, order)
             }
             
+            ConstraintExpression::ArrayContains(var_name, element) => {
+                match self.values.get(var_name) {
+                    Some(Value::Array(arr)) if arr.contains(element) => Ok(()),
+                    Some(Value::Array(_)) => {
+                        Err(format!("{} does not contain {}", var_name, element.to_string()))
+                    }
+                    Some(_) => Err(format!("{} is not an array", var_name)),
+                    None => Err(format!("{} not found", var_name)),
+                }
+            }
+            
             ConstraintExpression::All(constraints) => {
                 for constraint in constraints {
                     self.check_constraint(constraint)?;
@@ -170,11 +212,24 @@ impl ConstraintChecker {
                     Err(_) => Ok(()),
                 }
             }
-            
-            _ => Err("Constraint not implemented".to_string()),
         }
     }
     
+    fn check_unique(&self, var_names: &[String]) -> Result<(), String> {
+        let mut seen: Vec<(&String, &Value)> = Vec::new();
+        
+        for name in var_names {
+            let value = self.values.get(name)
+                .ok_or(format!("{} not found", name))?;
+            if let Some((other, _)) = seen.iter().find(|(_, v)| *v == value) {
+                return Err(format!("{} and {} hold the same value", other, name));
+            }
+            seen.push((name, value));
+        }
+        
+        Ok(())
+    }
+    
     fn check_type_constraint(&self, var_name: &str, expected_type: &TypeConstraint) -> Result<(), String> {
         let value = self.values.get(var_name)
             .ok_or(format!("{} not found", var_name))?;
@@ -185,11 +240,35 @@ impl ConstraintChecker {
             (Value::Bool(_), TypeConstraint::Boolean) => Ok(()),
             (Value::String(_), TypeConstraint::String) => Ok(()),
             (Value::Array(_), TypeConstraint::Array(_)) => Ok(()), // TODO: check element types
-            (Value::Map(_), TypeConstraint::Map(_, _)) => Ok(()), // TODO: check key/value types
+            (Value::Map(_), TypeConstraint::Map(_, _)) => {
+                if Self::value_has_type(value, expected_type) {
+                    Ok(())
+                } else {
+                    Err(format!("{} has map entries of the wrong type", var_name))
+                }
+            }
             _ => Err(format!("{} has wrong type", var_name)),
         }
     }
     
+    fn value_has_type(value: &Value, expected_type: &TypeConstraint) -> bool {
+        match (value, expected_type) {
+            (_, TypeConstraint::Any) => true,
+            (_, TypeConstraint::Union(types)) => types.iter().any(|t| Self::value_has_type(value, t)),
+            (Value::Int(_), TypeConstraint::Integer) => true,
+            (Value::Float(_), TypeConstraint::Float) => true,
+            (Value::Bool(_), TypeConstraint::Boolean) => true,
+            (Value::String(_), TypeConstraint::String) => true,
+            (Value::Array(items), TypeConstraint::Array(item_type)) => {
+                items.iter().all(|item| Self::value_has_type(item, item_type))
+            }
+            (Value::Map(map), TypeConstraint::Map(key_type, value_type)) => map.iter().all(|(k, v)| {
+                Self::value_has_type(&k.to_value(), key_type) && Self::value_has_type(v, value_type)
+            }),
+            _ => false,
+        }
+    }
+    
     fn check_range_constraint(&self, var_name: &str, range: &RangeConstraint) -> Result<(), String> {
         let value = self.values.get(var_name)
             .ok_or(format!("{} not found", var_name))?;
@@ -305,19 +384,16 @@ impl ConstraintChecker {
                     let prev = &arr[i - 1];
                     let curr = &arr[i];
                     
+                    // Mixed Int/Float pairs are promoted to Float, as the
+                    // executor's numeric comparisons do
                     let cmp_result = match (prev, curr) {
-                        (Value::Int(a), Value::Int(b)) => a.cmp(b),
-                        (Value::Float(a), Value::Float(b)) => {
-                            if a < b {
-                                std::cmp::Ordering::Less
-                            } else if a > b {
-                                std::cmp::Ordering::Greater
-                            } else {
-                                std::cmp::Ordering::Equal
-                            }
-                        }
-                        _ => return Err("Cannot compare array elements".to_string()),
-                    };
+                        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+                        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                        (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+                        (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+                        _ => None,
+                    }.ok_or("Cannot compare array elements")?;
                     
                     match order {
                         SortOrder::Ascending => {
@@ -0,0 +1,197 @@
+use crate::core::{Capability, OpCode, Program};
+use crate::types::TypeChecker;
+use crate::verification::{ProofChecker, Verifier};
+use serde::{Deserialize, Serialize};
+
+/// Acceptance policy for generated programs, usually loaded from a TOML file.
+///
+/// Every field is optional; rules that are not configured are not evaluated.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatePolicy {
+    pub max_nodes: Option<usize>,
+    pub max_file_size: Option<usize>,
+    pub required_traits: Vec<String>,
+    pub forbidden_opcodes: Vec<String>,
+    pub forbidden_capabilities: Vec<String>,
+    pub require_verification: bool,
+    pub require_type_check: bool,
+    pub require_signature: bool,
+}
+
+impl GatePolicy {
+    pub fn from_toml_str(source: &str) -> Result<Self, String> {
+        let policy: GatePolicy = toml::from_str(source)
+            .map_err(|e| format!("Invalid gate policy: {}", e))?;
+
+        for name in &policy.forbidden_opcodes {
+            if opcode_from_name(name).is_none() {
+                return Err(format!("Invalid gate policy: unknown opcode '{}'", name));
+            }
+        }
+        for name in &policy.forbidden_capabilities {
+            if Capability::from_name(name).is_none() {
+                return Err(format!("Invalid gate policy: unknown capability '{}'", name));
+            }
+        }
+
+        Ok(policy)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read policy {}: {}", path, e))?;
+        Self::from_toml_str(&source)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GateRuleResult {
+    pub rule: String,
+    pub passed: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GateReport {
+    pub passed: bool,
+    pub rules: Vec<GateRuleResult>,
+}
+
+impl GateReport {
+    pub fn failures(&self) -> impl Iterator<Item = &GateRuleResult> {
+        self.rules.iter().filter(|r| !r.passed)
+    }
+}
+
+/// Decides whether a program is acceptable to ship under a `GatePolicy`,
+/// producing one report entry per configured rule.
+pub struct GateEvaluator {
+    policy: GatePolicy,
+}
+
+impl GateEvaluator {
+    pub fn new(policy: GatePolicy) -> Self {
+        GateEvaluator { policy }
+    }
+
+    /// Evaluate a program, using its exact serialized size as the file size.
+    pub fn evaluate(&self, program: &Program) -> GateReport {
+        self.evaluate_with_size(program, program.serialized_size())
+    }
+
+    /// Evaluate a program loaded from a file of `file_size` bytes.
+    pub fn evaluate_with_size(&self, program: &Program, file_size: usize) -> GateReport {
+        let policy = &self.policy;
+        let mut rules = Vec::new();
+
+        if let Some(max) = policy.max_nodes {
+            let count = program.nodes.len();
+            rules.push(rule(
+                "max_nodes",
+                count <= max,
+                format!("{} nodes (limit {})", count, max),
+            ));
+        }
+
+        if let Some(max) = policy.max_file_size {
+            rules.push(rule(
+                "max_file_size",
+                file_size <= max,
+                format!("{} bytes (limit {})", file_size, max),
+            ));
+        }
+
+        for name in &policy.forbidden_opcodes {
+            let opcode = opcode_from_name(name).map(|op| op as u16);
+            let offending: Vec<String> = program.nodes.iter()
+                .filter(|n| Some(n.opcode) == opcode)
+                .map(|n| n.result_id.to_string())
+                .collect();
+            let message = if offending.is_empty() {
+                format!("no {} nodes", name)
+            } else {
+                format!("{} used by node(s) {}", name, offending.join(", "))
+            };
+            rules.push(rule(&format!("forbidden_opcode:{}", name), offending.is_empty(), message));
+        }
+
+        for name in &policy.forbidden_capabilities {
+            let required = Capability::from_name(name)
+                .map(|cap| program.metadata.required_capabilities.contains(&cap))
+                .unwrap_or(false);
+            let message = if required {
+                format!("program requires {}", name)
+            } else {
+                format!("{} not required", name)
+            };
+            rules.push(rule(&format!("forbidden_capability:{}", name), !required, message));
+        }
+
+        for name in &policy.required_traits {
+            let declared = program.metadata.traits.iter().any(|t| &t.name == name);
+            let entry_index = program.node_index(program.metadata.entry_point);
+            let (passed, message) = if !declared {
+                (false, format!("trait '{}' is not declared", name))
+            } else if let Some(index) = entry_index {
+                match ProofChecker::new().check_trait_satisfaction(program, index as u32, name) {
+                    Ok(true) => (true, format!("trait '{}' proved", name)),
+                    Ok(false) => (false, format!("proof of '{}' was rejected", name)),
+                    Err(e) => (false, format!("could not prove '{}': {}", name, e)),
+                }
+            } else {
+                (false, "entry point node not found".to_string())
+            };
+            rules.push(rule(&format!("required_trait:{}", name), passed, message));
+        }
+
+        if policy.require_verification {
+            let result = Verifier::new(program.clone()).verify_program();
+            let message = if result.is_valid {
+                "static verification clean".to_string()
+            } else {
+                result.errors.iter()
+                    .map(|e| format!("node {}: {}", e.node_id, e.message))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            rules.push(rule("verification", result.is_valid, message));
+        }
+
+        if policy.require_type_check {
+            let (passed, message) = match TypeChecker::new().check_program(program) {
+                Ok(()) => (true, "type check clean".to_string()),
+                Err(e) => (false, e),
+            };
+            rules.push(rule("type_check", passed, message));
+        }
+
+        if policy.require_signature {
+            // The format has no signature chunk yet, so this rule fails closed.
+            rules.push(rule(
+                "signature",
+                false,
+                "program is not signed".to_string(),
+            ));
+        }
+
+        GateReport {
+            passed: rules.iter().all(|r| r.passed),
+            rules,
+        }
+    }
+}
+
+fn rule(name: &str, passed: bool, message: String) -> GateRuleResult {
+    GateRuleResult {
+        rule: name.to_string(),
+        passed,
+        message,
+    }
+}
+
+fn opcode_from_name(name: &str) -> Option<OpCode> {
+    (0..=u16::MAX)
+        .filter_map(|raw| OpCode::try_from(raw).ok())
+        .find(|op| format!("{:?}", op) == name)
+}
@@ -2,8 +2,10 @@ pub mod proof;
 pub mod verifier;
 pub mod traits;
 pub mod constraints;
+pub mod gate;
 
 pub use proof::*;
 pub use verifier::*;
 pub use traits::*;
-pub use constraints::*;
\ No newline at end of file
+pub use constraints::*;
+pub use gate::*;
\ No newline at end of file
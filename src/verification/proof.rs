@@ -1,8 +1,9 @@
 use crate::core::{Program, Node, OpCode};
 use crate::verification::traits::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proof {
     pub theorem: String,
     pub trait_kind: TraitKind,
@@ -11,13 +12,13 @@ pub struct Proof {
     pub conclusion: Conclusion,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Assumption {
     pub description: String,
     pub condition: ConditionExpression,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofStep {
     pub step_number: usize,
     pub description: String,
@@ -25,7 +26,7 @@ pub struct ProofStep {
     pub derived_fact: ConditionExpression,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Justification {
     Assumption(usize),
     Definition(String),
@@ -37,13 +38,13 @@ pub enum Justification {
     DirectComputation,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InductionProof {
     pub base_case: Box<ProofStep>,
     pub inductive_step: Box<ProofStep>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conclusion {
     pub statement: String,
     pub expression: ConditionExpression,
@@ -91,7 +92,7 @@ impl ProofGenerator {
         
         // Check opcode purity
         let is_pure = match OpCode::try_from(node.opcode) {
-            Ok(opcode) => self.is_opcode_pure(&opcode),
+            Ok(opcode) => Self::is_opcode_pure(&opcode),
             Err(_) => false,
         };
         
@@ -173,6 +174,12 @@ impl ProofGenerator {
     }
     
     fn prove_is_deterministic(&self, node: &Node, trait_def: &TraitDefinition) -> Result<Proof, String> {
+        if let Some(source) = self.find_nondeterministic_source(node) {
+            return Err(format!(
+                "Node {} depends on non-deterministic node {}", node.result_id, source
+            ));
+        }
+
         let proof = Proof {
             theorem: format!("Node {} is deterministic", node.result_id),
             trait_kind: TraitKind::IsDeterministic,
@@ -215,19 +222,52 @@ impl ProofGenerator {
         Ok(proof)
     }
     
-    fn is_opcode_pure(&self, opcode: &OpCode) -> bool {
+    /// Result id of the first node reachable from `node` (itself included)
+    /// whose output can differ between runs on the same inputs.
+    fn find_nondeterministic_source(&self, node: &Node) -> Option<u32> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![*node];
+        while let Some(current) = stack.pop() {
+            if !visited.insert(current.result_id) {
+                continue;
+            }
+            if matches!(OpCode::try_from(current.opcode), Ok(OpCode::Random) | Ok(OpCode::Now) | Ok(OpCode::GetEnv) | Ok(OpCode::HttpGet)) {
+                return Some(current.result_id);
+            }
+            for dep in current.dependencies() {
+                if let Some(dep_node) = self.program.nodes.iter().find(|n| n.result_id == dep) {
+                    stack.push(*dep_node);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether an opcode's result depends only on its arguments and running
+    /// it has no effects.
+    pub fn is_opcode_pure(opcode: &OpCode) -> bool {
         match opcode {
             // Pure operations
             OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div | OpCode::Mod |
+            OpCode::BitAnd | OpCode::BitOr | OpCode::BitXor | OpCode::BitNot | OpCode::Shl | OpCode::Shr |
+            OpCode::Sqrt | OpCode::Pow | OpCode::Abs | OpCode::Floor | OpCode::Ceil | OpCode::Min | OpCode::Max | OpCode::Neg |
             OpCode::Eq | OpCode::Ne | OpCode::Lt | OpCode::Le | OpCode::Gt | OpCode::Ge |
             OpCode::And | OpCode::Or | OpCode::Not | OpCode::Xor |
             OpCode::ConstInt | OpCode::ConstFloat | OpCode::ConstString | OpCode::ConstBool |
-            OpCode::CreateArray | OpCode::CreateMap | OpCode::ArrayGet | OpCode::MapGet |
-            OpCode::DefineFunc | OpCode::CreateClosure => true,
+            OpCode::CreateArray | OpCode::CreateMap | OpCode::ArrayGet | OpCode::MapGet | OpCode::ArrayLen |
+            OpCode::ArrayPush | OpCode::ArrayPop | OpCode::ArraySlice | OpCode::ArrayConcat |
+            OpCode::MapContains | OpCode::MapKeys | OpCode::MapValues |
+            OpCode::DefineFunc | OpCode::CreateClosure | OpCode::Cast | OpCode::TypeOf |
+            OpCode::StrConcat | OpCode::StrLen | OpCode::StrSlice | OpCode::Concat | OpCode::Len |
+            OpCode::ParseInt | OpCode::ParseFloat | OpCode::Format | OpCode::PtrAdd => true,
+            
+            // No effects of its own; impure steps show up in its arguments
+            OpCode::Seq => true,
             
             // Impure operations
-            OpCode::Print | OpCode::Read | OpCode::ArraySet | OpCode::MapSet |
-            OpCode::Store | OpCode::Free | OpCode::ExternalCall => false,
+            OpCode::Print | OpCode::Read | OpCode::ArraySet | OpCode::MapSet | OpCode::MapDelete |
+            OpCode::Store | OpCode::Free | OpCode::ExternalCall | OpCode::Random | OpCode::Now | OpCode::Sleep |
+            OpCode::FileRead | OpCode::FileWrite | OpCode::GetEnv | OpCode::HttpGet => false,
             
             _ => false,
         }
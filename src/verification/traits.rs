@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TraitKind {
     // Data properties
     IsSorted,
@@ -30,7 +31,7 @@ pub enum TraitKind {
     Custom(String),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TypeConstraint {
     Integer,
     Float,
@@ -43,7 +44,7 @@ pub enum TypeConstraint {
     Any,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ComplexityBound {
     Constant,
     Logarithmic,
@@ -68,7 +69,7 @@ pub struct Condition {
     pub expression: ConditionExpression,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConditionExpression {
     // Comparison
     Equal(Box<ConditionExpression>, Box<ConditionExpression>),
@@ -102,7 +103,7 @@ pub enum ConditionExpression {
     Apply(Box<ConditionExpression>, Vec<ConditionExpression>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConstantValue {
     Integer(i64),
     Float(f64),
@@ -234,6 +235,20 @@ impl TraitRegistry {
                 }
             ],
         });
+        
+        // IsDeterministic trait
+        self.register_trait(TraitDefinition {
+            name: "IsDeterministic".to_string(),
+            kind: TraitKind::IsDeterministic,
+            preconditions: vec![],
+            postconditions: vec![
+                Condition {
+                    description: "Same inputs always produce same outputs".to_string(),
+                    expression: ConditionExpression::Constant(ConstantValue::Boolean(true)),
+                }
+            ],
+            invariants: vec![],
+        });
     }
     
     pub fn register_trait(&mut self, trait_def: TraitDefinition) {
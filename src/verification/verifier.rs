@@ -1,7 +1,8 @@
 use crate::core::{Program, Node, OpCode};
 use crate::runtime::{Executor, Value};
-use crate::verification::{ProofChecker, ConstraintChecker, Constraint, ConstraintExpression, ConstraintSeverity};
-use std::collections::HashMap;
+use crate::verification::{ProofChecker, ConstraintChecker, ConstraintSeverity};
+use crate::types::{TypeChecker, TypeError};
+use std::collections::HashSet;
 
 pub struct Verifier {
     program: Program,
@@ -35,6 +36,43 @@ impl Verifier {
             }
         }
         
+        let entry_error = self.verify_entry_point().err();
+        if let Some(e) = &entry_error {
+            result.errors.push(VerificationError {
+                node_id: self.program.metadata.entry_point,
+                message: e.clone(),
+            });
+            result.is_valid = false;
+        }
+        
+        let id_errors = self.verify_result_ids();
+        if !id_errors.is_empty() {
+            result.errors.extend(id_errors);
+            result.is_valid = false;
+        }
+        
+        if let Err(cycle) = self.detect_cycles() {
+            let path: Vec<String> = cycle.iter().map(|id| id.to_string()).collect();
+            result.errors.push(VerificationError {
+                node_id: cycle[0],
+                message: format!("Cyclic dependency: {} -> {}", path.join(" -> "), cycle[0]),
+            });
+            result.is_valid = false;
+        }
+        
+        for error in self.verify_types() {
+            result.errors.push(VerificationError {
+                node_id: error.node_id,
+                message: error.message,
+            });
+            result.is_valid = false;
+        }
+        
+        // Trait proofs and constraint checks start from the entry point
+        if entry_error.is_some() {
+            return result;
+        }
+        
         // Verify program traits
         for trait_def in &self.program.metadata.traits {
             if let Err(e) = self.verify_trait(&trait_def.name) {
@@ -83,6 +121,13 @@ impl Verifier {
                     opcode, expected, node.arg_count
                 ));
             }
+        } else if let Some((min, max)) = self.get_arg_count_range(&opcode) {
+            if !(min..=max).contains(&node.arg_count) {
+                return Err(format!(
+                    "Opcode {:?} expects {} to {} arguments, got {}",
+                    opcode, min, max, node.arg_count
+                ));
+            }
         }
         
         // Verify argument references are valid
@@ -101,26 +146,102 @@ impl Verifier {
         Ok(())
     }
     
+    /// The entry point must be the result id of a node. A node's position in
+    /// `nodes` is a common mistake, so the error says when it looks like one.
+    pub fn verify_entry_point(&self) -> Result<(), String> {
+        let entry_point = self.program.metadata.entry_point;
+        if self.program.node_index(entry_point).is_some() {
+            return Ok(());
+        }
+        match self.program.nodes.get(entry_point as usize) {
+            Some(node) => Err(format!(
+                "Entry point {} is not the result_id of any node. It looks like a node index: \
+                 the node at index {} has result_id {}",
+                entry_point, entry_point, node.result_id
+            )),
+            None => Err(format!(
+                "Entry point {} is not the result_id of any node ({} nodes in the program)",
+                entry_point, self.program.nodes.len()
+            )),
+        }
+    }
+    
+    /// Every node needs its own non-zero result id: values are memoized and
+    /// nodes looked up by it, and 0 stands for a missing argument.
+    pub fn verify_result_ids(&self) -> Vec<VerificationError> {
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        for (index, node) in self.program.nodes.iter().enumerate() {
+            if node.result_id == 0 {
+                errors.push(VerificationError {
+                    node_id: 0,
+                    message: format!("Node at index {} uses result id 0, which is reserved for missing arguments", index),
+                });
+            } else if !seen.insert(node.result_id) {
+                errors.push(VerificationError {
+                    node_id: node.result_id,
+                    message: format!("Duplicate result id: {}", node.result_id),
+                });
+            }
+        }
+        errors
+    }
+    
+    /// Depth-first search over argument edges reachable from the entry point.
+    /// On failure returns the ids forming the cycle, in edge order.
+    pub fn detect_cycles(&self) -> Result<(), Vec<u32>> {
+        let entry_point = self.program.metadata.entry_point;
+        let mut finished = HashSet::new();
+        // (node id, its dependencies, next dependency to visit)
+        let mut path: Vec<(u32, Vec<u32>, usize)> = Vec::new();
+        if let Some(index) = self.program.node_index(entry_point) {
+            path.push((entry_point, self.program.nodes[index].dependencies(), 0));
+        }
+
+        while let Some((_, deps, next)) = path.last_mut() {
+            let dep = match deps.get(*next) {
+                Some(&dep) => dep,
+                None => {
+                    let (id, _, _) = path.pop().unwrap();
+                    finished.insert(id);
+                    continue;
+                }
+            };
+            *next += 1;
+
+            if finished.contains(&dep) {
+                continue;
+            }
+            if let Some(start) = path.iter().position(|(id, _, _)| *id == dep) {
+                return Err(path[start..].iter().map(|(id, _, _)| *id).collect());
+            }
+            // Dangling references are reported by verify_node
+            if let Some(index) = self.program.node_index(dep) {
+                path.push((dep, self.program.nodes[index].dependencies(), 0));
+            }
+        }
+
+        Ok(())
+    }
+    
     fn verify_trait(&self, trait_name: &str) -> Result<(), String> {
-        // Check if we can generate and verify a proof for this trait
+        // Check if we can generate and verify a proof for this trait.
+        // The entry point is a result id; proofs address nodes by position.
+        let entry_point = self.program.metadata.entry_point;
+        let entry_index = self.program.node_index(entry_point)
+            .ok_or(format!("Entry point node {} not found", entry_point))?;
         self.proof_checker.check_trait_satisfaction(
             &self.program,
-            self.program.metadata.entry_point,
+            entry_index as u32,
             trait_name
         )?;
         Ok(())
     }
     
     fn check_program_constraints(&self) -> Vec<crate::verification::constraints::ConstraintViolation> {
+        // Cycles are rejected structurally by detect_cycles
         let mut checker = ConstraintChecker::new();
         
-        // Add standard constraints
-        checker.add_constraint(Constraint {
-            name: "no_cycles".to_string(),
-            expression: ConstraintExpression::All(vec![]), // TODO: implement cycle detection
-            severity: ConstraintSeverity::Error,
-        });
-        
         // Run a test execution to get values
         let mut executor = Executor::new(self.program.clone());
         if let Ok(result) = executor.execute() {
@@ -136,28 +257,98 @@ impl Verifier {
             OpCode::Return => Some(1),
             OpCode::Call => None, // Variable args
             OpCode::Branch => Some(3),
+            OpCode::Loop => None, // Condition, body and an optional iteration bound
             
             OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div | OpCode::Mod => Some(2),
+            OpCode::BitAnd | OpCode::BitOr | OpCode::BitXor | OpCode::Shl | OpCode::Shr => Some(2),
+            OpCode::BitNot => Some(1),
+            OpCode::Pow | OpCode::Min | OpCode::Max => Some(2),
+            OpCode::Sqrt | OpCode::Abs | OpCode::Floor | OpCode::Ceil | OpCode::Neg => Some(1),
             OpCode::Eq | OpCode::Ne | OpCode::Lt | OpCode::Le | OpCode::Gt | OpCode::Ge => Some(2),
             OpCode::And | OpCode::Or | OpCode::Xor => Some(2),
             OpCode::Not => Some(1),
             
+            OpCode::Free => Some(1),
+            OpCode::LoadArg => None, // Index, plus an optional default
+            OpCode::LoadParam => Some(1),
+            OpCode::ArgCount => Some(0),
+            OpCode::Load => None, // Reference, plus an optional node to run first
+            OpCode::Store | OpCode::PtrAdd => Some(2),
+            OpCode::Alloc => None, // Size and an optional initial value
+            
             OpCode::ConstInt | OpCode::ConstFloat | OpCode::ConstString | OpCode::ConstBool => Some(1),
             
             OpCode::CreateArray => None, // Variable args
             OpCode::CreateMap => Some(0),
             OpCode::ArrayGet | OpCode::MapGet => Some(2),
-            OpCode::ArraySet | OpCode::MapSet => Some(3),
+            OpCode::ArrayLen | OpCode::ArrayPop => Some(1),
+            OpCode::ArrayPush | OpCode::ArrayConcat | OpCode::MapContains | OpCode::MapDelete => Some(2),
+            OpCode::MapKeys | OpCode::MapValues => Some(1),
+            OpCode::ArraySet | OpCode::MapSet | OpCode::ArraySlice => Some(3),
             
             OpCode::DefineFunc => Some(2),
             OpCode::CreateClosure => None, // Variable args
             
-            OpCode::Print => None, // Variable args
+            OpCode::Cast => Some(2),
+            OpCode::TypeOf => Some(1),
+            OpCode::StrConcat => Some(2),
+            OpCode::StrLen => Some(1),
+            OpCode::StrSlice => Some(3),
+            OpCode::Concat => Some(2),
+            OpCode::Len => Some(1),
+            OpCode::ParseInt | OpCode::ParseFloat => Some(1),
+            OpCode::Format => Some(2),
+            
+            OpCode::Print => None, // At least one value
+            OpCode::Random => None, // None, or an integer range
+            OpCode::Now => Some(0),
+            OpCode::Sleep => None, // Duration, plus an optional handle
+            OpCode::FileRead => Some(1),
+            OpCode::FileWrite => Some(2),
+            OpCode::GetEnv => Some(1),
+            OpCode::HttpGet => Some(1),
+            OpCode::Exit => Some(1),
+            OpCode::Try => Some(2),
+            OpCode::Throw => Some(1),
+            OpCode::Assert => None, // Condition, plus an optional message
+            OpCode::Seq => None, // Two or three steps
+            
+            OpCode::AsyncBegin => Some(0),
+            OpCode::AsyncAwait => Some(1),
+            OpCode::AsyncComplete => Some(2),
+            OpCode::AsyncTimeout => Some(2),
+            OpCode::AsyncJoin => None, // One to three handles
+            
+            OpCode::ExternalCall => None, // Name plus up to two arguments
             
             _ => None,
         }
     }
     
+    /// Bounds for the variable-arity opcodes that still have limits.
+    fn get_arg_count_range(&self, opcode: &OpCode) -> Option<(u8, u8)> {
+        match opcode {
+            OpCode::Loop => Some((2, 3)),
+            OpCode::Alloc => Some((1, 2)),
+            OpCode::Load => Some((1, 2)),
+            OpCode::LoadArg => Some((1, 2)),
+            OpCode::Print => Some((1, 3)),
+            OpCode::ExternalCall => Some((1, 3)),
+            OpCode::Random => Some((0, 2)),
+            OpCode::Sleep => Some((1, 2)),
+            OpCode::AsyncJoin => Some((1, 3)),
+            OpCode::Assert => Some((1, 2)),
+            OpCode::Seq => Some((2, 3)),
+            _ => None,
+        }
+    }
+    
+    /// Infer each node's type from the constants upward and report the
+    /// nodes whose operand types are incompatible with their opcode.
+    pub fn verify_types(&self) -> Vec<TypeError> {
+        TypeChecker::new().check_nodes(&self.program).to_vec()
+    }
+    
     pub fn verify_safety(&self) -> SafetyAnalysis {
         let mut analysis = SafetyAnalysis {
             has_unsafe_operations: false,
@@ -180,6 +371,31 @@ impl Verifier {
                     OpCode::Print | OpCode::Read => {
                         analysis.side_effects.push(format!("I/O operation at node {}", node.result_id));
                     }
+                    OpCode::Random => {
+                        analysis.deterministic = false;
+                        analysis.side_effects.push(format!("Random number at node {}", node.result_id));
+                    }
+                    OpCode::Now => {
+                        analysis.deterministic = false;
+                        analysis.side_effects.push(format!("Clock read at node {}", node.result_id));
+                    }
+                    OpCode::FileRead | OpCode::FileWrite => {
+                        analysis.side_effects.push(format!("File access at node {}", node.result_id));
+                    }
+                    OpCode::GetEnv => {
+                        analysis.deterministic = false;
+                        analysis.side_effects.push(format!("Environment read at node {}", node.result_id));
+                    }
+                    OpCode::HttpGet => {
+                        analysis.deterministic = false;
+                        analysis.side_effects.push(format!("Network request at node {}", node.result_id));
+                    }
+                    OpCode::Exit => {
+                        analysis.side_effects.push(format!("Process exit at node {}", node.result_id));
+                    }
+                    OpCode::Sleep => {
+                        analysis.side_effects.push(format!("Sleep at node {}", node.result_id));
+                    }
                     _ => {}
                 }
             }
@@ -1,4 +1,4 @@
-use crate::core::{Program, Node, OpCode};
+use crate::core::{Program, Node, NodeDescriber, OpCode};
 use std::collections::{HashMap, HashSet};
 
 pub struct GraphRenderer {
@@ -48,7 +48,7 @@ impl GraphRenderer {
                 .map(|op| format!("{:?}", op))
                 .unwrap_or_else(|_| format!("Unknown({})", node.opcode));
 
-            let label = self.get_node_label(node, &opcode_name);
+            let label = escape_dot_label(&self.get_node_label(node, &opcode_name));
             let color = self.get_node_color(&opcode_name);
 
             dot.push_str(&format!(
@@ -98,7 +98,7 @@ impl GraphRenderer {
                 .map(|op| format!("{:?}", op))
                 .unwrap_or_else(|_| format!("Unknown({})", node.opcode));
 
-            let label = self.get_node_label(node, &opcode_name);
+            let label = escape_mermaid_label(&self.get_node_label(node, &opcode_name));
             
             mermaid.push_str(&format!("    n{}[\"{}\"]\n", node.result_id, label));
         }
@@ -142,6 +142,61 @@ impl GraphRenderer {
         mermaid
     }
 
+    /// Standalone SVG drawing of `calculate_layout`, no Graphviz needed.
+    /// Edges run from the top of an argument to the bottom of its consumer.
+    pub fn render_to_svg(&self) -> String {
+        let layout = self.calculate_layout();
+        let mut svg = String::new();
+        svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"Arial\" font-size=\"11\">\n",
+            w = layout.width, h = layout.height
+        ));
+        svg.push_str("  <defs>\n");
+        svg.push_str("    <marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\">\n");
+        svg.push_str("      <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#555\"/>\n");
+        svg.push_str("    </marker>\n");
+        svg.push_str("  </defs>\n");
+
+        // Edges first so nodes are drawn over them
+        let positions: HashMap<u32, &GraphNode> = layout.nodes.iter().map(|n| (n.id, n)).collect();
+        for edge in &layout.edges {
+            if let (Some(from), Some(to)) = (positions.get(&edge.from), positions.get(&edge.to)) {
+                svg.push_str(&format!(
+                    "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#555\" marker-end=\"url(#arrow)\"><title>{}</title></line>\n",
+                    from.x + from.width / 2.0, from.y,
+                    to.x + to.width / 2.0, to.y + to.height,
+                    escape_xml(&edge.label)
+                ));
+            }
+        }
+
+        for node in &layout.nodes {
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"8\" ry=\"8\" fill=\"{}\" stroke=\"#888\"/>\n",
+                node.x, node.y, node.width, node.height, self.get_node_color(&node.opcode)
+            ));
+            let lines: Vec<&str> = node.label.lines().collect();
+            let line_height = 14.0;
+            let first_y = node.y + node.height / 2.0 - (lines.len() as f32 - 1.0) * line_height / 2.0;
+            svg.push_str(&format!(
+                "  <text x=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\">",
+                node.x + node.width / 2.0
+            ));
+            for (i, line) in lines.iter().enumerate() {
+                svg.push_str(&format!(
+                    "<tspan x=\"{}\" y=\"{}\">{}</tspan>",
+                    node.x + node.width / 2.0,
+                    first_y + i as f32 * line_height,
+                    escape_xml(line)
+                ));
+            }
+            svg.push_str("</text>\n");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
     pub fn calculate_layout(&self) -> GraphLayout {
         let mut layout = GraphLayout {
             nodes: Vec::new(),
@@ -157,7 +212,7 @@ impl GraphRenderer {
         // Group nodes by level
         let mut nodes_by_level: HashMap<usize, Vec<&Node>> = HashMap::new();
         for (node_id, level) in &levels {
-            if let Some(node) = self.program.nodes.get(*node_id as usize) {
+            if let Some(node) = self.find_node_by_result_id(*node_id) {
                 nodes_by_level.entry(*level).or_insert(Vec::new()).push(node);
             }
         }
@@ -216,32 +271,14 @@ impl GraphRenderer {
         self.program.nodes.iter().find(|n| n.result_id == result_id)
     }
 
+    /// Unescaped label text; each output format escapes it on the way out.
     fn get_node_label(&self, node: &Node, opcode_name: &str) -> String {
-        let mut label = format!("Node {}\\n{}", node.result_id, opcode_name);
+        let mut label = format!("Node {}\n{}", node.result_id, opcode_name);
 
         // Add constant values to the label
-        match OpCode::try_from(node.opcode) {
-            Ok(OpCode::ConstInt) => {
-                if let Some(val) = self.program.constants.get_int(node.args[0]) {
-                    label.push_str(&format!("\\nValue: {}", val));
-                }
-            }
-            Ok(OpCode::ConstFloat) => {
-                if let Some(val) = self.program.constants.get_float(node.args[0]) {
-                    label.push_str(&format!("\\nValue: {}", val));
-                }
-            }
-            Ok(OpCode::ConstString) => {
-                if let Some(val) = self.program.constants.get_string(node.args[0]) {
-                    label.push_str(&format!("\\nValue: \\\"{}\\\"", val));
-                }
-            }
-            Ok(OpCode::ConstBool) => {
-                if let Some(val) = self.program.constants.get_bool(node.args[0]) {
-                    label.push_str(&format!("\\nValue: {}", val));
-                }
-            }
-            _ => {}
+        let description = NodeDescriber::new(&self.program).describe(node);
+        if let Some(preview) = description.constant_preview {
+            label.push_str(&format!("\nValue: {}", preview));
         }
 
         label
@@ -250,14 +287,20 @@ impl GraphRenderer {
     fn get_node_color(&self, opcode_name: &str) -> &'static str {
         match opcode_name {
             "ConstInt" | "ConstFloat" | "ConstString" | "ConstBool" => "#e8f5e9",
-            "Add" | "Sub" | "Mul" | "Div" | "Mod" => "#fff3e0",
+            "Add" | "Sub" | "Mul" | "Div" | "Mod" |
+            "BitAnd" | "BitOr" | "BitXor" | "BitNot" | "Shl" | "Shr" |
+            "Sqrt" | "Pow" | "Abs" | "Floor" | "Ceil" | "Min" | "Max" | "Neg" => "#fff3e0",
             "Eq" | "Ne" | "Lt" | "Le" | "Gt" | "Ge" => "#e3f2fd",
             "And" | "Or" | "Not" | "Xor" => "#f3e5f5",
-            "Branch" => "#fff9c4",
+            "Branch" | "Loop" | "Exit" | "Assert" | "Try" | "Throw" | "Seq" => "#fff9c4",
             "Call" | "Return" => "#fce4ec",
-            "DefineFunc" | "CreateClosure" => "#e1f5fe",
-            "CreateArray" | "CreateMap" | "ArrayGet" | "ArraySet" | "MapGet" | "MapSet" => "#f1f8e9",
-            "Print" | "Read" => "#efebe9",
+            "DefineFunc" | "CreateClosure" | "LoadParam" => "#e1f5fe",
+            "CreateArray" | "CreateMap" | "ArrayGet" | "ArraySet" | "MapGet" | "MapSet" | "ArrayLen" |
+            "ArrayPush" | "ArrayPop" | "ArraySlice" | "ArrayConcat" |
+            "MapContains" | "MapKeys" | "MapValues" | "MapDelete" => "#f1f8e9",
+            "Cast" | "TypeOf" => "#ede7f6",
+            "StrConcat" | "StrLen" | "StrSlice" | "Concat" | "Len" | "ParseInt" | "ParseFloat" | "Format" => "#e0f2f1",
+            "Print" | "Read" | "Random" | "Now" | "Sleep" | "FileRead" | "FileWrite" | "GetEnv" | "HttpGet" => "#efebe9",
             _ => "#f5f5f5",
         }
     }
@@ -265,68 +308,137 @@ impl GraphRenderer {
     fn get_mermaid_style(&self, opcode_name: &str) -> &'static str {
         match opcode_name {
             "ConstInt" | "ConstFloat" | "ConstString" | "ConstBool" => "fill:#e8f5e9,stroke:#4caf50",
-            "Add" | "Sub" | "Mul" | "Div" | "Mod" => "fill:#fff3e0,stroke:#ff9800",
+            "Add" | "Sub" | "Mul" | "Div" | "Mod" |
+            "BitAnd" | "BitOr" | "BitXor" | "BitNot" | "Shl" | "Shr" |
+            "Sqrt" | "Pow" | "Abs" | "Floor" | "Ceil" | "Min" | "Max" | "Neg" => "fill:#fff3e0,stroke:#ff9800",
             "Eq" | "Ne" | "Lt" | "Le" | "Gt" | "Ge" => "fill:#e3f2fd,stroke:#2196f3",
             "And" | "Or" | "Not" | "Xor" => "fill:#f3e5f5,stroke:#9c27b0",
-            "Branch" => "fill:#fff9c4,stroke:#ffeb3b",
+            "Branch" | "Loop" | "Exit" | "Assert" | "Try" | "Throw" | "Seq" => "fill:#fff9c4,stroke:#ffeb3b",
             "Call" | "Return" => "fill:#fce4ec,stroke:#e91e63",
-            "DefineFunc" | "CreateClosure" => "fill:#e1f5fe,stroke:#00bcd4",
-            "CreateArray" | "CreateMap" | "ArrayGet" | "ArraySet" | "MapGet" | "MapSet" => "fill:#f1f8e9,stroke:#8bc34a",
-            "Print" | "Read" => "fill:#efebe9,stroke:#795548",
+            "DefineFunc" | "CreateClosure" | "LoadParam" => "fill:#e1f5fe,stroke:#00bcd4",
+            "CreateArray" | "CreateMap" | "ArrayGet" | "ArraySet" | "MapGet" | "MapSet" | "ArrayLen" |
+            "ArrayPush" | "ArrayPop" | "ArraySlice" | "ArrayConcat" |
+            "MapContains" | "MapKeys" | "MapValues" | "MapDelete" => "fill:#f1f8e9,stroke:#8bc34a",
+            "Cast" | "TypeOf" => "fill:#ede7f6,stroke:#673ab7",
+            "StrConcat" | "StrLen" | "StrSlice" | "Concat" | "Len" | "ParseInt" | "ParseFloat" | "Format" => "fill:#e0f2f1,stroke:#009688",
+            "Print" | "Read" | "Random" | "Now" | "Sleep" | "FileRead" | "FileWrite" | "GetEnv" | "HttpGet" => "fill:#efebe9,stroke:#795548",
             _ => "fill:#f5f5f5,stroke:#9e9e9e",
         }
     }
 
+    /// Level of each node: the longest path from a node with no consumers
+    /// (the entry point first) down its argument edges. Levels come from a
+    /// longest-path pass over a depth-first order; an edge back into a node
+    /// still being visited would close a cycle, so it adds no depth and the
+    /// pass terminates on cyclic graphs.
     fn calculate_node_levels(&self) -> HashMap<u32, usize> {
-        let mut levels: HashMap<u32, usize> = HashMap::new();
-        let mut visited: HashSet<u32> = HashSet::new();
-
-        // Start from entry point
-        let entry_point = self.program.metadata.entry_point;
-        if let Some(entry_node) = self.program.nodes.get(entry_point as usize) {
-            self.calculate_node_level(entry_node.result_id, 0, &mut levels, &mut visited);
-        }
-
-        // Process any unvisited nodes
-        for node in &self.program.nodes {
-            if !visited.contains(&node.result_id) {
-                self.calculate_node_level(node.result_id, 0, &mut levels, &mut visited);
+        let order = self.consumer_first_order();
+        let position: HashMap<u32, usize> = order.iter()
+            .enumerate()
+            .map(|(pos, id)| (*id, pos))
+            .collect();
+
+        let mut levels: HashMap<u32, usize> = order.iter().map(|id| (*id, 0)).collect();
+        for (pos, id) in order.iter().enumerate() {
+            let level = levels[id];
+            let node = match self.find_node_by_result_id(*id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for arg_id in node.dependencies() {
+                // Arguments ordered before their consumer are back edges
+                if position.get(&arg_id).is_some_and(|&arg_pos| arg_pos > pos) {
+                    let arg_level = levels.get_mut(&arg_id).unwrap();
+                    *arg_level = (*arg_level).max(level + 1);
+                }
             }
         }
 
         levels
     }
 
-    fn calculate_node_level(
-        &self,
-        node_id: u32,
-        current_level: usize,
-        levels: &mut HashMap<u32, usize>,
-        visited: &mut HashSet<u32>,
-    ) {
-        if visited.contains(&node_id) {
-            return;
-        }
+    /// Reverse depth-first postorder over argument edges, starting from the
+    /// entry point and then any nodes it does not reach. Every consumer
+    /// precedes its arguments except along back edges.
+    fn consumer_first_order(&self) -> Vec<u32> {
+        let mut visited: HashSet<u32> = HashSet::new();
+        let mut postorder = Vec::new();
 
-        visited.insert(node_id);
-        levels.insert(node_id, current_level);
+        let entry_point = self.program.metadata.entry_point;
+        let roots = std::iter::once(entry_point)
+            .chain(self.program.nodes.iter().map(|n| n.result_id));
 
-        if let Some(node) = self.find_node_by_result_id(node_id) {
-            for i in 0..node.arg_count as usize {
-                let arg_id = node.args[i];
-                if arg_id != 0 {
-                    if let Some(arg_node) = self.find_node_by_result_id(arg_id) {
-                        let new_level = current_level + 1;
-                        if let Some(&existing_level) = levels.get(&arg_id) {
-                            if new_level > existing_level {
-                                levels.insert(arg_id, new_level);
+        for root in roots {
+            if self.find_node_by_result_id(root).is_none() || !visited.insert(root) {
+                continue;
+            }
+            // (node id, its arguments, next argument to visit)
+            let mut stack = vec![(root, self.find_node_by_result_id(root).unwrap().dependencies(), 0)];
+            while let Some((id, args, next)) = stack.last_mut() {
+                match args.get(*next) {
+                    Some(&arg_id) => {
+                        *next += 1;
+                        if let Some(arg_node) = self.find_node_by_result_id(arg_id) {
+                            if visited.insert(arg_id) {
+                                stack.push((arg_id, arg_node.dependencies(), 0));
                             }
-                        } else {
-                            self.calculate_node_level(arg_id, new_level, levels, visited);
                         }
                     }
+                    None => {
+                        postorder.push(*id);
+                        stack.pop();
+                    }
                 }
             }
         }
+
+        postorder.reverse();
+        postorder
+    }
+}
+
+/// Escape text for SVG/XML text and attribute content.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape text for a double-quoted DOT label. Newlines become `\n` line
+/// breaks; record-shape delimiters are backslash-escaped.
+pub fn escape_dot_label(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            '<' | '>' | '{' | '}' | '|' | '[' | ']' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
     }
-}
\ No newline at end of file
+    escaped
+}
+
+/// Escape text for a quoted Mermaid node label using Mermaid entity codes.
+/// Newlines become `<br/>` line breaks.
+pub fn escape_mermaid_label(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '\n' => escaped.push_str("<br/>"),
+            '\r' => {}
+            '\\' | '<' | '>' | '{' | '}' | '|' | '[' | ']' | '#' => {
+                escaped.push_str(&format!("#{};", c as u32));
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,4 +1,4 @@
-use crate::core::{Program, Node, OpCode, ConstantPool};
+use crate::core::{Program, Node, NodeDescriber, NodeFlag, OpCode, ConstantPool};
 use std::collections::HashMap;
 
 pub struct TextRenderer {
@@ -35,6 +35,9 @@ impl TextRenderer {
             .unwrap_or_else(|_| format!("Unknown({})", node.opcode));
 
         let mut result = format!("{}Node {} [{}]", " ".repeat(indent), node.result_id, opcode);
+        if node.has_flag(NodeFlag::Volatile) {
+            result.push_str(" (volatile)");
+        }
 
         // Add node description based on opcode
         let description = self.describe_node(&node);
@@ -64,49 +67,53 @@ impl TextRenderer {
     }
 
     fn describe_node(&self, node: &Node) -> String {
-        match OpCode::try_from(node.opcode) {
-            Ok(OpCode::ConstInt) => {
-                if let Some(val) = self.program.constants.get_int(node.args[0]) {
-                    format!("{}", val)
-                } else {
-                    "Invalid constant".to_string()
-                }
-            }
-            Ok(OpCode::ConstFloat) => {
-                if let Some(val) = self.program.constants.get_float(node.args[0]) {
-                    format!("{}", val)
-                } else {
-                    "Invalid constant".to_string()
-                }
-            }
-            Ok(OpCode::ConstString) => {
-                if let Some(val) = self.program.constants.get_string(node.args[0]) {
-                    format!("\"{}\"", val)
-                } else {
-                    "Invalid constant".to_string()
+        let description = NodeDescriber::new(&self.program).describe(node);
+        description.constant_preview.unwrap_or(description.detail)
+    }
+
+    /// One line per node in program order, e.g.
+    /// `#3 Add args=[#1 #2] ; 10, 20`. Constant arguments are shown by
+    /// value, other arguments by the node producing them. The entry point
+    /// line starts with `->`.
+    pub fn render_disassembly(&self) -> String {
+        let describer = NodeDescriber::new(&self.program);
+        let entry_point = self.program.metadata.entry_point;
+        let mut output = String::new();
+
+        for node in &self.program.nodes {
+            let description = describer.describe(node);
+            let marker = if node.result_id == entry_point { "->" } else { "  " };
+            let args = &node.args[..(node.arg_count as usize).min(3)];
+
+            let (arg_list, annotation) = match description.constant_preview {
+                // Const* args are constant pool indices, not node references
+                Some(preview) => {
+                    let indices: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                    (indices.join(" "), preview)
                 }
-            }
-            Ok(OpCode::ConstBool) => {
-                if let Some(val) = self.program.constants.get_bool(node.args[0]) {
-                    format!("{}", val)
-                } else {
-                    "Invalid constant".to_string()
+                None => {
+                    let refs: Vec<String> = args.iter().map(|a| format!("#{}", a)).collect();
+                    let values: Vec<String> = args.iter().map(|&arg_id| {
+                        if arg_id == 0 {
+                            return "nil".to_string();
+                        }
+                        match describer.describe_id(arg_id).and_then(|d| d.constant_preview) {
+                            Some(preview) => preview,
+                            None => format!("-> produced by #{}", arg_id),
+                        }
+                    }).collect();
+                    (refs.join(" "), values.join(", "))
                 }
+            };
+
+            output.push_str(&format!("{} #{} {} args=[{}]", marker, node.result_id, description.short_label, arg_list));
+            if !annotation.is_empty() {
+                output.push_str(&format!(" ; {}", annotation));
             }
-            Ok(OpCode::Add) => "Addition".to_string(),
-            Ok(OpCode::Sub) => "Subtraction".to_string(),
-            Ok(OpCode::Mul) => "Multiplication".to_string(),
-            Ok(OpCode::Div) => "Division".to_string(),
-            Ok(OpCode::Eq) => "Equality check".to_string(),
-            Ok(OpCode::Lt) => "Less than".to_string(),
-            Ok(OpCode::Branch) => "Conditional branch".to_string(),
-            Ok(OpCode::Call) => "Function call".to_string(),
-            Ok(OpCode::DefineFunc) => "Function definition".to_string(),
-            Ok(OpCode::CreateArray) => "Array creation".to_string(),
-            Ok(OpCode::CreateMap) => "Map creation".to_string(),
-            Ok(OpCode::Print) => "Print output".to_string(),
-            _ => String::new(),
+            output.push('\n');
         }
+
+        output
     }
 
     pub fn render_summary(&self) -> String {